@@ -26,6 +26,7 @@ fn open_output_file() -> Result<File, Box<dyn std::error::Error>> {
     let output_file = OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(output_path)?;
     Ok(output_file)
 }
@@ -1,14 +1,18 @@
+#[cfg(feature = "buddy-alloc")]
 use buddy_alloc::{BuddyAllocParam, FastAllocParam, NonThreadsafeAlloc};
 
 // These values can be tuned
-const FAST_HEAP_SIZE: usize = 4 * 1024; // 4 KB
-const HEAP_SIZE: usize = 16 * 1024; // 16 KB
-const LEAF_SIZE: usize = 16;
+pub const FAST_HEAP_SIZE: usize = 4 * 1024; // 4 KB
+pub const HEAP_SIZE: usize = 16 * 1024; // 16 KB
+pub const LEAF_SIZE: usize = 16;
 
+#[cfg(feature = "buddy-alloc")]
 static mut FAST_HEAP: *mut [u8; FAST_HEAP_SIZE] =
     (0x10000 - HEAP_SIZE - FAST_HEAP_SIZE) as *mut [u8; FAST_HEAP_SIZE];
+#[cfg(feature = "buddy-alloc")]
 static mut HEAP: *mut [u8; HEAP_SIZE] = (0x10000 - HEAP_SIZE) as *mut [u8; HEAP_SIZE];
 
+#[cfg(feature = "buddy-alloc")]
 #[global_allocator]
 static ALLOC: NonThreadsafeAlloc = unsafe {
     let fast_param = FastAllocParam::new(FAST_HEAP as *mut u8, FAST_HEAP_SIZE);
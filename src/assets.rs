@@ -16,3 +16,10 @@ pub const FONT_SPRITE: Sprite = Sprite::new(
     sprites::FONT_FLAGS,
     &sprites::FONT,
 );
+
+pub const SMALL_FONT_SPRITE: Sprite = Sprite::new(
+    sprites::SMALLFONT_WIDTH,
+    sprites::SMALLFONT_HEIGHT,
+    sprites::SMALLFONT_FLAGS,
+    &sprites::SMALLFONT,
+);
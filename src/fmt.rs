@@ -6,6 +6,8 @@
 
 //! Text formatting primitives
 
+use crate::time::FRAMES_PER_SECOND;
+
 pub fn pad_text(text: &str, length: usize) -> String {
     let mut text = text.to_owned();
     for _ in text.len()..length {
@@ -22,6 +24,9 @@ pub fn format_number(mut n: u32, length: Option<usize>) -> String {
         n /= 10;
         digits.push(digit);
     }
+    if digits.is_empty() {
+        digits.push(0);
+    }
     digits.reverse();
 
     let mut output = String::with_capacity(capacity);
@@ -29,12 +34,35 @@ pub fn format_number(mut n: u32, length: Option<usize>) -> String {
         output.push(' ');
     }
     for digit in &digits {
-        let c = char::from_u32(*digit as u32 + '0' as u32).unwrap();
+        let c = char::from_u32(*digit + '0' as u32).unwrap();
         output.push(c);
     }
     output
 }
 
+/// Format a time in frames as seconds with tenths
+///
+/// The length applies to the whole seconds, which are padded as in
+/// `format_number`.
+pub fn format_time(frames: u32, length: Option<usize>) -> String {
+    let seconds = frames / FRAMES_PER_SECOND;
+    let tenths = frames % FRAMES_PER_SECOND * 10 / FRAMES_PER_SECOND;
+    let mut output = format_number(seconds, length);
+    output.push('.');
+    output.push(char::from_u32(tenths + '0' as u32).unwrap());
+    output
+}
+
+/// Format a number of hundredths as a decimal number
+pub fn format_hundredths(n: u32) -> String {
+    let fraction = n % 100;
+    let mut output = format_number(n / 100, None);
+    output.push('.');
+    output.push(char::from_u32(fraction / 10 + '0' as u32).unwrap());
+    output.push(char::from_u32(fraction % 10 + '0' as u32).unwrap());
+    output
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -70,4 +98,23 @@ mod tests {
         let expected = "  100";
         assert_eq!(text, expected);
     }
+
+    #[test]
+    fn test_format_number_0() {
+        assert_eq!(format_number(0, Some(3)), "  0");
+        assert_eq!(format_number(0, None), "0");
+    }
+
+    #[test]
+    fn test_format_hundredths() {
+        assert_eq!(format_hundredths(175), "1.75");
+        assert_eq!(format_hundredths(5), "0.05");
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(30 * 60 + 31, Some(4)), "  30.5");
+        assert_eq!(format_time(59, Some(3)), "  0.9");
+        assert_eq!(format_time(0, None), "0.0");
+    }
 }
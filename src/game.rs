@@ -6,13 +6,46 @@
 
 //! Game components
 
+mod cursor;
+pub use cursor::Cursor;
+
+mod date;
+pub use date::Date;
+
 mod difficulty;
 pub use difficulty::Difficulty;
 
+mod generator;
+pub use generator::{FirstClick, Generator, LayoutSearch};
+
 mod highscores;
-pub use highscores::HighScores;
+pub use highscores::{HighScore, HighScores, LeaderboardEntry, LEADERBOARD_SIZE};
 
 mod r#loop;
 
 mod map;
-pub use map::Map;
+pub use map::{Map, MAX_HEIGHT, MAX_WIDTH};
+
+mod performance;
+pub use performance::{Clicks, Performance};
+
+mod puzzle;
+pub use puzzle::{Puzzle, PuzzleProgress, PUZZLES};
+
+mod replay;
+pub use replay::{Action, Move, Replay};
+
+mod save;
+pub use save::Save;
+
+mod seed;
+pub use seed::{Seed, CODE_ALPHABET, CODE_LENGTH};
+
+mod settings;
+pub use settings::Settings;
+
+mod statistics;
+pub use statistics::{DifficultyStatistics, Statistics};
+
+mod solver;
+pub use solver::{deduce, solve, solve_step, Deductions};
@@ -0,0 +1,84 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tile cursor
+
+use crate::graphics::{draw_rect, DrawColors};
+use crate::input::InputFrame;
+use crate::wasm4::{BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP};
+
+use super::Map;
+
+/// A cursor pointing at a map tile
+///
+/// The cursor is moved with the gamepad directional buttons.
+/// It is hidden until the gamepad is used, so that it does not get in the way
+/// when playing with the mouse.
+#[derive(Clone)]
+pub struct Cursor {
+    position: (usize, usize),
+    visible: bool,
+}
+
+impl Cursor {
+    /// Create a new hidden cursor at the centre of a map
+    pub fn new(map: &Map) -> Self {
+        Self {
+            position: (map.width() / 2, map.height() / 2),
+            visible: false,
+        }
+    }
+
+    /// Return the coordinates of the tile under the cursor
+    pub fn position(&self) -> (usize, usize) {
+        self.position
+    }
+
+    /// Hide the cursor until the gamepad is used again
+    pub fn hide(&mut self) {
+        self.visible = false;
+    }
+
+    /// Move the cursor following the gamepad directional buttons
+    ///
+    /// Any gamepad press shows the cursor, and the map is scrolled to keep
+    /// it inside the viewport.
+    pub fn update(&mut self, input: &InputFrame, map: &mut Map) {
+        if !input.any_pressed() {
+            return;
+        }
+        self.visible = true;
+
+        let (mut x, mut y) = self.position;
+        if input.pressed(BUTTON_LEFT) && x > 0 {
+            x -= 1;
+        }
+        if input.pressed(BUTTON_RIGHT) && x + 1 < map.width() {
+            x += 1;
+        }
+        if input.pressed(BUTTON_UP) && y > 0 {
+            y -= 1;
+        }
+        if input.pressed(BUTTON_DOWN) && y + 1 < map.height() {
+            y += 1;
+        }
+        self.position = (x, y);
+
+        map.scroll_to_tile(x, y);
+    }
+
+    /// Draw the cursor as a frame around its tile
+    pub fn draw(&self, map: &Map) {
+        if !self.visible {
+            return;
+        }
+        let (x, y) = self.position;
+        if let Some((screen_x, screen_y)) = map.tile_to_screen(x, y) {
+            DrawColors.set(0x40);
+            draw_rect(screen_x, screen_y, map.tile_size(), map.tile_size());
+        }
+    }
+}
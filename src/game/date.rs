@@ -0,0 +1,226 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Calendar dates
+
+use crate::fmt::format_number;
+
+use super::Seed;
+
+/// First year of supported dates
+pub const MIN_YEAR: u16 = 2000;
+
+/// Last year of supported dates
+pub const MAX_YEAR: u16 = 2099;
+
+/// A calendar date
+///
+/// WASM-4 has no clock, so dates are entered by the player.
+/// Dates are stored as the number of days since the first day of
+/// [`MIN_YEAR`].
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Date {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    /// Create a new date
+    ///
+    /// Return `None` if the date does not exist or is not supported.
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        let is_valid = (MIN_YEAR..=MAX_YEAR).contains(&year)
+            && (1..=12).contains(&month)
+            && 1 <= day
+            && day <= days_in_month(year, month);
+        if is_valid {
+            Some(Self { year, month, day })
+        } else {
+            None
+        }
+    }
+
+    /// Create a date from the number of days since the first supported day
+    pub fn from_days(days: u16) -> Self {
+        let mut days = days;
+        let mut year = MIN_YEAR;
+        while year < MAX_YEAR && days >= days_in_year(year) {
+            days -= days_in_year(year);
+            year += 1;
+        }
+        let mut month = 1;
+        while month < 12 && days >= days_in_month(year, month) as u16 {
+            days -= days_in_month(year, month) as u16;
+            month += 1;
+        }
+        let day = (days as u8 + 1).min(days_in_month(year, month));
+        Self { year, month, day }
+    }
+
+    /// Return the number of days since the first supported day
+    pub fn days(&self) -> u16 {
+        let years: u16 = (MIN_YEAR..self.year).map(days_in_year).sum();
+        let months: u16 = (1..self.month)
+            .map(|month| days_in_month(self.year, month) as u16)
+            .sum();
+        years + months + self.day as u16 - 1
+    }
+
+    /// Return the year
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// Return the month, starting from 1
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// Return the day of the month, starting from 1
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// Add a number of years
+    ///
+    /// The day is clamped to the last day of the month, and the year is
+    /// clamped to the supported range.
+    pub fn add_years(&self, step: i32) -> Self {
+        let year = clamp(self.year as i32 + step, MIN_YEAR, MAX_YEAR);
+        let day = self.day.min(days_in_month(year, self.month));
+        Self { year, day, ..*self }
+    }
+
+    /// Add a number of months, wrapping around within the year
+    ///
+    /// The day is clamped to the last day of the month.
+    pub fn add_months(&self, step: i32) -> Self {
+        let month = (self.month as i32 - 1 + step).rem_euclid(12) as u8 + 1;
+        let day = self.day.min(days_in_month(self.year, month));
+        Self {
+            month,
+            day,
+            ..*self
+        }
+    }
+
+    /// Add a number of days
+    ///
+    /// The result is clamped to the supported range.
+    pub fn add_days(&self, step: i32) -> Self {
+        let last = Self::new(MAX_YEAR, 12, 31)
+            .map(|date| date.days())
+            .unwrap_or(0);
+        Self::from_days(clamp(self.days() as i32 + step, 0, last))
+    }
+
+    /// Return the seed of the board of this day
+    ///
+    /// The days count is scrambled, so that boards of consecutive days have
+    /// unrelated seeds.
+    pub fn seed(&self) -> Seed {
+        let mut value = (self.days() as u32).wrapping_mul(0x9e37_79b9);
+        value ^= value >> 16;
+        value = value.wrapping_mul(0x85eb_ca6b);
+        value ^= value >> 13;
+        Seed::new(value)
+    }
+
+    /// Format the date as `YYYY-MM-DD`
+    pub fn format(&self) -> String {
+        format_number(self.year.into(), Some(4))
+            + "-"
+            + &format_padded(self.month)
+            + "-"
+            + &format_padded(self.day)
+    }
+}
+
+fn format_padded(value: u8) -> String {
+    if value < 10 {
+        "0".to_owned() + &format_number(value.into(), None)
+    } else {
+        format_number(value.into(), None)
+    }
+}
+
+fn clamp(value: i32, min: u16, max: u16) -> u16 {
+    value.max(min as i32).min(max as i32) as u16
+}
+
+fn is_leap_year(year: u16) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_year(year: u16) -> u16 {
+    if is_leap_year(year) {
+        366
+    } else {
+        365
+    }
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_round_trip() {
+        let last = Date::new(MAX_YEAR, 12, 31).unwrap().days();
+        for days in 0..=last {
+            assert_eq!(Date::from_days(days).days(), days);
+        }
+    }
+
+    #[test]
+    fn days_examples() {
+        assert_eq!(Date::new(2000, 1, 1).unwrap().days(), 0);
+        assert_eq!(Date::new(2000, 3, 1).unwrap().days(), 60);
+        assert_eq!(Date::new(2021, 10, 18).unwrap().days(), 7961);
+    }
+
+    #[test]
+    fn invalid_dates() {
+        assert!(Date::new(2021, 2, 29).is_none());
+        assert!(Date::new(2024, 2, 29).is_some());
+        assert!(Date::new(2021, 13, 1).is_none());
+        assert!(Date::new(2021, 4, 31).is_none());
+        assert!(Date::new(1999, 12, 31).is_none());
+    }
+
+    #[test]
+    fn add_clamps_day() {
+        let date = Date::new(2024, 1, 31).unwrap();
+        assert_eq!(date.add_months(1), Date::new(2024, 2, 29).unwrap());
+        assert_eq!(date.add_months(-1), Date::new(2024, 12, 31).unwrap());
+        let date = Date::new(2024, 2, 29).unwrap();
+        assert_eq!(date.add_years(1), Date::new(2025, 2, 28).unwrap());
+        assert_eq!(date.add_days(1), Date::new(2024, 3, 1).unwrap());
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(Date::new(2021, 9, 5).unwrap().format(), "2021-09-05");
+    }
+
+    /// Check that boards of the day never change across versions
+    #[test]
+    fn seeds_are_stable() {
+        let date = Date::new(2021, 10, 18).unwrap();
+        assert_eq!(date.seed().code(), "BQ3C6H");
+        assert_ne!(date.seed(), date.add_days(1).seed());
+    }
+}
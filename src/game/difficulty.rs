@@ -6,8 +6,18 @@
 
 //! Difficulty structures and functions
 
+/// Width of maps for predefined difficulties
+const DEFAULT_WIDTH: usize = 16;
+
+/// Height of maps for predefined difficulties
+const DEFAULT_HEIGHT: usize = 14;
+
+/// Number of tiles around the first uncovered tile that might be kept free
+/// from mines, i.e. the tile itself and its neighbours
+const SAFE_START_SIZE: usize = 9;
+
 /// Represent the game difficulty
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Difficulty {
     /// An easy game with 10 mines
     Easy,
@@ -17,6 +27,18 @@ pub enum Difficulty {
 
     /// A hard game with 50 mines
     Hard,
+
+    /// A game with user-chosen map size and mines count
+    Custom {
+        /// Map width
+        width: usize,
+
+        /// Map height
+        height: usize,
+
+        /// Mines count
+        mines: usize,
+    },
 }
 
 impl Difficulty {
@@ -26,8 +48,33 @@ impl Difficulty {
             Difficulty::Easy => 10,
             Difficulty::Medium => 30,
             Difficulty::Hard => 50,
+            Difficulty::Custom { mines, .. } => *mines,
+        }
+    }
+
+    /// Return the map width for this difficulty
+    pub fn width(&self) -> usize {
+        match self {
+            Difficulty::Custom { width, .. } => *width,
+            _ => DEFAULT_WIDTH,
+        }
+    }
+
+    /// Return the map height for this difficulty
+    pub fn height(&self) -> usize {
+        match self {
+            Difficulty::Custom { height, .. } => *height,
+            _ => DEFAULT_HEIGHT,
         }
     }
+
+    /// Return the maximal mines count for a map of given size
+    ///
+    /// The mines count must leave room for a safe start, i.e. the first
+    /// uncovered tile and all its neighbours must be free from mines.
+    pub fn max_mines_count(width: usize, height: usize) -> usize {
+        (width * height).saturating_sub(SAFE_START_SIZE)
+    }
 }
 
 impl AsRef<str> for Difficulty {
@@ -36,6 +83,7 @@ impl AsRef<str> for Difficulty {
             Difficulty::Easy => "Easy",
             Difficulty::Medium => "Medium",
             Difficulty::Hard => "Hard",
+            Difficulty::Custom { .. } => "Custom",
         }
     }
 }
@@ -0,0 +1,578 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Mine layout generation
+
+use rand_core::RngCore;
+
+use crate::debug;
+use crate::graphics::Tile;
+
+use super::{solve_step, Difficulty, Map};
+
+/// Maximal number of mines moved before giving up on a no-guess layout
+const MAX_REPAIRS: usize = 100;
+
+/// Represent the strategy used to place mines
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Generator {
+    /// Mines are placed randomly, around the first uncovered tile according
+    /// to the first click policy
+    Random,
+
+    /// Mines are placed so that the map can be cleared without guessing
+    ///
+    /// The first uncovered tile and its neighbours never contain mines,
+    /// regardless of the first click policy, and mines are moved until the
+    /// map can be cleared by logic alone.
+    NoGuess,
+}
+
+/// Represent the protection given to the first uncovered tile
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FirstClick {
+    /// The first uncovered tile never contains a mine
+    Safe,
+
+    /// The first uncovered tile and its neighbours never contain mines, so
+    /// that the first click always opens a region
+    ///
+    /// When there are too many mines to leave the neighbours free, only the
+    /// first uncovered tile is protected.
+    Opening,
+
+    /// The first uncovered tile can contain a mine
+    Classic,
+}
+
+impl Default for FirstClick {
+    fn default() -> Self {
+        FirstClick::Safe
+    }
+}
+
+impl AsRef<str> for FirstClick {
+    fn as_ref(&self) -> &str {
+        match self {
+            FirstClick::Safe => "Safe",
+            FirstClick::Opening => "Opening",
+            FirstClick::Classic => "Classic",
+        }
+    }
+}
+
+impl Generator {
+    /// Check whether this generator can place a given number of mines on a
+    /// map of given size
+    ///
+    /// No-guess layouts are only available up to the density of mines of hard
+    /// games, since denser layouts take too long to find.
+    pub fn is_available(&self, width: usize, height: usize, mines_count: usize) -> bool {
+        match self {
+            Generator::Random => true,
+            Generator::NoGuess => {
+                let hard_area = Difficulty::Hard.width() * Difficulty::Hard.height();
+                mines_count * hard_area <= Difficulty::Hard.mines_count() * width * height
+            }
+        }
+    }
+
+    /// Generate a mine layout
+    ///
+    /// The tile at `start` is the first one uncovered by the player, and it
+    /// is protected from mines according to `first_click`.
+    /// Return `None` if the generator is not available for the map, or if no
+    /// layout could be found.
+    pub fn generate<R>(
+        &self,
+        generator: &mut R,
+        width: usize,
+        height: usize,
+        mines_count: usize,
+        start: (usize, usize),
+        first_click: FirstClick,
+    ) -> Option<Vec<(usize, usize)>>
+    where
+        R: RngCore,
+    {
+        let mut search = self.search(generator, width, height, mines_count, start, first_click);
+        while !search.step() {}
+        search.into_mines()
+    }
+
+    /// Start searching a mine layout
+    ///
+    /// The search is the same as in [`Generator::generate`], but it can be
+    /// spread over several frames with [`LayoutSearch::step`].
+    pub fn search<R>(
+        &self,
+        mut generator: R,
+        width: usize,
+        height: usize,
+        mines_count: usize,
+        start: (usize, usize),
+        first_click: FirstClick,
+    ) -> LayoutSearch<R>
+    where
+        R: RngCore,
+    {
+        let is_start = |x: usize, y: usize| (x, y) == start;
+        let is_near_start = |x: usize, y: usize| is_near(start, x, y);
+        let has_room_for_opening = mines_count + count_near(width, height, start) <= width * height;
+
+        let mines = match (self, first_click) {
+            (Generator::Random, FirstClick::Safe) => Some(place_mines_randomly(
+                &mut generator,
+                width,
+                height,
+                mines_count,
+                is_start,
+            )),
+            (Generator::Random, FirstClick::Opening) => {
+                if has_room_for_opening {
+                    Some(place_mines_randomly(
+                        &mut generator,
+                        width,
+                        height,
+                        mines_count,
+                        is_near_start,
+                    ))
+                } else {
+                    debug!("Too many mines for an opening, protecting the first tile only");
+                    Some(place_mines_randomly(
+                        &mut generator,
+                        width,
+                        height,
+                        mines_count,
+                        is_start,
+                    ))
+                }
+            }
+            (Generator::Random, FirstClick::Classic) => Some(place_mines_randomly(
+                &mut generator,
+                width,
+                height,
+                mines_count,
+                |_, _| false,
+            )),
+            (Generator::NoGuess, _) => {
+                if !self.is_available(width, height, mines_count) || !has_room_for_opening {
+                    debug!("Too many mines for a no-guess layout");
+                    None
+                } else {
+                    Some(place_mines_randomly(
+                        &mut generator,
+                        width,
+                        height,
+                        mines_count,
+                        is_near_start,
+                    ))
+                }
+            }
+        };
+
+        LayoutSearch {
+            finished: mines.is_none() || *self == Generator::Random,
+            generator,
+            width,
+            height,
+            start,
+            mines,
+            map: None,
+            repairs: 0,
+            steps: 0,
+        }
+    }
+}
+
+/// A search of a mine layout, which can be spread over several frames
+///
+/// Random layouts are found right away, while no-guess layouts are repaired
+/// until the solver can clear them.
+/// Solving a large map takes longer than a frame, so the map being solved is
+/// kept between steps.
+#[derive(Clone)]
+pub struct LayoutSearch<R> {
+    generator: R,
+    width: usize,
+    height: usize,
+    start: (usize, usize),
+    mines: Option<Vec<(usize, usize)>>,
+    map: Option<Map>,
+    repairs: usize,
+    steps: usize,
+    finished: bool,
+}
+
+impl<R> LayoutSearch<R>
+where
+    R: RngCore,
+{
+    /// Run the solver once on the current layout, and repair the layout if
+    /// the solver is stuck
+    ///
+    /// Return `true` when the search is finished.
+    pub fn step(&mut self) -> bool {
+        if self.finished {
+            return true;
+        }
+        let mines = match &mut self.mines {
+            Some(mines) => mines,
+            None => return true,
+        };
+        self.steps += 1;
+
+        let start = self.start;
+        let mut map = match self.map.take() {
+            Some(map) => map,
+            None => {
+                let mut map = Map::new(self.width, self.height, 1, (0, 0)).with_mines(mines);
+                map.uncover_tile(start.0, start.1);
+                map
+            }
+        };
+        match solve_step(&mut map) {
+            None => {
+                self.map = Some(map);
+                return false;
+            }
+            Some(true) => {
+                debug!("Found a no-guess layout after {} repairs", self.repairs);
+                self.finished = true;
+                return true;
+            }
+            Some(false) => {}
+        }
+
+        // Instead of trying new layouts until one can be cleared, the mines
+        // the solver gets stuck on are moved away, since far fewer attempts
+        // are needed that way.
+        self.repairs += 1;
+        let is_near_start = |x: usize, y: usize| is_near(start, x, y);
+        if self.repairs == MAX_REPAIRS
+            || !move_undecided_mine(&mut self.generator, &map, mines, is_near_start)
+        {
+            debug!("Could not find a no-guess layout");
+            self.mines = None;
+            self.finished = true;
+        }
+        self.finished
+    }
+
+    /// Return the number of steps made so far
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Return the mine layout found by a finished search
+    ///
+    /// Return `None` if no layout could be found.
+    pub fn into_mines(self) -> Option<Vec<(usize, usize)>> {
+        self.mines
+    }
+}
+
+impl AsRef<str> for Generator {
+    fn as_ref(&self) -> &str {
+        match self {
+            Generator::Random => "Random",
+            Generator::NoGuess => "No guessing",
+        }
+    }
+}
+
+/// Check whether a tile is the start tile or one of its neighbours
+fn is_near(start: (usize, usize), x: usize, y: usize) -> bool {
+    (x as i32 - start.0 as i32).abs() <= 1 && (y as i32 - start.1 as i32).abs() <= 1
+}
+
+/// Count the tiles that are the start tile or one of its neighbours
+fn count_near(width: usize, height: usize, start: (usize, usize)) -> usize {
+    (0..width)
+        .flat_map(|x| (0..height).map(move |y| (x, y)))
+        .filter(|(x, y)| is_near(start, *x, *y))
+        .count()
+}
+
+fn place_mines_randomly<R, F>(
+    generator: &mut R,
+    width: usize,
+    height: usize,
+    mines_count: usize,
+    is_forbidden: F,
+) -> Vec<(usize, usize)>
+where
+    R: RngCore,
+    F: Fn(usize, usize) -> bool,
+{
+    let mut mines = Vec::with_capacity(mines_count);
+    for _ in 0..mines_count {
+        let mut x = generator.next_u32() as usize % width;
+        let mut y = generator.next_u32() as usize % height;
+        while mines.contains(&(x, y)) || is_forbidden(x, y) {
+            x = generator.next_u32() as usize % width;
+            y = generator.next_u32() as usize % height;
+        }
+        mines.push((x, y));
+    }
+    mines
+}
+
+/// Move a mine that stops the solver to a tile it has not reached
+///
+/// The moved mine is one of the covered mines next to the uncovered region,
+/// whose content could not be deduced.
+/// If the solver is stuck behind deduced mines instead, one of those is moved.
+/// The mine goes preferably to a covered tile away from the uncovered region,
+/// so that the deductions made so far remain valid as much as possible.
+/// Return `false` if no mine can be moved.
+fn move_undecided_mine<R, F>(
+    generator: &mut R,
+    map: &Map,
+    mines: &mut [(usize, usize)],
+    is_forbidden: F,
+) -> bool
+where
+    R: RngCore,
+    F: Fn(usize, usize) -> bool,
+{
+    let is_next_to = |x: usize, y: usize, tile: Tile| {
+        map.neighbours(x, y)
+            .into_iter()
+            .any(|(nx, ny)| *map.tile(nx, ny) == tile)
+    };
+
+    // Candidates and targets are counted and then found again, instead of
+    // being collected, since memory is scarce on large maps.
+    let is_stuck = |(x, y): (usize, usize)| {
+        *map.tile(x, y) == Tile::Covered && is_next_to(x, y, Tile::Uncovered)
+    };
+    let is_blocking = |(x, y): (usize, usize)| {
+        *map.tile(x, y) == Tile::Flagged && is_next_to(x, y, Tile::Covered)
+    };
+    let is_candidate: &dyn Fn((usize, usize)) -> bool = if mines.iter().any(|m| is_stuck(*m)) {
+        &is_stuck
+    } else {
+        &is_blocking
+    };
+
+    let is_free = |x: usize, y: usize| !map.is_mine(x, y) && !is_forbidden(x, y);
+    let is_away = |(x, y): (usize, usize)| {
+        is_free(x, y) && *map.tile(x, y) == Tile::Covered && !is_next_to(x, y, Tile::Uncovered)
+    };
+    let is_anywhere = |(x, y): (usize, usize)| is_free(x, y);
+    let all_tiles = (0..map.height()).flat_map(|y| (0..map.width()).map(move |x| (x, y)));
+    let is_target: &dyn Fn((usize, usize)) -> bool = if all_tiles.clone().any(is_away) {
+        &is_away
+    } else {
+        &is_anywhere
+    };
+
+    let candidates_count = mines.iter().filter(|m| is_candidate(**m)).count();
+    let targets_count = all_tiles.clone().filter(|t| is_target(*t)).count();
+    if candidates_count == 0 || targets_count == 0 {
+        return false;
+    }
+    let candidate = (0..mines.len())
+        .filter(|i| is_candidate(mines[*i]))
+        .nth(generator.next_u32() as usize % candidates_count);
+    let target = all_tiles
+        .filter(|t| is_target(*t))
+        .nth(generator.next_u32() as usize % targets_count);
+    match (candidate, target) {
+        (Some(candidate), Some(target)) => {
+            mines[candidate] = target;
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use crate::game::solve;
+
+    const WIDTH: usize = 16;
+    const HEIGHT: usize = 14;
+
+    fn check_no_guess_layouts(width: usize, height: usize, mines_count: usize, count: usize) {
+        let mut generator = XorShiftRng::seed_from_u64(42);
+        for i in 0..count {
+            let start = (i % width, (i * 7) % height);
+            // The first click policy is ignored
+            let mines = Generator::NoGuess
+                .generate(
+                    &mut generator,
+                    width,
+                    height,
+                    mines_count,
+                    start,
+                    FirstClick::Classic,
+                )
+                .unwrap();
+            assert_eq!(mines.len(), mines_count);
+            assert!(mines.iter().all(|(x, y)| !is_near(start, *x, *y)));
+
+            // Moved mines never land on other mines
+            let map = Map::new(width, height, 10, (0, 0)).with_mines(&mines);
+            assert_eq!(map.mines_count(), mines_count);
+
+            let mut map = map;
+            map.uncover_tile(start.0, start.1);
+            assert!(solve(&mut map));
+        }
+    }
+
+    #[test]
+    fn no_guess_easy_is_solvable() {
+        let mines_count = Difficulty::Easy.mines_count();
+        check_no_guess_layouts(WIDTH, HEIGHT, mines_count, 50);
+    }
+
+    #[test]
+    fn no_guess_medium_is_solvable() {
+        let mines_count = Difficulty::Medium.mines_count();
+        check_no_guess_layouts(WIDTH, HEIGHT, mines_count, 50);
+    }
+
+    #[test]
+    fn no_guess_hard_is_solvable() {
+        let mines_count = Difficulty::Hard.mines_count();
+        check_no_guess_layouts(WIDTH, HEIGHT, mines_count, 20);
+    }
+
+    #[test]
+    fn no_guess_custom_is_solvable() {
+        check_no_guess_layouts(24, 9, 48, 10);
+        check_no_guess_layouts(4, 4, 3, 20);
+    }
+
+    #[test]
+    #[cfg(not(feature = "buddy-alloc"))]
+    fn largest_no_guess_layout_fits_console_heap() {
+        use crate::game::{MAX_HEIGHT, MAX_WIDTH};
+        use crate::harness::with_console_heap;
+
+        let found = with_console_heap(|| {
+            let mut generator = XorShiftRng::seed_from_u64(42);
+            Generator::NoGuess
+                .generate(
+                    &mut generator,
+                    MAX_WIDTH,
+                    MAX_HEIGHT,
+                    211,
+                    (MAX_WIDTH / 2, MAX_HEIGHT / 2),
+                    FirstClick::Safe,
+                )
+                .is_some()
+        });
+        assert!(found);
+    }
+
+    #[test]
+    fn no_guess_is_not_available_for_dense_maps() {
+        let hard = Difficulty::Hard.mines_count();
+        assert!(Generator::NoGuess.is_available(WIDTH, HEIGHT, hard));
+        assert!(!Generator::NoGuess.is_available(WIDTH, HEIGHT, hard + 1));
+        assert!(Generator::Random.is_available(WIDTH, HEIGHT, hard + 1));
+
+        let mut generator = XorShiftRng::seed_from_u64(42);
+        let mines = Generator::NoGuess.generate(
+            &mut generator,
+            WIDTH,
+            HEIGHT,
+            90,
+            (3, 4),
+            FirstClick::Safe,
+        );
+        assert_eq!(mines, None);
+    }
+
+    #[test]
+    fn random_avoids_start() {
+        let mut generator = XorShiftRng::seed_from_u64(42);
+        let start = (3, 4);
+        let mines = Generator::Random
+            .generate(&mut generator, WIDTH, HEIGHT, 50, start, FirstClick::Safe)
+            .unwrap();
+        assert_eq!(mines.len(), 50);
+        assert!(!mines.contains(&start));
+    }
+
+    #[test]
+    fn opening_avoids_neighbours() {
+        let mut generator = XorShiftRng::seed_from_u64(42);
+        let mut map_generator = XorShiftRng::seed_from_u64(7);
+        for i in 0..200 {
+            // Include corners and edges, which have fewer neighbours
+            let start = match i % 4 {
+                0 => (0, 0),
+                1 => (WIDTH - 1, (i * 3) % HEIGHT),
+                _ => (
+                    map_generator.next_u32() as usize % WIDTH,
+                    map_generator.next_u32() as usize % HEIGHT,
+                ),
+            };
+            let mines = Generator::Random
+                .generate(
+                    &mut generator,
+                    WIDTH,
+                    HEIGHT,
+                    Difficulty::Hard.mines_count(),
+                    start,
+                    FirstClick::Opening,
+                )
+                .unwrap();
+            assert_eq!(mines.len(), Difficulty::Hard.mines_count());
+            assert!(mines.iter().all(|(x, y)| !is_near(start, *x, *y)));
+
+            // The first click uncovers a tile without adjacent mines
+            let map = Map::new(WIDTH, HEIGHT, 10, (0, 0)).with_mines(&mines);
+            assert_eq!(map.count_neighbour_mines(start.0, start.1), 0);
+        }
+    }
+
+    #[test]
+    fn opening_falls_back_to_safe_tile() {
+        let mut generator = XorShiftRng::seed_from_u64(42);
+        let start = (3, 4);
+        let mines_count = WIDTH * HEIGHT - 5;
+        let mines = Generator::Random
+            .generate(
+                &mut generator,
+                WIDTH,
+                HEIGHT,
+                mines_count,
+                start,
+                FirstClick::Opening,
+            )
+            .unwrap();
+        assert_eq!(mines.len(), mines_count);
+        assert!(!mines.contains(&start));
+    }
+
+    #[test]
+    fn classic_can_place_mine_on_start() {
+        let mut generator = XorShiftRng::seed_from_u64(42);
+        let start = (3, 4);
+        let mines = Generator::Random
+            .generate(
+                &mut generator,
+                WIDTH,
+                HEIGHT,
+                WIDTH * HEIGHT,
+                start,
+                FirstClick::Classic,
+            )
+            .unwrap();
+        assert!(mines.contains(&start));
+    }
+}
@@ -6,54 +6,150 @@
 
 //! Functions for loading and saving high scores
 
-use crate::debug;
-use crate::wasm4::{diskr, diskw};
+use super::puzzle::PUZZLES_STORED_SIZE;
+use super::save::{
+    Save, CUSTOM_SECTION, DAILY_SECTION, DISK_SIZE, LEADERBOARD_SECTION, REPLAY_SECTION,
+};
+use super::settings::SETTINGS_STORED_SIZE;
+use super::statistics::STATISTICS_STORED_SIZE;
+use super::{Date, Difficulty, Performance, Replay};
+use crate::time::FRAMES_PER_SECOND;
 
-use super::Difficulty;
+/// Number of entries in the leaderboard of each difficulty
+pub const LEADERBOARD_SIZE: usize = 5;
+
+/// Initials of entries set before leaderboards were introduced
+pub const UNKNOWN_INITIALS: [u8; 3] = *b"---";
+
+/// Size of a high score stored before the introduction of versioned saves,
+/// i.e. a marker and the time in whole seconds
+const LEGACY_RECORD_SIZE: usize = 3;
+
+/// Size of a stored time in frames
+const TIME_SIZE: usize = 4;
+
+/// Size of a stored performance, i.e. the 3BV and the clicks count
+const PERFORMANCE_SIZE: usize = 4;
+
+/// Size of a stored leaderboard entry, i.e. the time, the hints count, the
+/// initials, the game number and the performance
+const ENTRY_RECORD_SIZE: usize = TIME_SIZE + 6 + PERFORMANCE_SIZE;
+
+/// Size of a stored custom high score, i.e. the map size, the mines count,
+/// the time, the hints count and the performance
+const CUSTOM_RECORD_SIZE: usize = TIME_SIZE + 5 + PERFORMANCE_SIZE;
+
+/// Size of the stored record of boards of the day, i.e. a set of flags, the
+/// date of the last board, the date of the last won board, the streak, the
+/// best time, its hints count and its performance
+const DAILY_RECORD_SIZE: usize = TIME_SIZE + 8 + PERFORMANCE_SIZE;
+
+/// Maximal number of stored custom high scores
+const MAX_CUSTOM_HIGHSCORES: usize = 16;
+
+/// Flag marking the date of the last board as stored
+const DAILY_DATE_FLAG: u8 = 0b001;
+
+/// Flag marking the date of the last won board as stored
+const DAILY_LAST_WON_FLAG: u8 = 0b010;
+
+/// Flag marking the best time as stored
+const DAILY_BEST_FLAG: u8 = 0b100;
+
+/// Space available on disk for high scores and replays, leaving room for the
+/// statistics, the settings and the solved puzzles
+const BUDGET: usize =
+    DISK_SIZE - STATISTICS_STORED_SIZE - SETTINGS_STORED_SIZE - PUZZLES_STORED_SIZE;
+
+/// A single high score
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HighScore {
+    /// Time taken to win the game in frames
+    pub time: u32,
+
+    /// Number of hints used during the game
+    pub hints: u8,
+
+    /// Performance of the game, if known
+    pub performance: Option<Performance>,
+}
+
+/// An entry in a leaderboard
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LeaderboardEntry {
+    /// Time taken to win the game in frames
+    pub time: u32,
+
+    /// Number of hints used during the game
+    pub hints: u8,
+
+    /// Initials of the player, as uppercase ASCII letters
+    pub initials: [u8; 3],
+
+    /// Number of won games when the entry was set, or zero if unknown
+    pub game: u16,
+
+    /// Performance of the game, if known
+    pub performance: Option<Performance>,
+}
 
 /// High scores
 ///
-/// High scores are represented as time taken to win the game in seconds.
+/// High scores are represented as time taken to win the game in frames, so
+/// that games won in the same second are still ranked.
 /// Therefore, lower scores are actually higher.
+/// The number of hints used and the performance, i.e. the 3BV of the board
+/// and the number of clicks, are stored alongside the time.
+///
+/// Easy, medium and hard games have a leaderboard of the five best times,
+/// each with the initials of the player and the number of the won game.
+/// The total number of won games and the last entered initials are stored
+/// as well.
+///
+/// Custom games have a separate high score for each combination of map size
+/// and mines count.
+/// Only the 16 most recently set custom high scores are kept.
+///
+/// Boards of the day have a separate best time and a streak, i.e. the number
+/// of boards won on consecutive days.
+/// The date of the last board of the day is stored as well, since WASM-4 has
+/// no clock.
+///
+/// The replay of the best game is stored for easy, medium and hard games.
+/// Replays are stored in the space left on disk by other data; when they do
+/// not fit, the longest replays of other difficulties are discarded first.
 #[derive(Clone)]
 pub struct HighScores {
-    easy: Option<u16>,
-    medium: Option<u16>,
-    hard: Option<u16>,
+    leaderboards: [Vec<LeaderboardEntry>; 3],
+    wins: u16,
+    initials: [u8; 3],
+    custom: Vec<(Difficulty, HighScore)>,
+    daily_date: Option<Date>,
+    daily_last_won: Option<Date>,
+    daily_streak: u16,
+    daily_best: Option<HighScore>,
+    replays: Vec<Replay>,
 }
 
 impl HighScores {
     /// Load the high scores
-    ///
-    /// The result is a list of optional times in seconds, one for each difficulty.
     pub fn load() -> Self {
-        let mut highscores = [None, None, None];
-
-        let mut buffer: [u8; 3 * 3] = [0; 3 * 3];
-        let bytes_read = unsafe { diskr(&mut buffer as *mut u8, buffer.len() as u32) };
-        if bytes_read == buffer.len() as u32 {
-            for i in 0..3 {
-                let stored = buffer[i * 3] > 0;
-                if stored {
-                    let time = (buffer[i * 3 + 1] as u16) | (buffer[i * 3 + 2] as u16) << 8;
-                    highscores[i] = Some(time);
-                }
-            }
-        }
-
-        Self {
-            easy: highscores[0],
-            medium: highscores[1],
-            hard: highscores[2],
-        }
+        Self::from_save(&Save::load())
     }
 
     /// Get the highscore for a difficulty
-    pub fn get(&self, difficulty: Difficulty) -> Option<u16> {
+    pub fn get(&self, difficulty: Difficulty) -> Option<HighScore> {
         match difficulty {
-            Difficulty::Easy => self.easy,
-            Difficulty::Medium => self.medium,
-            Difficulty::Hard => self.hard,
+            Difficulty::Custom { .. } => self
+                .custom
+                .iter()
+                .find(|(custom, _)| *custom == difficulty)
+                .map(|(_, highscore)| *highscore),
+            _ => self.leaderboard(difficulty).first().map(|entry| HighScore {
+                time: entry.time,
+                hints: entry.hints,
+                performance: entry.performance,
+            }),
         }
     }
 
@@ -61,52 +157,845 @@ impl HighScores {
     ///
     /// The score is set only if better than the current one, i.e. if the time
     /// is smaller.
-    pub fn set(&mut self, difficulty: Difficulty, time: u16) {
-        match difficulty {
-            Difficulty::Easy => {
-                let new_time = self
-                    .easy
-                    .map_or(time, |old_time| std::cmp::min(old_time, time));
-                self.easy = Some(new_time);
+    /// For easy, medium and hard games the score is added to the leaderboard
+    /// if it qualifies, with the last entered initials.
+    /// Return whether the score became the best one.
+    pub fn set(&mut self, difficulty: Difficulty, highscore: HighScore) -> bool {
+        let entry = LeaderboardEntry {
+            time: highscore.time,
+            hints: highscore.hints,
+            initials: self.initials,
+            game: self.wins,
+            performance: highscore.performance,
+        };
+        self.insert(difficulty, entry) == Some(0)
+    }
+
+    /// Return the leaderboard of a difficulty, sorted from the best time
+    ///
+    /// Custom games have no leaderboard.
+    pub fn leaderboard(&self, difficulty: Difficulty) -> &[LeaderboardEntry] {
+        match leaderboard_index(difficulty) {
+            Some(index) => &self.leaderboards[index],
+            None => &[],
+        }
+    }
+
+    /// Return the rank a time would have in the leaderboard of a difficulty
+    ///
+    /// Return `None` if the time does not qualify, i.e. if it is not better
+    /// than any of the times in a full leaderboard.
+    /// Equal times are ranked after the existing ones.
+    /// For custom games, only a time better than the current one qualifies.
+    pub fn rank(&self, difficulty: Difficulty, time: u32) -> Option<usize> {
+        match leaderboard_index(difficulty) {
+            Some(index) => {
+                let rank = self.leaderboards[index]
+                    .iter()
+                    .take_while(|entry| entry.time <= time)
+                    .count();
+                if rank < LEADERBOARD_SIZE {
+                    Some(rank)
+                } else {
+                    None
+                }
+            }
+            None => match self.get(difficulty) {
+                Some(old) if time >= old.time => None,
+                _ => Some(0),
+            },
+        }
+    }
+
+    /// Insert an entry in the leaderboard of a difficulty
+    ///
+    /// The worst entry is dropped when the leaderboard is full.
+    /// The initials are remembered for the next entry.
+    /// Custom games only keep the time and the hints count of the best entry.
+    /// Return the rank of the entry, or `None` if it does not qualify.
+    pub fn insert(&mut self, difficulty: Difficulty, entry: LeaderboardEntry) -> Option<usize> {
+        let rank = self.rank(difficulty, entry.time)?;
+        match leaderboard_index(difficulty) {
+            Some(index) => {
+                let leaderboard = &mut self.leaderboards[index];
+                leaderboard.insert(rank, entry);
+                leaderboard.truncate(LEADERBOARD_SIZE);
+                self.initials = entry.initials;
             }
-            Difficulty::Medium => {
-                let new_time = self
-                    .medium
-                    .map_or(time, |old_time| std::cmp::min(old_time, time));
-                self.medium = Some(new_time);
+            None => {
+                let highscore = HighScore {
+                    time: entry.time,
+                    hints: entry.hints,
+                    performance: entry.performance,
+                };
+                self.custom.retain(|(custom, _)| *custom != difficulty);
+                if self.custom.len() == MAX_CUSTOM_HIGHSCORES {
+                    self.custom.remove(0);
+                }
+                self.custom.push((difficulty, highscore));
+            }
+        }
+        Some(rank)
+    }
+
+    /// Record a won game and return its number
+    pub fn record_win(&mut self) -> u16 {
+        self.wins = self.wins.saturating_add(1);
+        self.wins
+    }
+
+    /// Return the last entered initials
+    pub fn initials(&self) -> [u8; 3] {
+        self.initials
+    }
+
+    /// Get the replay of the best game for a difficulty
+    pub fn replay(&self, difficulty: Difficulty) -> Option<&Replay> {
+        self.replays
+            .iter()
+            .find(|replay| replay.difficulty() == difficulty)
+    }
+
+    /// Set the replay of the best game for its difficulty
+    ///
+    /// Replays of custom games are not stored.
+    /// If the replays do not fit on disk, the longest replays of other
+    /// difficulties are discarded.
+    /// A replay too long to fit on disk even alone is not stored.
+    pub fn set_replay(&mut self, replay: Replay) {
+        let difficulty = replay.difficulty();
+        if let Difficulty::Custom { .. } = difficulty {
+            return;
+        }
+
+        self.replays
+            .retain(|other| other.difficulty() != difficulty);
+        let others = std::mem::take(&mut self.replays);
+        self.replays.push(replay);
+        if self.to_bytes().len() > BUDGET {
+            self.replays = others;
+            return;
+        }
+        self.replays.extend(others);
+
+        while self.to_bytes().len() > BUDGET {
+            let longest_other = self
+                .replays
+                .iter()
+                .enumerate()
+                .skip(1)
+                .max_by_key(|(_, other)| other.to_bytes().len())
+                .map(|(i, _)| i);
+            match longest_other {
+                Some(i) => self.replays.remove(i),
+                None => break,
+            };
+        }
+    }
+
+    /// Return the date of the last board of the day
+    pub fn daily_date(&self) -> Option<Date> {
+        self.daily_date
+    }
+
+    /// Set the date of the last board of the day
+    pub fn set_daily_date(&mut self, date: Date) {
+        self.daily_date = Some(date);
+    }
+
+    /// Return the best time on boards of the day
+    pub fn daily_best(&self) -> Option<HighScore> {
+        self.daily_best
+    }
+
+    /// Return the streak of won boards of the day up to a date
+    ///
+    /// The streak is broken if neither the board of that date nor the one of
+    /// the day before were won.
+    pub fn daily_streak(&self, date: Date) -> u16 {
+        match self.daily_last_won {
+            Some(last) if last == date || last.add_days(1) == date => self.daily_streak,
+            _ => 0,
+        }
+    }
+
+    /// Record a won board of the day
+    ///
+    /// Winning a board again or winning a past board does not change the
+    /// streak.
+    pub fn set_daily(&mut self, date: Date, highscore: HighScore) {
+        match self.daily_last_won {
+            Some(last) if date <= last => {}
+            Some(last) if last.add_days(1) == date => {
+                self.daily_streak = self.daily_streak.saturating_add(1);
+                self.daily_last_won = Some(date);
             }
-            Difficulty::Hard => {
-                let new_time = self
-                    .hard
-                    .map_or(time, |old_time| std::cmp::min(old_time, time));
-                self.hard = Some(new_time);
+            _ => {
+                self.daily_streak = 1;
+                self.daily_last_won = Some(date);
             }
         }
+
+        let is_better = match self.daily_best {
+            Some(old) => highscore.time < old.time,
+            None => true,
+        };
+        if is_better {
+            self.daily_best = Some(highscore);
+        }
     }
 
     /// Save highscores
+    ///
+    /// Other stored data is kept.
     pub fn save(&self) {
-        let mut buffer: [u8; 3 * 3] = [0; 3 * 3];
+        let mut save = Save::load();
+        self.write(&mut save);
+        save.store();
+    }
+
+    /// Read the high scores from their sections
+    ///
+    /// Sections with unexpected sizes are ignored.
+    pub fn from_save(save: &Save) -> Self {
+        let mut highscores = Self::empty();
+
+        if let Some(section) = save.section(LEADERBOARD_SECTION) {
+            highscores.read_leaderboards(section);
+        }
+        if let Some(section) = save.section(CUSTOM_SECTION) {
+            highscores.read_custom_records(section);
+        }
+        if let Some(section) = save.section(DAILY_SECTION) {
+            if section.len() == DAILY_RECORD_SIZE {
+                highscores.read_daily_record(section);
+            }
+        }
+        highscores.replays = save
+            .sections(REPLAY_SECTION)
+            .filter_map(Replay::from_bytes)
+            .collect();
+
+        highscores
+    }
+
+    /// Write the high scores to their sections
+    pub fn write(&self, save: &mut Save) {
+        let mut leaderboards = self.initials.to_vec();
+        leaderboards.extend_from_slice(&self.wins.to_le_bytes());
+        for leaderboard in self.leaderboards.iter() {
+            leaderboards.push(leaderboard.len() as u8);
+            for entry in leaderboard {
+                leaderboards.extend_from_slice(&entry.time.to_le_bytes());
+                leaderboards.push(entry.hints);
+                leaderboards.extend_from_slice(&entry.initials);
+                leaderboards.extend_from_slice(&entry.game.to_le_bytes());
+                write_performance(&mut leaderboards, entry.performance);
+            }
+        }
+        save.set_section(LEADERBOARD_SECTION, leaderboards);
+
+        let mut custom = Vec::with_capacity(self.custom.len() * CUSTOM_RECORD_SIZE);
+        for (difficulty, highscore) in &self.custom {
+            custom.push(difficulty.width() as u8);
+            custom.push(difficulty.height() as u8);
+            custom.push(difficulty.mines_count() as u8);
+            custom.push((difficulty.mines_count() >> 8) as u8);
+            custom.extend_from_slice(&highscore.time.to_le_bytes());
+            custom.push(highscore.hints);
+            write_performance(&mut custom, highscore.performance);
+        }
+        save.set_section(CUSTOM_SECTION, custom);
+
+        save.set_section(DAILY_SECTION, self.daily_record());
+
+        save.remove_sections(REPLAY_SECTION);
+        for replay in &self.replays {
+            save.push_section(REPLAY_SECTION, replay.to_bytes());
+        }
+    }
+
+    /// Load high scores stored before the introduction of versioned saves
+    ///
+    /// The original layout contains three records, for easy, medium and hard
+    /// games, with times in whole seconds.
+    /// Data with any other size is ignored.
+    pub fn from_legacy_bytes(buffer: &[u8]) -> Self {
+        let mut highscores = Self::empty();
+        if buffer.len() == 3 * LEGACY_RECORD_SIZE {
+            highscores.read_legacy_records(buffer);
+        }
+        highscores
+    }
+
+    fn empty() -> Self {
+        Self {
+            leaderboards: [Vec::new(), Vec::new(), Vec::new()],
+            wins: 0,
+            initials: *b"AAA",
+            custom: Vec::new(),
+            daily_date: None,
+            daily_last_won: None,
+            daily_streak: 0,
+            daily_best: None,
+            replays: Vec::new(),
+        }
+    }
+
+    /// Read the easy, medium and hard records stored before the introduction
+    /// of versioned saves
+    fn read_legacy_records(&mut self, buffer: &[u8]) {
+        let records = buffer.chunks_exact(LEGACY_RECORD_SIZE);
+        for (leaderboard, record) in self.leaderboards.iter_mut().zip(records) {
+            let stored = record[0] > 0;
+            if stored {
+                let seconds = (record[1] as u32) | (record[2] as u32) << 8;
+                leaderboard.push(LeaderboardEntry {
+                    time: seconds * FRAMES_PER_SECOND,
+                    hints: 0,
+                    initials: UNKNOWN_INITIALS,
+                    game: 0,
+                    performance: None,
+                });
+            }
+        }
+    }
+
+    /// Read the leaderboards
+    ///
+    /// Reading stops at the first truncated leaderboard.
+    fn read_leaderboards(&mut self, buffer: &[u8]) {
+        if buffer.len() < 5 {
+            return;
+        }
+        self.initials = [buffer[0], buffer[1], buffer[2]];
+        self.wins = (buffer[3] as u16) | (buffer[4] as u16) << 8;
+
+        let mut buffer = &buffer[5..];
+        for leaderboard in self.leaderboards.iter_mut() {
+            let (&count, rest) = match buffer.split_first() {
+                Some(split) => split,
+                None => return,
+            };
+            let size = count as usize * ENTRY_RECORD_SIZE;
+            let records = match rest.get(..size) {
+                Some(records) => records,
+                None => return,
+            };
+            for record in records
+                .chunks_exact(ENTRY_RECORD_SIZE)
+                .take(LEADERBOARD_SIZE)
+            {
+                let (time, record) = record.split_at(TIME_SIZE);
+                leaderboard.push(LeaderboardEntry {
+                    time: read_time(time),
+                    hints: record[0],
+                    initials: [record[1], record[2], record[3]],
+                    game: (record[4] as u16) | (record[5] as u16) << 8,
+                    performance: read_performance(&record[6..]),
+                });
+            }
+            buffer = &rest[size..];
+        }
+    }
+
+    /// Read the custom records
+    fn read_custom_records(&mut self, buffer: &[u8]) {
+        let hints_offset = 4 + TIME_SIZE;
+        for record in buffer.chunks_exact(CUSTOM_RECORD_SIZE) {
+            let difficulty = Difficulty::Custom {
+                width: record[0] as usize,
+                height: record[1] as usize,
+                mines: (record[2] as usize) | (record[3] as usize) << 8,
+            };
+            let highscore = HighScore {
+                time: read_time(&record[4..]),
+                hints: record[hints_offset],
+                performance: read_performance(&record[hints_offset + 1..]),
+            };
+            self.custom.push((difficulty, highscore));
+        }
+    }
+
+    /// Read the record of boards of the day
+    fn read_daily_record(&mut self, record: &[u8]) {
+        let flags = record[0];
+        let read_u16 = |i: usize| (record[i] as u16) | (record[i + 1] as u16) << 8;
+        if flags & DAILY_DATE_FLAG != 0 {
+            self.daily_date = Some(Date::from_days(read_u16(1)));
+        }
+        if flags & DAILY_LAST_WON_FLAG != 0 {
+            self.daily_last_won = Some(Date::from_days(read_u16(3)));
+            self.daily_streak = read_u16(5);
+        }
+        if flags & DAILY_BEST_FLAG != 0 {
+            let hints_offset = 7 + TIME_SIZE;
+            self.daily_best = Some(HighScore {
+                time: read_time(&record[7..]),
+                hints: record[hints_offset],
+                performance: read_performance(&record[hints_offset + 1..]),
+            });
+        }
+    }
+
+    /// Return the record of boards of the day
+    fn daily_record(&self) -> Vec<u8> {
+        let mut flags = 0;
+        if self.daily_date.is_some() {
+            flags |= DAILY_DATE_FLAG;
+        }
+        if self.daily_last_won.is_some() {
+            flags |= DAILY_LAST_WON_FLAG;
+        }
+        if self.daily_best.is_some() {
+            flags |= DAILY_BEST_FLAG;
+        }
+        let date = self.daily_date.map(|date| date.days()).unwrap_or(0);
+        let last_won = self.daily_last_won.map(|date| date.days()).unwrap_or(0);
+        let best = self.daily_best.unwrap_or(HighScore {
+            time: 0,
+            hints: 0,
+            performance: None,
+        });
+
+        let mut record = Vec::with_capacity(DAILY_RECORD_SIZE);
+        record.push(flags);
+        for value in [date, last_won, self.daily_streak].iter() {
+            record.extend_from_slice(&value.to_le_bytes());
+        }
+        record.extend_from_slice(&best.time.to_le_bytes());
+        record.push(best.hints);
+        write_performance(&mut record, best.performance);
+        record
+    }
+
+    #[cfg(test)]
+    fn from_bytes(buffer: &[u8]) -> Self {
+        Self::from_save(&Save::from_bytes(buffer))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut save = Save::new();
+        self.write(&mut save);
+        save.to_bytes()
+    }
+}
+
+/// Read a stored time in frames
+fn read_time(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Read a stored performance
+///
+/// A null 3BV marks an unknown performance.
+fn read_performance(bytes: &[u8]) -> Option<Performance> {
+    let three_bv = (bytes[0] as u16) | (bytes[1] as u16) << 8;
+    let clicks = (bytes[2] as u16) | (bytes[3] as u16) << 8;
+    if three_bv > 0 {
+        Some(Performance { three_bv, clicks })
+    } else {
+        None
+    }
+}
+
+/// Append a performance to a record
+///
+/// An unknown performance is stored with a null 3BV.
+fn write_performance(record: &mut Vec<u8>, performance: Option<Performance>) {
+    let performance = performance.unwrap_or(Performance {
+        three_bv: 0,
+        clicks: 0,
+    });
+    record.extend_from_slice(&performance.three_bv.to_le_bytes());
+    record.extend_from_slice(&performance.clicks.to_le_bytes());
+}
+
+/// Return the index of the leaderboard of a difficulty
+fn leaderboard_index(difficulty: Difficulty) -> Option<usize> {
+    match difficulty {
+        Difficulty::Easy => Some(0),
+        Difficulty::Medium => Some(1),
+        Difficulty::Hard => Some(2),
+        Difficulty::Custom { .. } => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::{Action, Generator, Seed};
+
+    fn score(time: u32, hints: u8) -> HighScore {
+        HighScore {
+            time,
+            hints,
+            performance: None,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut highscores = HighScores::from_bytes(&[]);
+        highscores.set(Difficulty::Medium, score(300, 2));
+
+        let highscores = HighScores::from_bytes(&highscores.to_bytes());
+        assert!(highscores.get(Difficulty::Easy).is_none());
+        let highscore = highscores.get(Difficulty::Medium).unwrap();
+        assert_eq!(highscore.time, 300);
+        assert_eq!(highscore.hints, 2);
+        assert!(highscores.get(Difficulty::Hard).is_none());
+    }
+
+    #[test]
+    fn load_without_hints() {
+        let buffer = [0xff, 0x2c, 0x01, 0x00, 0x00, 0x00, 0xff, 0x10, 0x00];
+        let highscores = HighScores::from_bytes(&buffer);
+        assert_eq!(
+            highscores.get(Difficulty::Easy).unwrap().time,
+            300 * FRAMES_PER_SECOND
+        );
+        assert_eq!(highscores.get(Difficulty::Easy).unwrap().hints, 0);
+        assert!(highscores.get(Difficulty::Medium).is_none());
+        assert_eq!(
+            highscores.get(Difficulty::Hard).unwrap().time,
+            16 * FRAMES_PER_SECOND
+        );
+    }
+
+    #[test]
+    fn custom_round_trip() {
+        let small = Difficulty::Custom {
+            width: 8,
+            height: 8,
+            mines: 10,
+        };
+        let large = Difficulty::Custom {
+            width: 32,
+            height: 32,
+            mines: 300,
+        };
 
-        if let Some(time) = self.easy {
-            buffer[0] = 0xff;
-            buffer[1] = time as u8;
-            buffer[2] = (time >> 8) as u8;
+        let mut highscores = HighScores::from_bytes(&[]);
+        highscores.set(small, score(25, 0));
+        highscores.set(large, score(70, 1));
+        highscores.set(Difficulty::Easy, score(60, 0));
+
+        let highscores = HighScores::from_bytes(&highscores.to_bytes());
+        assert_eq!(highscores.get(small).unwrap().time, 25);
+        assert_eq!(highscores.get(large).unwrap().time, 70);
+        assert_eq!(highscores.get(large).unwrap().hints, 1);
+        assert_eq!(highscores.get(Difficulty::Easy).unwrap().time, 60);
+    }
+
+    #[test]
+    fn custom_keeps_most_recent() {
+        let mut highscores = HighScores::from_bytes(&[]);
+        for mines in 1..=(MAX_CUSTOM_HIGHSCORES + 1) {
+            let difficulty = Difficulty::Custom {
+                width: 8,
+                height: 8,
+                mines,
+            };
+            highscores.set(difficulty, score(100, 0));
         }
-        if let Some(time) = self.medium {
-            buffer[3] = 0xff;
-            buffer[4] = time as u8;
-            buffer[5] = (time >> 8) as u8;
+
+        assert_eq!(highscores.custom.len(), MAX_CUSTOM_HIGHSCORES);
+        let oldest = Difficulty::Custom {
+            width: 8,
+            height: 8,
+            mines: 1,
+        };
+        assert!(highscores.get(oldest).is_none());
+        assert!(
+            HighScores::from_bytes(&highscores.to_bytes())
+                .to_bytes()
+                .len()
+                <= DISK_SIZE
+        );
+    }
+
+    #[test]
+    fn set_keeps_better_time() {
+        let mut highscores = HighScores::from_bytes(&[]);
+        highscores.set(Difficulty::Easy, score(30, 0));
+        highscores.set(Difficulty::Easy, score(40, 1));
+        assert_eq!(highscores.get(Difficulty::Easy).unwrap().time, 30);
+        assert_eq!(highscores.get(Difficulty::Easy).unwrap().hints, 0);
+
+        highscores.set(Difficulty::Easy, score(20, 3));
+        assert_eq!(highscores.get(Difficulty::Easy).unwrap().time, 20);
+        assert_eq!(highscores.get(Difficulty::Easy).unwrap().hints, 3);
+    }
+
+    fn date(day: u8) -> Date {
+        Date::new(2021, 10, day).unwrap()
+    }
+
+    #[test]
+    fn daily_round_trip() {
+        let small = Difficulty::Custom {
+            width: 8,
+            height: 8,
+            mines: 10,
+        };
+
+        let mut highscores = HighScores::from_bytes(&[]);
+        highscores.set(small, score(25, 0));
+        highscores.set_daily_date(date(18));
+        highscores.set_daily(date(17), score(80, 0));
+        highscores.set_daily(date(18), score(70, 2));
+
+        let buffer = highscores.to_bytes();
+        assert!(buffer.len() <= DISK_SIZE);
+        let highscores = HighScores::from_bytes(&buffer);
+        assert_eq!(highscores.get(small).unwrap().time, 25);
+        assert_eq!(highscores.daily_date(), Some(date(18)));
+        assert_eq!(highscores.daily_streak(date(18)), 2);
+        assert_eq!(highscores.daily_best().unwrap().time, 70);
+        assert_eq!(highscores.daily_best().unwrap().hints, 2);
+    }
+
+    #[test]
+    fn load_without_daily() {
+        let buffer = [0xff, 0x3c, 0, 0, 0, 0, 0, 0, 0];
+        let highscores = HighScores::from_bytes(&buffer);
+        assert_eq!(
+            highscores.get(Difficulty::Easy).unwrap().time,
+            60 * FRAMES_PER_SECOND
+        );
+        assert!(highscores.daily_date().is_none());
+        assert!(highscores.daily_best().is_none());
+        assert_eq!(highscores.daily_streak(date(18)), 0);
+    }
+
+    #[test]
+    fn daily_streak() {
+        let mut highscores = HighScores::from_bytes(&[]);
+        highscores.set_daily(date(10), score(100, 0));
+        highscores.set_daily(date(11), score(100, 0));
+        highscores.set_daily(date(11), score(90, 0));
+        highscores.set_daily(date(9), score(90, 0));
+        assert_eq!(highscores.daily_streak(date(11)), 2);
+        assert_eq!(highscores.daily_streak(date(12)), 2);
+        assert_eq!(highscores.daily_streak(date(13)), 0);
+
+        highscores.set_daily(date(13), score(100, 0));
+        assert_eq!(highscores.daily_streak(date(13)), 1);
+        assert_eq!(highscores.daily_best().unwrap().time, 90);
+    }
+
+    fn replay(difficulty: Difficulty, moves: usize) -> Replay {
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        let mut replay = Replay::new(difficulty, Generator::Random, seed, (0, 0));
+        for i in 0..moves {
+            for _ in 0..60 {
+                replay.tick();
+            }
+            replay.record(Action::Uncover(i % 8, i % 8));
         }
-        if let Some(time) = self.hard {
-            buffer[6] = 0xff;
-            buffer[7] = time as u8;
-            buffer[8] = (time >> 8) as u8;
+        replay
+    }
+
+    #[test]
+    fn replay_round_trip() {
+        let mut highscores = HighScores::from_bytes(&[]);
+        highscores.set_daily(date(18), score(70, 2));
+        assert!(highscores.set(Difficulty::Easy, score(60, 0)));
+        highscores.set_replay(replay(Difficulty::Easy, 20));
+        assert!(highscores.set(Difficulty::Hard, score(200, 0)));
+        highscores.set_replay(replay(Difficulty::Hard, 50));
+        assert!(!highscores.set(Difficulty::Hard, score(300, 0)));
+
+        let highscores = HighScores::from_bytes(&highscores.to_bytes());
+        assert_eq!(
+            highscores.replay(Difficulty::Easy),
+            Some(&replay(Difficulty::Easy, 20))
+        );
+        assert!(highscores.replay(Difficulty::Medium).is_none());
+        assert_eq!(
+            highscores.replay(Difficulty::Hard),
+            Some(&replay(Difficulty::Hard, 50))
+        );
+        assert_eq!(highscores.daily_best().unwrap().time, 70);
+    }
+
+    #[test]
+    fn replay_replaces_same_difficulty() {
+        let mut highscores = HighScores::from_bytes(&[]);
+        highscores.set_replay(replay(Difficulty::Medium, 10));
+        highscores.set_replay(replay(Difficulty::Medium, 5));
+        assert_eq!(highscores.replays.len(), 1);
+        assert_eq!(
+            highscores.replay(Difficulty::Medium),
+            Some(&replay(Difficulty::Medium, 5))
+        );
+    }
+
+    #[test]
+    fn replays_fit_on_disk() {
+        let mut highscores = HighScores::from_bytes(&[]);
+        highscores.set_replay(replay(Difficulty::Easy, 150));
+        highscores.set_replay(replay(Difficulty::Medium, 90));
+        highscores.set_replay(replay(Difficulty::Hard, 200));
+        assert!(highscores.to_bytes().len() <= DISK_SIZE);
+        assert!(highscores.replay(Difficulty::Easy).is_none());
+        assert!(highscores.replay(Difficulty::Medium).is_some());
+        assert!(highscores.replay(Difficulty::Hard).is_some());
+
+        highscores.set_replay(replay(Difficulty::Easy, 400));
+        assert!(highscores.to_bytes().len() <= DISK_SIZE);
+        assert!(highscores.replay(Difficulty::Easy).is_none());
+        assert!(highscores.replay(Difficulty::Medium).is_some());
+        assert!(highscores.replay(Difficulty::Hard).is_some());
+    }
+
+    #[test]
+    fn custom_replays_are_not_stored() {
+        let custom = Difficulty::Custom {
+            width: 8,
+            height: 8,
+            mines: 10,
+        };
+        let mut highscores = HighScores::from_bytes(&[]);
+        highscores.set_replay(replay(custom, 10));
+        assert!(highscores.replay(custom).is_none());
+    }
+
+    #[test]
+    fn write_keeps_other_sections() {
+        let mut save = Save::new();
+        save.set_section(200, vec![1, 2, 3]);
+        let mut highscores = HighScores::from_save(&save);
+        highscores.set(Difficulty::Easy, score(60, 0));
+        highscores.write(&mut save);
+
+        assert_eq!(save.section(200), Some(&[1, 2, 3][..]));
+        assert_eq!(
+            HighScores::from_save(&save)
+                .get(Difficulty::Easy)
+                .unwrap()
+                .time,
+            60
+        );
+    }
+
+    #[test]
+    fn corrupt_data_is_discarded() {
+        let mut highscores = HighScores::from_bytes(&[]);
+        highscores.set(Difficulty::Easy, score(60, 0));
+        let mut buffer = highscores.to_bytes();
+        let last = buffer.len() - 1;
+        buffer[last] ^= 0x01;
+
+        let highscores = HighScores::from_bytes(&buffer);
+        assert!(highscores.get(Difficulty::Easy).is_none());
+    }
+
+    fn entry(time: u32, initials: &[u8; 3]) -> LeaderboardEntry {
+        LeaderboardEntry {
+            time,
+            hints: 0,
+            initials: *initials,
+            game: time as u16,
+            performance: Some(Performance {
+                three_bv: 30,
+                clicks: time as u16,
+            }),
         }
+    }
 
-        let bytes_written = unsafe { diskw(buffer.as_mut_ptr(), buffer.len() as u32) };
-        if bytes_written != buffer.len() as u32 {
-            debug!("Failed to save highscores");
+    #[test]
+    fn leaderboard_keeps_best_times() {
+        let mut highscores = HighScores::from_bytes(&[]);
+        for time in [50, 30, 70, 10, 60, 40].iter() {
+            highscores.insert(Difficulty::Medium, entry(*time, b"ABC"));
         }
+        let times: Vec<u32> = highscores
+            .leaderboard(Difficulty::Medium)
+            .iter()
+            .map(|entry| entry.time)
+            .collect();
+        assert_eq!(times, vec![10, 30, 40, 50, 60]);
+        assert!(highscores.leaderboard(Difficulty::Easy).is_empty());
+
+        assert_eq!(highscores.rank(Difficulty::Medium, 5), Some(0));
+        assert_eq!(highscores.rank(Difficulty::Medium, 30), Some(2));
+        assert_eq!(highscores.rank(Difficulty::Medium, 59), Some(4));
+        assert_eq!(highscores.rank(Difficulty::Medium, 60), None);
+        assert_eq!(
+            highscores.insert(Difficulty::Medium, entry(80, b"XYZ")),
+            None
+        );
+    }
+
+    #[test]
+    fn leaderboard_round_trip() {
+        let mut highscores = HighScores::from_bytes(&[]);
+        assert_eq!(highscores.record_win(), 1);
+        highscores.insert(Difficulty::Easy, entry(20, b"ABC"));
+        highscores.insert(Difficulty::Hard, entry(200, b"XYZ"));
+        highscores.insert(Difficulty::Hard, entry(100, b"QRS"));
+
+        let highscores = HighScores::from_bytes(&highscores.to_bytes());
+        assert_eq!(
+            highscores.leaderboard(Difficulty::Easy),
+            &[entry(20, b"ABC")]
+        );
+        assert!(highscores.leaderboard(Difficulty::Medium).is_empty());
+        assert_eq!(
+            highscores.leaderboard(Difficulty::Hard),
+            &[entry(100, b"QRS"), entry(200, b"XYZ")]
+        );
+        assert_eq!(highscores.initials(), *b"QRS");
+        assert_eq!(highscores.wins, 1);
+    }
+
+    #[test]
+    fn times_in_the_same_second_are_ranked() {
+        let mut highscores = HighScores::from_bytes(&[]);
+        highscores.insert(Difficulty::Easy, entry(30 * FRAMES_PER_SECOND + 40, b"ABC"));
+        highscores.insert(Difficulty::Easy, entry(30 * FRAMES_PER_SECOND + 10, b"XYZ"));
+        assert_eq!(
+            highscores.rank(Difficulty::Easy, 30 * FRAMES_PER_SECOND + 20),
+            Some(1)
+        );
+
+        let highscores = HighScores::from_bytes(&highscores.to_bytes());
+        assert_eq!(
+            highscores.leaderboard(Difficulty::Easy),
+            &[
+                entry(30 * FRAMES_PER_SECOND + 10, b"XYZ"),
+                entry(30 * FRAMES_PER_SECOND + 40, b"ABC")
+            ]
+        );
+    }
+
+    #[test]
+    fn performance_round_trip() {
+        let small = Difficulty::Custom {
+            width: 8,
+            height: 8,
+            mines: 10,
+        };
+        let highscore = HighScore {
+            time: 1234,
+            hints: 1,
+            performance: Some(Performance {
+                three_bv: 17,
+                clicks: 25,
+            }),
+        };
+
+        let mut highscores = HighScores::from_bytes(&[]);
+        highscores.set(Difficulty::Hard, highscore);
+        highscores.set(small, highscore);
+        highscores.set_daily(date(18), highscore);
+
+        let highscores = HighScores::from_bytes(&highscores.to_bytes());
+        assert_eq!(highscores.get(Difficulty::Hard), Some(highscore));
+        assert_eq!(highscores.get(small), Some(highscore));
+        assert_eq!(highscores.daily_best(), Some(highscore));
     }
 }
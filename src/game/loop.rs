@@ -6,30 +6,35 @@
 
 //! Game main loop
 
+use core::ptr::addr_of_mut;
+
 use crate::graphics::{draw_horizontal_line, draw_vertical_line, DrawColors};
-use crate::input::Mouse;
+use crate::input::{InputFrame, InputSource, Wasm4Input};
 use crate::statemachine::STATE_MACHINE;
 use crate::time::{BigTicker, Ticker};
 
+/// The source of inputs from the console
+static mut INPUT: Wasm4Input = Wasm4Input::new();
+
 #[no_mangle]
 fn start() {}
 
 #[no_mangle]
 fn update() {
-    let state_machine = unsafe { &mut STATE_MACHINE };
+    let state_machine = unsafe { &mut *addr_of_mut!(STATE_MACHINE) };
+    let input = unsafe { &mut *addr_of_mut!(INPUT) }.next_frame();
 
-    state_machine.draw();
-    state_machine.update(&Mouse);
+    state_machine.draw(&input);
+    state_machine.update(&input);
 
-    draw_mouse_pointer();
+    draw_mouse_pointer(&input);
 
-    Mouse.update();
     Ticker.update();
     BigTicker.update();
 }
 
-fn draw_mouse_pointer() {
-    let pos = Mouse.coordinates();
+fn draw_mouse_pointer(input: &InputFrame) {
+    let pos = input.coordinates();
     DrawColors.set(4);
     draw_vertical_line(pos.0 as i32, pos.1 as i32 - 1, 3);
     draw_horizontal_line(pos.0 as i32 - 1, pos.1 as i32, 3);
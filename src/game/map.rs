@@ -10,8 +10,14 @@ use core::iter::Iterator;
 
 use crate::graphics::Tile;
 
-const MAX_WIDTH: usize = 16;
-const MAX_HEIGHT: usize = 16;
+/// Maximal map width
+pub const MAX_WIDTH: usize = 32;
+
+/// Maximal map height
+pub const MAX_HEIGHT: usize = 32;
+
+/// Tile sizes available when zooming, in pixels
+pub const ZOOM_LEVELS: [u32; 4] = [6, 8, 10, 16];
 
 /// Represent the game map
 ///
@@ -19,18 +25,36 @@ const MAX_HEIGHT: usize = 16;
 /// It contains a matrix of tiles, which can be either covered, uncovered or
 /// flagged.
 ///
+/// It also contains the mine layout, stored as one bitmask per row, and the
+/// number of mines adjacent to each tile, computed once when the mines are
+/// placed, so that queries about mines take constant time.
+///
 /// It also contains the offset to which the map is drawn.
+/// Maps larger than the screen are drawn through a viewport, i.e. a window
+/// showing only some of the tiles, which can be scrolled one tile at a time.
+/// The viewport is sized to fit a screen area, and it is resized whenever the
+/// tile size changes.
 #[derive(Clone)]
 pub struct Map {
     tile_size: u32,
     offset: (i32, i32),
+    area: (i32, i32, u32, u32),
     tiles: Vec<Tile>,
+    mine_rows: Vec<u32>,
+    neighbour_mines: Vec<u8>,
+    mines_count: usize,
     width: usize,
     height: usize,
+    viewport: (usize, usize),
+    scroll: (usize, usize),
+    pan_remainder: (i32, i32),
 }
 
 impl Map {
     /// Create a new map with given size and drawing offset
+    ///
+    /// The viewport initially shows the whole map, and the map contains no
+    /// mines.
     pub fn new(width: usize, height: usize, tile_size: u32, offset: (i32, i32)) -> Self {
         debug_assert!(width <= MAX_WIDTH);
         debug_assert!(height <= MAX_HEIGHT);
@@ -39,12 +63,136 @@ impl Map {
         Self {
             tile_size,
             offset,
+            area: (
+                offset.0,
+                offset.1,
+                width as u32 * tile_size,
+                height as u32 * tile_size,
+            ),
             tiles,
+            mine_rows: vec![0; height],
+            neighbour_mines: vec![0; width * height],
+            mines_count: 0,
             width,
             height,
+            viewport: (width, height),
+            scroll: (0, 0),
+            pan_remainder: (0, 0),
+        }
+    }
+
+    /// Fit the map inside a screen area
+    ///
+    /// The viewport shows as many tiles as fit inside the area, and it is
+    /// centred inside it.
+    pub fn with_area(mut self, area: (i32, i32, u32, u32)) -> Self {
+        self.area = area;
+        self.resize_viewport();
+        self
+    }
+
+    /// Place mines on the map
+    ///
+    /// Any previous mine layout is replaced, and the number of mines adjacent
+    /// to each tile is computed.
+    pub fn with_mines(mut self, mines: &[(usize, usize)]) -> Self {
+        self.mine_rows = vec![0; self.height];
+        for (x, y) in mines {
+            self.mine_rows[*y] |= 1 << x;
+        }
+        self.mines_count = self
+            .mine_rows
+            .iter()
+            .map(|row| row.count_ones() as usize)
+            .sum();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let count = self
+                    .neighbours(x, y)
+                    .into_iter()
+                    .filter(|(nx, ny)| self.is_mine(*nx, *ny))
+                    .count();
+                self.neighbour_mines[x + y * self.width] = count as u8;
+            }
+        }
+        self
+    }
+
+    /// Return the number of mines
+    pub fn mines_count(&self) -> usize {
+        self.mines_count
+    }
+
+    /// Check whether a tile contains a mine
+    pub fn is_mine(&self, x: usize, y: usize) -> bool {
+        self.mine_rows[y] & 1 << x != 0
+    }
+
+    /// Return the positions of all mines, row by row
+    pub fn mines(&self) -> Vec<(usize, usize)> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|(x, y)| self.is_mine(*x, *y))
+            .collect()
+    }
+
+    /// Return the size of tiles in pixels
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// Switch to the next larger zoom level, if any
+    pub fn zoom_in(&mut self) {
+        if let Some(tile_size) = ZOOM_LEVELS.iter().find(|size| **size > self.tile_size) {
+            self.set_tile_size(*tile_size);
         }
     }
 
+    /// Switch to the next smaller zoom level, if any
+    pub fn zoom_out(&mut self) {
+        if let Some(tile_size) = ZOOM_LEVELS
+            .iter()
+            .rev()
+            .find(|size| **size < self.tile_size)
+        {
+            self.set_tile_size(*tile_size);
+        }
+    }
+
+    /// Change the tile size
+    ///
+    /// The viewport is resized to fit the screen area, keeping the tile at
+    /// its centre in place as much as possible.
+    pub fn set_tile_size(&mut self, tile_size: u32) {
+        let centre = (
+            self.scroll.0 + self.viewport.0 / 2,
+            self.scroll.1 + self.viewport.1 / 2,
+        );
+
+        self.tile_size = tile_size;
+        self.resize_viewport();
+
+        self.scroll = (
+            centre.0.saturating_sub(self.viewport.0 / 2),
+            centre.1.saturating_sub(self.viewport.1 / 2),
+        );
+        self.scroll_by(0, 0);
+    }
+
+    fn resize_viewport(&mut self) {
+        let (x, y, width, height) = self.area;
+        let columns = core::cmp::min(self.width, (width / self.tile_size) as usize);
+        let rows = core::cmp::min(self.height, (height / self.tile_size) as usize);
+        self.viewport = (columns, rows);
+        self.offset = (
+            x + (width - columns as u32 * self.tile_size) as i32 / 2,
+            y + (height - rows as u32 * self.tile_size) as i32 / 2,
+        );
+        self.pan_remainder = (0, 0);
+        self.scroll_by(0, 0);
+    }
+
     /// Return the map's width
     pub fn width(&self) -> usize {
         self.width
@@ -55,14 +203,84 @@ impl Map {
         self.height
     }
 
+    /// Return the coordinates of the top-left visible tile
+    pub fn scroll(&self) -> (usize, usize) {
+        self.scroll
+    }
+
+    /// Scroll the viewport by a number of tiles
+    ///
+    /// The viewport never scrolls past the map borders.
+    pub fn scroll_by(&mut self, dx: isize, dy: isize) {
+        let max_x = (self.width - self.viewport.0) as isize;
+        let max_y = (self.height - self.viewport.1) as isize;
+        let x = (self.scroll.0 as isize + dx).max(0).min(max_x);
+        let y = (self.scroll.1 as isize + dy).max(0).min(max_y);
+        self.scroll = (x as usize, y as usize);
+    }
+
+    /// Pan the viewport following a mouse movement in pixels
+    ///
+    /// Moving the mouse to the right scrolls the viewport to the left, as if
+    /// the map was dragged.
+    /// Movements smaller than a tile are accumulated until they add up to a
+    /// whole tile.
+    pub fn pan(&mut self, dx: i16, dy: i16) {
+        let tile_size = self.tile_size as i32;
+        self.pan_remainder.0 += dx as i32;
+        self.pan_remainder.1 += dy as i32;
+        let tiles_x = self.pan_remainder.0 / tile_size;
+        let tiles_y = self.pan_remainder.1 / tile_size;
+        self.pan_remainder.0 -= tiles_x * tile_size;
+        self.pan_remainder.1 -= tiles_y * tile_size;
+        self.scroll_by(-tiles_x as isize, -tiles_y as isize);
+    }
+
+    /// Scroll the viewport just enough to show a tile
+    pub fn scroll_to_tile(&mut self, x: usize, y: usize) {
+        let dx = if x < self.scroll.0 {
+            x as isize - self.scroll.0 as isize
+        } else if x >= self.scroll.0 + self.viewport.0 {
+            (x + 1 - self.scroll.0 - self.viewport.0) as isize
+        } else {
+            0
+        };
+        let dy = if y < self.scroll.1 {
+            y as isize - self.scroll.1 as isize
+        } else if y >= self.scroll.1 + self.viewport.1 {
+            (y + 1 - self.scroll.1 - self.viewport.1) as isize
+        } else {
+            0
+        };
+        self.scroll_by(dx, dy);
+    }
+
+    /// Map tile coordinates to screen coordinates
+    ///
+    /// Tiles outside the viewport have no screen coordinates.
+    pub fn tile_to_screen(&self, x: usize, y: usize) -> Option<(i32, i32)> {
+        let inside_x = self.scroll.0 <= x && x < self.scroll.0 + self.viewport.0;
+        let inside_y = self.scroll.1 <= y && y < self.scroll.1 + self.viewport.1;
+        if inside_x && inside_y {
+            Some((
+                self.offset.0 + (x - self.scroll.0) as i32 * self.tile_size as i32,
+                self.offset.1 + (y - self.scroll.1) as i32 * self.tile_size as i32,
+            ))
+        } else {
+            None
+        }
+    }
+
     /// Map mouse coordinates to tile coordinates
+    ///
+    /// Only tiles inside the viewport can be pointed at.
     pub fn mouse_to_tile(&self, mouse_x: i16, mouse_y: i16) -> Option<(usize, usize)> {
         let mouse_x = mouse_x - self.offset.0 as i16;
         let mouse_y = mouse_y - self.offset.1 as i16;
         if mouse_x < 0
             || mouse_y < 0
-            || mouse_x / self.tile_size as i16 >= self.width as i16
-            || mouse_y / self.tile_size as i16 >= self.height as i16
+            || mouse_x / self.tile_size as i16 >= self.viewport.0 as i16
+            || mouse_y / self.tile_size as i16 >= self.viewport.1 as i16
         {
             None
         } else {
@@ -70,66 +288,136 @@ impl Map {
                 mouse_x / self.tile_size as i16,
                 mouse_y / self.tile_size as i16,
             );
-            Some((x as usize, y as usize))
+            Some((x as usize + self.scroll.0, y as usize + self.scroll.1))
         }
     }
 
     /// Draw the map
-    pub fn draw(&self, mines: &[(usize, usize)]) {
-        for tx in 0..self.width {
-            for ty in 0..self.height {
+    ///
+    /// Only the tiles inside the viewport are drawn.
+    pub fn draw(&self) {
+        for tx in self.scroll.0..self.scroll.0 + self.viewport.0 {
+            for ty in self.scroll.1..self.scroll.1 + self.viewport.1 {
                 let tile = self.tile(tx, ty);
 
-                let x = tx as i32 * self.tile_size as i32;
-                let y = ty as i32 * self.tile_size as i32;
+                let x = (tx - self.scroll.0) as i32 * self.tile_size as i32;
+                let y = (ty - self.scroll.1) as i32 * self.tile_size as i32;
 
-                let is_mine = mines.iter().any(|(mx, my)| (*mx, *my) == (tx, ty));
-
-                let neighbour_mines = self.count_neighbour_mines(mines, tx, ty);
                 tile.draw(
                     self.offset.0 + x,
                     self.offset.1 + y,
                     self.tile_size,
-                    is_mine,
-                    neighbour_mines,
+                    self.is_mine(tx, ty),
+                    self.count_neighbour_mines(tx, ty),
                 );
             }
         }
     }
 
-    /// Handle mouse left clicks on the map
-    pub fn handle_left_click(&mut self, mouse_x: i16, mouse_y: i16, mines: &[(usize, usize)]) {
-        if let Some((x, y)) = self.mouse_to_tile(mouse_x, mouse_y) {
-            self.uncover_tile(x, y, mines)
-        }
+    /// Uncover the neighbours of a tile if enough of them are flagged
+    ///
+    /// If the number of neighbouring mines and the number of neighbouring
+    /// flagged tiles are the same, all non-flagged neighbouring tiles are
+    /// recursively uncovered.
+    pub fn chord_tile(&mut self, x: usize, y: usize) {
+        let candidates = self.find_neighbouring_uncoverable_tiles(x, y);
+        self.uncover_tiles(candidates)
+    }
+
+    /// Check whether an uncovered tile contains a mine
+    pub fn has_stepped_on_mine(&self) -> bool {
+        self.count_uncovered_mines() > 0
     }
 
-    /// Handle mouse right clicks on the map
-    pub fn handle_right_click(&mut self, mouse_x: i16, mouse_y: i16) {
-        if let Some((x, y)) = self.mouse_to_tile(mouse_x, mouse_y) {
-            self.flip_flagged_tile(x, y)
+    /// Parse a map from its ASCII representation
+    ///
+    /// Each line is a row, and each character is a tile:
+    ///
+    /// * `.` is a covered tile,
+    /// * `*` is a covered mine,
+    /// * `f` is a flagged tile without a mine,
+    /// * `F` is a flagged mine,
+    /// * `0` to `8` are uncovered tiles, showing the number of adjacent mines,
+    /// * `X` is an uncovered mine.
+    ///
+    /// Blank lines and whitespace between tiles are ignored.
+    /// Return `None` if a character is not valid, if rows have different
+    /// lengths, if the map is empty or too large, or if an uncovered tile shows
+    /// a wrong number.
+    pub fn from_ascii(text: &str, tile_size: u32, offset: (i32, i32)) -> Option<Self> {
+        let rows: Vec<Vec<char>> = text
+            .lines()
+            .map(|line| line.chars().filter(|c| !c.is_whitespace()).collect())
+            .filter(|row: &Vec<char>| !row.is_empty())
+            .collect();
+        let width = rows.first()?.len();
+        let height = rows.len();
+        if width > MAX_WIDTH || height > MAX_HEIGHT || rows.iter().any(|row| row.len() != width) {
+            return None;
         }
+
+        let mines: Vec<(usize, usize)> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|(x, y)| matches!(rows[*y][*x], '*' | 'F' | 'X'))
+            .collect();
+        let mut map = Self::new(width, height, tile_size, offset).with_mines(&mines);
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, character) in row.iter().enumerate() {
+                match character {
+                    '.' | '*' => {}
+                    'f' | 'F' => map.flag_individual_tile(x, y),
+                    'X' => map.uncover_individual_tile(x, y),
+                    digit => {
+                        let number = digit.to_digit(10)? as usize;
+                        if number != map.count_neighbour_mines(x, y) {
+                            return None;
+                        }
+                        map.uncover_individual_tile(x, y);
+                    }
+                }
+            }
+        }
+
+        Some(map)
     }
 
-    /// Handle simultaneous mouse left and right clicks on the map
-    pub fn handle_left_and_right_click(
-        &mut self,
-        mouse_x: i16,
-        mouse_y: i16,
-        mines: &[(usize, usize)],
-    ) {
-        if let Some((x, y)) = self.mouse_to_tile(mouse_x, mouse_y) {
-            let candidates = self.find_neighbouring_uncoverable_tiles(x, y, mines);
-            self.uncover_tiles(candidates, mines)
+    /// Return the ASCII representation of the map
+    ///
+    /// The format is described in [`Map::from_ascii`], and every row ends with
+    /// a newline.
+    pub fn to_ascii(&self) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let character = match (self.tile(x, y), self.is_mine(x, y)) {
+                    (Tile::Covered, false) => '.',
+                    (Tile::Covered, true) => '*',
+                    (Tile::Flagged, false) => 'f',
+                    (Tile::Flagged, true) => 'F',
+                    (Tile::Uncovered, false) => {
+                        let number = self.count_neighbour_mines(x, y) as u32;
+                        char::from_digit(number, 10).unwrap_or('?')
+                    }
+                    (Tile::Uncovered, true) => 'X',
+                };
+                text.push(character);
+            }
+            text.push('\n');
         }
+        text
     }
 
-    /// Check whether an uncovered tile contains a mine
-    pub fn has_stepped_on_mine(&self, mines: &[(usize, usize)]) -> bool {
-        mines
-            .iter()
-            .map(|(x, y)| self.tile(*x, *y))
-            .any(|tile| matches!(tile, Tile::Uncovered))
+    /// Check whether all safe tiles are uncovered
+    pub fn is_cleared(&self) -> bool {
+        self.count_uncovered_tiles() + self.mines_count == self.width * self.height
+    }
+
+    /// Count the mines not yet flagged
+    ///
+    /// Every flagged tile counts as a found mine, even if it is not one.
+    pub fn count_remaining_mines(&self) -> usize {
+        self.mines_count.saturating_sub(self.count_flagged_tiles())
     }
 
     /// Count the flagged tiles
@@ -148,6 +436,94 @@ impl Map {
             .count()
     }
 
+    /// Count the uncovered tiles not covering a mine
+    pub fn count_uncovered_safe_tiles(&self) -> usize {
+        self.count_uncovered_tiles() - self.count_uncovered_mines()
+    }
+
+    fn count_uncovered_mines(&self) -> usize {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|(x, y)| self.is_mine(*x, *y) && matches!(self.tile(*x, *y), Tile::Uncovered))
+            .count()
+    }
+
+    /// Return the 3BV of the board, i.e. the minimum number of left clicks
+    /// needed to clear it
+    ///
+    /// Each opening, i.e. each connected area of tiles without neighbouring
+    /// mines together with its border, takes one click.
+    /// Each other safe tile takes one click as well.
+    pub fn three_bv(&self) -> usize {
+        self.count_three_bv(false)
+    }
+
+    /// Return the 3BV of the cleared part of the board
+    ///
+    /// An opening is cleared as soon as one of its tiles without neighbouring
+    /// mines is uncovered, since that uncovers the whole opening.
+    pub fn solved_three_bv(&self) -> usize {
+        self.count_three_bv(true)
+    }
+
+    fn count_three_bv(&self, only_solved: bool) -> usize {
+        let is_opening = |x: usize, y: usize| {
+            !self.is_mine(x, y) && self.neighbour_mines[x + y * self.width] == 0
+        };
+        let is_uncovered = |x: usize, y: usize| matches!(self.tile(x, y), Tile::Uncovered);
+
+        let mut marked = vec![false; self.tiles.len()];
+        let mut three_bv = 0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = x + y * self.width;
+                if marked[index] || !is_opening(x, y) {
+                    continue;
+                }
+
+                marked[index] = true;
+                let mut solved = false;
+                // Tiles are stored by index to save memory on large openings
+                let mut openings = vec![index as u16];
+                while let Some(opening) = openings.pop() {
+                    let (ox, oy) = (opening as usize % self.width, opening as usize / self.width);
+                    solved |= is_uncovered(ox, oy);
+                    for (nx, ny) in self.neighbours(ox, oy) {
+                        let neighbour = nx + ny * self.width;
+                        if !marked[neighbour] {
+                            marked[neighbour] = true;
+                            if is_opening(nx, ny) {
+                                openings.push(neighbour as u16);
+                            }
+                        }
+                    }
+                }
+                if solved || !only_solved {
+                    three_bv += 1;
+                }
+            }
+        }
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = x + y * self.width;
+                if !self.is_mine(x, y) && !marked[index] && (is_uncovered(x, y) || !only_solved) {
+                    three_bv += 1;
+                }
+            }
+        }
+
+        three_bv
+    }
+
+    /// Restore the state of a tile
+    ///
+    /// Unlike other moves, any state can be restored, e.g. to cover an
+    /// uncovered tile when undoing a move.
+    pub fn restore_tile(&mut self, x: usize, y: usize, tile: Tile) {
+        self.tiles[x + y * self.width] = tile;
+    }
+
     /// Flag a tile
     ///
     /// If the tile is already flagged or uncovered, it does nothing.
@@ -159,6 +535,13 @@ impl Map {
         }
     }
 
+    /// Flag all covered tiles containing a mine
+    pub fn flag_mines(&mut self) {
+        for (x, y) in self.mines() {
+            self.flag_tile(x, y);
+        }
+    }
+
     /// Flip a tile
     ///
     /// This function flip the flagged status, from flagged to covered and
@@ -177,41 +560,61 @@ impl Map {
     /// number of neighbouring flagged tiles are compared.
     /// If they are the same, all non-flagged neighbouring tiles are also
     /// recursively uncovered.
-    pub fn uncover_tile(&mut self, initial_x: usize, initial_y: usize, mines: &[(usize, usize)]) {
+    pub fn uncover_tile(&mut self, initial_x: usize, initial_y: usize) {
         let tiles = vec![(initial_x, initial_y)];
-        self.uncover_tiles(tiles, mines)
-    }
-
-    fn uncover_tiles(
-        &mut self,
-        mut tiles_to_uncover: Vec<(usize, usize)>,
-        mines: &[(usize, usize)],
-    ) {
-        while let Some((x, y)) = tiles_to_uncover.pop() {
-            match self.tile(x, y) {
-                Tile::Uncovered => continue,
-                Tile::Covered => {
-                    self.uncover_individual_tile(x, y);
-                    let candidates = self.find_neighbouring_uncoverable_tiles(x, y, mines);
-                    for (cx, cy) in candidates {
-                        let tile = (cx as usize, cy as usize);
-                        if !tiles_to_uncover.iter().any(|t| *t == tile) {
-                            tiles_to_uncover.push(tile);
-                        }
-                    }
+        self.uncover_tiles(tiles)
+    }
+
+    /// Uncover a tile regardless of the flags around it
+    ///
+    /// If the tile has no adjacent mines, its neighbours are recursively
+    /// uncovered, as in an opening.
+    /// Unlike [`Map::uncover_tile`], flagged neighbours never cause further
+    /// tiles to be uncovered, so a wrong flag cannot lead to uncovering a
+    /// mine.
+    pub fn uncover_tile_ignoring_flags(&mut self, initial_x: usize, initial_y: usize) {
+        let mut tiles_to_uncover = Vec::new();
+        self.push_covered_tile(&mut tiles_to_uncover, initial_x, initial_y);
+        while let Some(index) = tiles_to_uncover.pop() {
+            let (x, y) = (index as usize % self.width, index as usize / self.width);
+            self.uncover_individual_tile(x, y);
+            if !self.is_mine(x, y) && self.count_neighbour_mines(x, y) == 0 {
+                for (nx, ny) in self.neighbours(x, y) {
+                    self.push_covered_tile(&mut tiles_to_uncover, nx, ny);
+                }
+            }
+        }
+    }
+
+    fn uncover_tiles(&mut self, tiles: Vec<(usize, usize)>) {
+        let mut tiles_to_uncover = Vec::new();
+        for (x, y) in tiles {
+            self.push_covered_tile(&mut tiles_to_uncover, x, y);
+        }
+        while let Some(index) = tiles_to_uncover.pop() {
+            let (x, y) = (index as usize % self.width, index as usize / self.width);
+            self.uncover_individual_tile(x, y);
+            if !self.is_mine(x, y) {
+                for (cx, cy) in self.find_neighbouring_uncoverable_tiles(x, y) {
+                    self.push_covered_tile(&mut tiles_to_uncover, cx, cy);
                 }
-                Tile::Flagged => continue,
             }
         }
     }
 
-    fn find_neighbouring_uncoverable_tiles(
-        &self,
-        x: usize,
-        y: usize,
-        mines: &[(usize, usize)],
-    ) -> Vec<(usize, usize)> {
-        let neighbour_mines = self.count_neighbour_mines(mines, x, y);
+    /// Add a covered tile to the tiles to uncover, unless it is already there
+    ///
+    /// Tiles are stored by index, and only covered tiles are stored, to bound
+    /// the memory used by large openings.
+    fn push_covered_tile(&self, tiles_to_uncover: &mut Vec<u16>, x: usize, y: usize) {
+        let index = (x + y * self.width) as u16;
+        if *self.tile(x, y) == Tile::Covered && !tiles_to_uncover.contains(&index) {
+            tiles_to_uncover.push(index);
+        }
+    }
+
+    fn find_neighbouring_uncoverable_tiles(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let neighbour_mines = self.count_neighbour_mines(x, y);
         let neighbour_flags = self.count_neighbour_flags(x, y);
         if neighbour_mines == neighbour_flags {
             self.neighbours(x, y)
@@ -220,25 +623,21 @@ impl Map {
         }
     }
 
-    fn count_neighbour_mines(&self, mines: &[(usize, usize)], x: usize, y: usize) -> usize {
-        mines
-            .iter()
-            .filter(|(mx, my)| {
-                let horizontally_adjacent = (*mx as i32 - x as i32).abs() <= 1;
-                let vertically_adjacent = (*my as i32 - y as i32).abs() <= 1;
-                horizontally_adjacent && vertically_adjacent
-            })
-            .count()
+    /// Count the mines adjacent to a tile
+    pub fn count_neighbour_mines(&self, x: usize, y: usize) -> usize {
+        self.neighbour_mines[x + y * self.width] as usize
     }
 
-    fn count_neighbour_flags(&self, x: usize, y: usize) -> usize {
+    /// Count the flagged tiles adjacent to a tile
+    pub fn count_neighbour_flags(&self, x: usize, y: usize) -> usize {
         self.neighbours(x, y)
             .into_iter()
             .filter(|(nx, ny)| matches!(self.tile(*nx, *ny), Tile::Flagged))
             .count()
     }
 
-    fn neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+    /// Return the coordinates of the tiles adjacent to a tile
+    pub fn neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
         // Must be a signed type to check for negative indices
         let x = x as i32;
         let y = y as i32;
@@ -260,7 +659,8 @@ impl Map {
         .collect()
     }
 
-    fn tile(&self, x: usize, y: usize) -> &Tile {
+    /// Return the tile at the given coordinates
+    pub fn tile(&self, x: usize, y: usize) -> &Tile {
         &self.tiles[x + y * self.width]
     }
 
@@ -276,3 +676,373 @@ impl Map {
         self.tiles[x + y * self.width] = Tile::Covered;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_fit_in_bitmasks() {
+        assert!(MAX_WIDTH <= u32::BITS as usize);
+    }
+
+    #[test]
+    fn mouse_to_tile_without_viewport() {
+        let map = Map::new(16, 14, 10, (0, 20));
+        assert_eq!(map.mouse_to_tile(5, 25), Some((0, 0)));
+        assert_eq!(map.mouse_to_tile(159, 159), Some((15, 13)));
+        assert_eq!(map.mouse_to_tile(5, 15), None);
+    }
+
+    #[test]
+    fn mouse_to_tile_with_scroll() {
+        let mut map = Map::new(30, 16, 10, (0, 20)).with_area((0, 20, 160, 140));
+        assert_eq!(map.mouse_to_tile(159, 159), Some((15, 13)));
+
+        map.scroll_by(5, 1);
+        assert_eq!(map.scroll(), (5, 1));
+        assert_eq!(map.mouse_to_tile(5, 25), Some((5, 1)));
+        assert_eq!(map.mouse_to_tile(159, 159), Some((20, 14)));
+    }
+
+    #[test]
+    fn scroll_stops_at_borders() {
+        let mut map = Map::new(30, 16, 10, (0, 20)).with_area((0, 20, 160, 140));
+        map.scroll_by(-3, -3);
+        assert_eq!(map.scroll(), (0, 0));
+        map.scroll_by(100, 100);
+        assert_eq!(map.scroll(), (14, 2));
+    }
+
+    #[test]
+    fn pan_accumulates_movements() {
+        let mut map = Map::new(30, 16, 10, (0, 20)).with_area((0, 20, 160, 140));
+        map.scroll_by(10, 0);
+        map.pan(6, 0);
+        assert_eq!(map.scroll(), (10, 0));
+        map.pan(6, 0);
+        assert_eq!(map.scroll(), (9, 0));
+        map.pan(-25, 0);
+        assert_eq!(map.scroll(), (11, 0));
+    }
+
+    #[test]
+    fn mouse_to_tile_at_each_zoom() {
+        let mut map = Map::new(32, 32, 10, (0, 20)).with_area((0, 20, 160, 140));
+        for &tile_size in ZOOM_LEVELS.iter() {
+            map.set_tile_size(tile_size);
+            assert_eq!(map.tile_size(), tile_size);
+
+            let (x, y) = (map.offset.0 as i16, map.offset.1 as i16);
+            let size = tile_size as i16;
+            let (sx, sy) = map.scroll();
+            assert_eq!(map.mouse_to_tile(x, y), Some((sx, sy)));
+            assert_eq!(
+                map.mouse_to_tile(x + 2 * size - 1, y + 3 * size),
+                Some((sx + 1, sy + 3))
+            );
+            assert_eq!(map.mouse_to_tile(x - 1, y), None);
+
+            let (columns, rows) = map.viewport;
+            assert_eq!(columns, core::cmp::min(32, 160 / tile_size as usize));
+            assert_eq!(rows, core::cmp::min(32, 140 / tile_size as usize));
+            assert_eq!(map.mouse_to_tile(x + columns as i16 * size, y), None);
+            assert_eq!(map.mouse_to_tile(x, y + rows as i16 * size), None);
+        }
+    }
+
+    #[test]
+    fn zoom_is_centred() {
+        let mut map = Map::new(16, 14, 10, (0, 20)).with_area((0, 20, 160, 140));
+        assert_eq!(map.offset, (0, 20));
+
+        map.zoom_in();
+        assert_eq!(map.tile_size(), 16);
+        assert_eq!(map.viewport, (10, 8));
+        assert_eq!(map.scroll(), (3, 3));
+        assert_eq!(map.offset, (0, 26));
+
+        map.zoom_out();
+        map.zoom_out();
+        assert_eq!(map.tile_size(), 8);
+        assert_eq!(map.viewport, (16, 14));
+        assert_eq!(map.scroll(), (0, 0));
+        assert_eq!(map.offset, (16, 34));
+
+        map.zoom_out();
+        map.zoom_out();
+        assert_eq!(map.tile_size(), 6);
+    }
+
+    #[test]
+    fn scroll_to_tile_shows_it() {
+        let mut map = Map::new(30, 16, 10, (0, 20)).with_area((0, 20, 160, 140));
+        assert_eq!(map.tile_to_screen(20, 0), None);
+
+        map.scroll_to_tile(20, 15);
+        assert_eq!(map.scroll(), (5, 2));
+        assert_eq!(map.tile_to_screen(20, 15), Some((150, 150)));
+
+        map.scroll_to_tile(7, 3);
+        assert_eq!(map.scroll(), (5, 2));
+
+        map.scroll_to_tile(0, 0);
+        assert_eq!(map.scroll(), (0, 0));
+        assert_eq!(map.tile_to_screen(0, 0), Some((0, 20)));
+    }
+
+    fn board(text: &str) -> Map {
+        Map::from_ascii(text, 10, (0, 0)).unwrap()
+    }
+
+    fn assert_board(map: &Map, expected: &str) {
+        assert_eq!(map.to_ascii(), board(expected).to_ascii());
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        let map = board(
+            "
+            . * f 0
+            F X 2 0
+            ",
+        );
+        assert_eq!(map.width(), 4);
+        assert_eq!(map.height(), 2);
+        assert_eq!(map.mines(), vec![(1, 0), (0, 1), (1, 1)]);
+        assert_eq!(map.count_flagged_tiles(), 2);
+        assert!(map.has_stepped_on_mine());
+        assert_eq!(map.to_ascii(), ".*f0\nFX20\n");
+        assert_eq!(board(&map.to_ascii()).to_ascii(), map.to_ascii());
+    }
+
+    #[test]
+    fn invalid_ascii() {
+        assert!(Map::from_ascii("", 10, (0, 0)).is_none());
+        assert!(Map::from_ascii(".?.", 10, (0, 0)).is_none());
+        assert!(Map::from_ascii("..\n...", 10, (0, 0)).is_none());
+        assert!(Map::from_ascii("*2", 10, (0, 0)).is_none());
+        assert!(Map::from_ascii(&".".repeat(MAX_WIDTH + 1), 10, (0, 0)).is_none());
+    }
+
+    #[test]
+    fn uncovering_floods_openings() {
+        let mut map = board(
+            "
+            . . . .
+            . . . .
+            . . . *
+            ",
+        );
+        map.uncover_tile(0, 0);
+        assert_board(
+            &map,
+            "
+            0 0 0 0
+            0 0 1 1
+            0 0 1 *
+            ",
+        );
+    }
+
+    #[test]
+    fn uncovering_keeps_flags() {
+        let mut map = board(
+            "
+            . . . .
+            . . . .
+            . . . F
+            ",
+        );
+        map.uncover_tile(0, 0);
+        assert_board(
+            &map,
+            "
+            0 0 0 0
+            0 0 1 1
+            0 0 1 F
+            ",
+        );
+    }
+
+    #[test]
+    fn uncovering_ignoring_flags() {
+        let text = "
+            0 0 1 . f
+            0 0 1 * .
+            0 0 1 1 1
+            ";
+
+        // The wrong flag makes the tile look chorded, uncovering the mine
+        let mut map = board(text);
+        map.uncover_tile(3, 0);
+        assert!(map.has_stepped_on_mine());
+
+        let mut map = board(text);
+        map.uncover_tile_ignoring_flags(3, 0);
+        assert_board(
+            &map,
+            "
+            0 0 1 1 f
+            0 0 1 * .
+            0 0 1 1 1
+            ",
+        );
+    }
+
+    #[test]
+    fn uncovering_ignoring_flags_floods_openings() {
+        let mut map = board(
+            "
+            . . . .
+            . . . .
+            f . . *
+            ",
+        );
+        map.uncover_tile_ignoring_flags(0, 0);
+        assert_board(
+            &map,
+            "
+            0 0 0 0
+            0 0 1 1
+            f 0 1 *
+            ",
+        );
+    }
+
+    #[test]
+    fn chording_uncovers_neighbours() {
+        let mut map = board(
+            "
+            . F .
+            1 1 1
+            ",
+        );
+        map.chord_tile(1, 1);
+        assert_board(
+            &map,
+            "
+            1 F 1
+            1 1 1
+            ",
+        );
+
+        // Chording next to a wrong flag uncovers the mine
+        let mut map = board(
+            "
+            f * .
+            1 1 1
+            ",
+        );
+        map.chord_tile(0, 1);
+        assert!(map.has_stepped_on_mine());
+        assert_board(
+            &map,
+            "
+            f X .
+            1 1 1
+            ",
+        );
+    }
+
+    #[test]
+    fn chording_requires_matching_flags() {
+        let mut map = board(
+            "
+            . * .
+            1 1 1
+            ",
+        );
+        map.chord_tile(1, 1);
+        assert_eq!(map.count_uncovered_tiles(), 3);
+    }
+
+    #[test]
+    fn win_detection() {
+        let mut map = board(
+            "
+            0 1 .
+            0 1 *
+            ",
+        );
+        assert!(!map.is_cleared());
+        map.uncover_tile(2, 0);
+        assert!(map.is_cleared());
+
+        // Flags are not required
+        assert_eq!(map.count_flagged_tiles(), 0);
+    }
+
+    #[test]
+    fn mines_are_precomputed() {
+        let map = Map::new(4, 3, 10, (0, 0)).with_mines(&[(0, 0), (2, 1), (3, 2)]);
+        assert_eq!(map.mines_count(), 3);
+        assert!(map.is_mine(0, 0));
+        assert!(map.is_mine(3, 2));
+        assert!(!map.is_mine(1, 0));
+        assert_eq!(map.count_neighbour_mines(1, 1), 2);
+        assert_eq!(map.count_neighbour_mines(2, 2), 2);
+        assert_eq!(map.count_neighbour_mines(3, 0), 1);
+        assert_eq!(map.count_neighbour_mines(0, 2), 0);
+    }
+
+    #[test]
+    fn stepping_on_mine_is_detected() {
+        let mut map = Map::new(3, 1, 10, (0, 0)).with_mines(&[(1, 0)]);
+        map.uncover_tile(0, 0);
+        assert!(!map.has_stepped_on_mine());
+        assert_eq!(map.count_uncovered_safe_tiles(), 1);
+        map.uncover_tile(1, 0);
+        assert!(map.has_stepped_on_mine());
+        assert_eq!(map.count_uncovered_safe_tiles(), 1);
+    }
+
+    #[test]
+    fn clearing_and_flagging_mines() {
+        let mut map = Map::new(3, 2, 10, (0, 0)).with_mines(&[(2, 0), (0, 1)]);
+        assert_eq!(map.mines(), vec![(2, 0), (0, 1)]);
+        assert_eq!(map.count_remaining_mines(), 2);
+
+        // Wrong flags still count as found mines
+        map.flag_tile(1, 0);
+        map.flag_tile(1, 1);
+        map.flag_tile(2, 1);
+        assert_eq!(map.count_remaining_mines(), 0);
+
+        for (x, y) in [(1, 0), (1, 1), (2, 1)].iter() {
+            map.flip_flagged_tile(*x, *y);
+        }
+        for (x, y) in [(0, 0), (1, 0), (1, 1), (2, 1)].iter() {
+            assert!(!map.is_cleared());
+            map.uncover_tile(*x, *y);
+        }
+        assert!(map.is_cleared());
+
+        map.flag_mines();
+        assert_eq!(map.count_flagged_tiles(), 2);
+        assert_eq!(map.count_remaining_mines(), 0);
+    }
+
+    #[test]
+    fn three_bv_counts_openings_and_isolated_tiles() {
+        // A single opening surrounding a mine in a corner
+        let map = Map::new(3, 3, 10, (0, 0)).with_mines(&[(0, 0)]);
+        assert_eq!(map.three_bv(), 1);
+
+        // Two numbered tiles next to a mine in the middle
+        let mut map = Map::new(3, 1, 10, (0, 0)).with_mines(&[(1, 0)]);
+        assert_eq!(map.three_bv(), 2);
+        assert_eq!(map.solved_three_bv(), 0);
+        map.uncover_tile(2, 0);
+        assert_eq!(map.solved_three_bv(), 1);
+
+        // An opening and three isolated numbered tiles
+        let mut map = Map::new(5, 3, 10, (0, 0)).with_mines(&[(3, 0), (3, 2), (4, 1)]);
+        assert_eq!(map.three_bv(), 4);
+        map.uncover_tile(0, 0);
+        assert_eq!(map.solved_three_bv(), 1);
+        map.uncover_tile(4, 0);
+        map.uncover_tile(4, 2);
+        assert_eq!(map.solved_three_bv(), 3);
+    }
+}
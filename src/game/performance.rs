@@ -0,0 +1,98 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Performance metrics of games
+
+use crate::time::FRAMES_PER_SECOND;
+
+/// Clicks made during a game
+///
+/// Gamepad buttons count as the clicks they are equivalent to.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Clicks {
+    /// Number of left clicks, i.e. uncovered tiles
+    pub left: u16,
+
+    /// Number of right clicks, i.e. flagged and unflagged tiles
+    pub right: u16,
+
+    /// Number of chords
+    pub chord: u16,
+}
+
+impl Clicks {
+    /// Return the total number of clicks
+    pub fn total(&self) -> u16 {
+        self.left
+            .saturating_add(self.right)
+            .saturating_add(self.chord)
+    }
+}
+
+/// Performance of a game
+///
+/// The performance of a game is measured by its 3BV, i.e. the minimum number
+/// of left clicks needed to clear the board, relative to the time taken and
+/// to the number of clicks actually made.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Performance {
+    /// 3BV of the cleared part of the board
+    pub three_bv: u16,
+
+    /// Total number of clicks
+    pub clicks: u16,
+}
+
+impl Performance {
+    /// Return the 3BV per second in hundredths, given the time in frames
+    ///
+    /// Return `None` if no time elapsed.
+    pub fn three_bv_per_second(&self, frames: u32) -> Option<u32> {
+        match frames {
+            0 => None,
+            frames => Some(self.three_bv as u32 * 100 * FRAMES_PER_SECOND / frames),
+        }
+    }
+
+    /// Return the efficiency, i.e. the 3BV per click, as a percentage
+    ///
+    /// Return `None` if no click was made.
+    pub fn efficiency(&self) -> Option<u32> {
+        match self.clicks {
+            0 => None,
+            clicks => Some(100 * self.three_bv as u32 / clicks as u32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics() {
+        let clicks = Clicks {
+            left: 30,
+            right: 8,
+            chord: 2,
+        };
+        assert_eq!(clicks.total(), 40);
+
+        let performance = Performance {
+            three_bv: 35,
+            clicks: clicks.total(),
+        };
+        assert_eq!(performance.three_bv_per_second(20 * 60), Some(175));
+        assert_eq!(performance.three_bv_per_second(0), None);
+        assert_eq!(performance.efficiency(), Some(87));
+
+        let performance = Performance {
+            three_bv: 0,
+            clicks: 0,
+        };
+        assert_eq!(performance.efficiency(), None);
+    }
+}
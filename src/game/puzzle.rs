@@ -0,0 +1,246 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Hand-made puzzles
+
+use super::save::{Save, PUZZLES_SECTION, SECTION_HEADER_SIZE};
+use super::Map;
+
+/// Size of the stored progress, i.e. a bitmask of solved puzzles
+const RECORD_SIZE: usize = 4;
+
+/// Size of the stored progress, including the section header
+pub const PUZZLES_STORED_SIZE: usize = SECTION_HEADER_SIZE + RECORD_SIZE;
+
+/// A hand-made puzzle
+///
+/// A puzzle is a partially uncovered board, in the format parsed by
+/// [`Map::from_ascii`], which can be cleared by logic alone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Puzzle {
+    /// Name of the puzzle
+    pub name: &'static str,
+
+    board: &'static str,
+}
+
+impl Puzzle {
+    /// Create the map of the puzzle
+    ///
+    /// Return `None` if the board is not valid.
+    pub fn map(&self, tile_size: u32, offset: (i32, i32)) -> Option<Map> {
+        Map::from_ascii(self.board, tile_size, offset)
+    }
+}
+
+/// Available puzzles, from the easiest to the hardest
+pub const PUZZLES: [Puzzle; 8] = [
+    Puzzle {
+        name: "Warm up",
+        board: "
+            000000
+            000111
+            1111*.
+            .*....
+            .....*
+            ..*...
+            ",
+    },
+    Puzzle {
+        name: "Corners",
+        board: "
+            *1001*.
+            11001..
+            00111..
+            001*...
+            112....
+            .*.....
+            ......*
+            ",
+    },
+    Puzzle {
+        name: "One two one",
+        board: "
+            00000000
+            00000000
+            11211000
+            .*.*2110
+            .....*10
+            ......21
+            *...*..*
+            ...*....
+            ",
+    },
+    Puzzle {
+        name: "Stairs",
+        board: "
+            000001.*
+            000012*2
+            00012*..
+            0001*...
+            0112....
+            12*.....
+            2*......
+            *.......
+            ",
+    },
+    Puzzle {
+        name: "Checkers",
+        board: "
+            ..10001..
+            .*10001*.
+            ..21212..
+            ...*.*...
+            .........
+            ...*.*...
+            .........
+            .*.....*.
+            .........
+            ",
+    },
+    Puzzle {
+        name: "Pairs",
+        board: "
+            ..........
+            .**.......
+            .......*..
+            .......*..
+            ......211.
+            *...**101.
+            11....101*
+            01....112.
+            02*....*..
+            02*.......
+            ",
+    },
+    Puzzle {
+        name: "Walls",
+        board: "
+            ............
+            ....*.......
+            ....*...*...
+            ....*...*...
+            ..1111..*...
+            .*1001......
+            ..1002*.....
+            ..1112*....*
+            ...*......21
+            .........*10
+            ",
+    },
+    Puzzle {
+        name: "Final exam",
+        board: "
+            ...100001.1000
+            ..*111101*1111
+            .....*101...*.
+            .*....222.....
+            .......**.....
+            ....*......*..
+            ..............
+            .*....*...*...
+            .............*
+            ...*....*.....
+            ...........*..
+            *.....*.......
+            ",
+    },
+];
+
+/// Record of solved puzzles
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PuzzleProgress {
+    solved: u32,
+}
+
+impl PuzzleProgress {
+    /// Load the progress
+    pub fn load() -> Self {
+        Self::from_save(&Save::load())
+    }
+
+    /// Save the progress
+    ///
+    /// Other stored data is kept.
+    pub fn save(&self) {
+        let mut save = Save::load();
+        self.write(&mut save);
+        save.store();
+    }
+
+    /// Check whether a puzzle was solved
+    pub fn is_solved(&self, index: usize) -> bool {
+        self.solved & 1 << index != 0
+    }
+
+    /// Mark a puzzle as solved
+    pub fn mark_solved(&mut self, index: usize) {
+        self.solved |= 1 << index;
+    }
+
+    /// Return the number of solved puzzles
+    pub fn count_solved(&self) -> usize {
+        self.solved.count_ones() as usize
+    }
+
+    /// Read the progress from its section
+    ///
+    /// A section with unexpected size is ignored.
+    pub fn from_save(save: &Save) -> Self {
+        match save.section(PUZZLES_SECTION) {
+            Some(section) if section.len() == RECORD_SIZE => Self {
+                solved: u32::from_le_bytes([section[0], section[1], section[2], section[3]]),
+            },
+            _ => Self::default(),
+        }
+    }
+
+    /// Write the progress to its section
+    pub fn write(&self, save: &mut Save) {
+        save.set_section(PUZZLES_SECTION, self.solved.to_le_bytes().to_vec());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::{deduce, solve};
+
+    #[test]
+    fn puzzles_fit_in_progress() {
+        assert!(PUZZLES.len() <= 8 * RECORD_SIZE);
+    }
+
+    #[test]
+    fn puzzles_are_solvable_by_logic() {
+        for puzzle in PUZZLES.iter() {
+            let mut map = puzzle.map(10, (0, 0)).expect(puzzle.name);
+            assert!(map.width() <= 16 && map.height() <= 14, "{}", puzzle.name);
+            assert!(!map.is_cleared(), "{}", puzzle.name);
+            assert!(!deduce(&map).safe.is_empty(), "{}", puzzle.name);
+            assert!(solve(&mut map), "{}", puzzle.name);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut progress = PuzzleProgress::default();
+        progress.mark_solved(0);
+        progress.mark_solved(PUZZLES.len() - 1);
+        assert!(progress.is_solved(0));
+        assert!(!progress.is_solved(1));
+        assert_eq!(progress.count_solved(), 2);
+
+        let mut save = Save::new();
+        assert_eq!(PuzzleProgress::from_save(&save), PuzzleProgress::default());
+        progress.write(&mut save);
+        let section = save.section(PUZZLES_SECTION).unwrap();
+        assert_eq!(SECTION_HEADER_SIZE + section.len(), PUZZLES_STORED_SIZE);
+        let loaded = PuzzleProgress::from_save(&Save::from_bytes(&save.to_bytes()));
+        assert_eq!(loaded, progress);
+    }
+}
@@ -0,0 +1,458 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Recording of games
+
+use rand_xorshift::XorShiftRng;
+
+use super::{Difficulty, FirstClick, Generator, LayoutSearch, Seed};
+
+/// Number of bits used to encode a tile coordinate
+const COORDINATE_BITS: usize = 5;
+
+/// Number of bits used to encode the kind of an action
+const ACTION_BITS: usize = 2;
+
+/// An action performed by the player on a tile
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// A tile was uncovered
+    Uncover(usize, usize),
+
+    /// A tile was flagged or unflagged
+    Flag(usize, usize),
+
+    /// The neighbours of a tile were uncovered
+    Chord(usize, usize),
+
+    /// A tile was uncovered as a hint
+    Hint(usize, usize),
+}
+
+/// An action performed at a given frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Move {
+    /// Number of frames since the beginning of the game
+    pub frame: u32,
+
+    /// The performed action
+    pub action: Action,
+}
+
+/// A recorded game
+///
+/// A game can be fully re-simulated from its difficulty, its mine layout
+/// generator, its first click policy, its seed, its first uncovered tile and
+/// the sequence of moves.
+/// Changes of the view, such as scrolling and zooming, are not recorded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    difficulty: Difficulty,
+    generator: Generator,
+    first_click: FirstClick,
+    seed: Seed,
+    start: (usize, usize),
+    moves: Vec<Move>,
+    frame: u32,
+}
+
+impl Replay {
+    /// Create a new recording of a game started from a tile
+    pub fn new(
+        difficulty: Difficulty,
+        generator: Generator,
+        seed: Seed,
+        start: (usize, usize),
+    ) -> Self {
+        Self {
+            difficulty,
+            generator,
+            first_click: FirstClick::default(),
+            seed,
+            start,
+            moves: Vec::new(),
+            frame: 0,
+        }
+    }
+
+    /// Use a given first click policy instead of the default one
+    pub fn with_first_click(mut self, first_click: FirstClick) -> Self {
+        self.first_click = first_click;
+        self
+    }
+
+    /// Return the game difficulty
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Return the game seed
+    pub fn seed(&self) -> Seed {
+        self.seed
+    }
+
+    /// Return the first uncovered tile
+    pub fn start(&self) -> (usize, usize) {
+        self.start
+    }
+
+    /// Return the recorded moves
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// Return the number of recorded frames
+    pub fn frames(&self) -> u32 {
+        self.frame
+    }
+
+    /// Generate the mine layout of the game
+    ///
+    /// Return `None` if the generator could not place the mines.
+    pub fn mines(&self) -> Option<Vec<(usize, usize)>> {
+        self.generator.generate(
+            &mut self.seed.generator(),
+            self.difficulty.width(),
+            self.difficulty.height(),
+            self.difficulty.mines_count(),
+            self.start,
+            self.first_click,
+        )
+    }
+
+    /// Start searching the mine layout of the game
+    ///
+    /// The search finds the same layout as [`Replay::mines`].
+    pub fn search(&self) -> LayoutSearch<XorShiftRng> {
+        self.generator.search(
+            self.seed.generator(),
+            self.difficulty.width(),
+            self.difficulty.height(),
+            self.difficulty.mines_count(),
+            self.start,
+            self.first_click,
+        )
+    }
+
+    /// Advance to the next frame
+    ///
+    /// This function must be called at each frame of the game.
+    pub fn tick(&mut self) {
+        self.frame = self.frame.saturating_add(1);
+    }
+
+    /// Record an action at the current frame
+    pub fn record(&mut self, action: Action) {
+        self.moves.push(Move {
+            frame: self.frame,
+            action,
+        });
+    }
+
+    /// Forget all moves but the first ones
+    ///
+    /// Moves are forgotten when undone, while the number of recorded frames
+    /// is kept.
+    pub fn rewind(&mut self, moves_count: usize) {
+        self.moves.truncate(moves_count);
+    }
+
+    /// Encode the replay in a compact form
+    ///
+    /// The header contains the difficulty, the generator and the first click
+    /// policy in a single byte, followed by the map size and mines count for
+    /// custom games, the seed, the first uncovered tile and the number of
+    /// recorded frames.
+    /// Each move is encoded as two variable-length integers, the number of
+    /// frames since the previous move followed by the action kind and the
+    /// tile coordinates, which usually take three bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let difficulty_tag = match self.difficulty {
+            Difficulty::Easy => 0,
+            Difficulty::Medium => 1,
+            Difficulty::Hard => 2,
+            Difficulty::Custom { .. } => 3,
+        };
+        let generator_tag = match self.generator {
+            Generator::Random => 0,
+            Generator::NoGuess => 1,
+        };
+        let first_click_tag = match self.first_click {
+            FirstClick::Safe => 0,
+            FirstClick::Opening => 1,
+            FirstClick::Classic => 2,
+        };
+
+        let mut buffer = vec![difficulty_tag | generator_tag << 4 | first_click_tag << 6];
+        if let Difficulty::Custom {
+            width,
+            height,
+            mines,
+        } = self.difficulty
+        {
+            buffer.push(width as u8);
+            buffer.push(height as u8);
+            buffer.push(mines as u8);
+            buffer.push((mines >> 8) as u8);
+        }
+        buffer.extend_from_slice(&self.seed.value().to_le_bytes());
+        buffer.push(self.start.0 as u8);
+        buffer.push(self.start.1 as u8);
+        write_varint(&mut buffer, self.frame);
+
+        let mut previous_frame = 0;
+        for Move { frame, action } in &self.moves {
+            let (kind, (x, y)) = match *action {
+                Action::Uncover(x, y) => (0, (x, y)),
+                Action::Flag(x, y) => (1, (x, y)),
+                Action::Chord(x, y) => (2, (x, y)),
+                Action::Hint(x, y) => (3, (x, y)),
+            };
+            write_varint(&mut buffer, frame - previous_frame);
+            previous_frame = *frame;
+            let value = (kind << (2 * COORDINATE_BITS)) | (y << COORDINATE_BITS) as u32 | x as u32;
+            write_varint(&mut buffer, value);
+        }
+
+        buffer
+    }
+
+    /// Decode a replay encoded by [`Replay::to_bytes`]
+    ///
+    /// Return `None` if the buffer is not a valid replay.
+    pub fn from_bytes(buffer: &[u8]) -> Option<Self> {
+        let (&tag, mut buffer) = buffer.split_first()?;
+        let difficulty = match tag & 0x0f {
+            0 => Difficulty::Easy,
+            1 => Difficulty::Medium,
+            2 => Difficulty::Hard,
+            3 => {
+                let (custom, rest) = split_at(buffer, 4)?;
+                buffer = rest;
+                Difficulty::Custom {
+                    width: custom[0] as usize,
+                    height: custom[1] as usize,
+                    mines: (custom[2] as usize) | (custom[3] as usize) << 8,
+                }
+            }
+            _ => return None,
+        };
+        let generator = match (tag >> 4) & 0x03 {
+            0 => Generator::Random,
+            1 => Generator::NoGuess,
+            _ => return None,
+        };
+        let first_click = match tag >> 6 {
+            0 => FirstClick::Safe,
+            1 => FirstClick::Opening,
+            2 => FirstClick::Classic,
+            _ => return None,
+        };
+
+        let (header, mut buffer) = split_at(buffer, 6)?;
+        let seed = Seed::new(u32::from_le_bytes([
+            header[0], header[1], header[2], header[3],
+        ]));
+        let start = (header[4] as usize, header[5] as usize);
+        let mut replay =
+            Self::new(difficulty, generator, seed, start).with_first_click(first_click);
+        replay.frame = read_varint(&mut buffer)?;
+
+        let mask = (1 << COORDINATE_BITS) - 1;
+        let mut frame = 0u32;
+        while !buffer.is_empty() {
+            frame = frame.checked_add(read_varint(&mut buffer)?)?;
+            let value = read_varint(&mut buffer)?;
+            let x = (value & mask) as usize;
+            let y = ((value >> COORDINATE_BITS) & mask) as usize;
+            let kind = value >> (2 * COORDINATE_BITS);
+            if kind >= 1 << ACTION_BITS || x >= difficulty.width() || y >= difficulty.height() {
+                return None;
+            }
+            let action = match kind {
+                0 => Action::Uncover(x, y),
+                1 => Action::Flag(x, y),
+                2 => Action::Chord(x, y),
+                _ => Action::Hint(x, y),
+            };
+            replay.moves.push(Move { frame, action });
+        }
+
+        Some(replay)
+    }
+}
+
+/// Split a buffer at a position, returning `None` if it is too short
+fn split_at(buffer: &[u8], position: usize) -> Option<(&[u8], &[u8])> {
+    if buffer.len() < position {
+        None
+    } else {
+        Some(buffer.split_at(position))
+    }
+}
+
+/// Append an integer using seven bits per byte
+///
+/// The most significant bit of each byte is set on all bytes but the last.
+fn write_varint(buffer: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        buffer.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+/// Read an integer written by [`write_varint`] and advance the buffer
+fn read_varint(buffer: &mut &[u8]) -> Option<u32> {
+    let mut value: u32 = 0;
+    for shift in (0..32).step_by(7) {
+        let (&byte, rest) = buffer.split_first()?;
+        *buffer = rest;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::{MAX_HEIGHT, MAX_WIDTH};
+
+    #[test]
+    fn coordinates_fit_in_moves() {
+        assert!(MAX_WIDTH <= 1 << COORDINATE_BITS);
+        assert!(MAX_HEIGHT <= 1 << COORDINATE_BITS);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        let custom = Difficulty::Custom {
+            width: 32,
+            height: 30,
+            mines: 300,
+        };
+        let games = [
+            (Difficulty::Easy, Generator::NoGuess, FirstClick::Safe),
+            (Difficulty::Hard, Generator::Random, FirstClick::Opening),
+            (custom, Generator::Random, FirstClick::Classic),
+        ];
+        for (difficulty, generator, first_click) in games.iter() {
+            let mut replay =
+                Replay::new(*difficulty, *generator, seed, (3, 4)).with_first_click(*first_click);
+            replay.record(Action::Uncover(0, 0));
+            for _ in 0..1000 {
+                replay.tick();
+            }
+            replay.record(Action::Flag(15, 13));
+            replay.record(Action::Chord(1, 2));
+            replay.tick();
+            replay.record(Action::Hint(7, 8));
+
+            let bytes = replay.to_bytes();
+            assert_eq!(Replay::from_bytes(&bytes), Some(replay));
+        }
+    }
+
+    #[test]
+    fn long_pauses_round_trip() {
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        let mut replay = Replay::new(Difficulty::Easy, Generator::Random, seed, (0, 0));
+        replay.record(Action::Uncover(1, 1));
+        replay.frame = u32::MAX;
+        replay.record(Action::Flag(15, 13));
+
+        let bytes = replay.to_bytes();
+        assert_eq!(Replay::from_bytes(&bytes), Some(replay));
+    }
+
+    #[test]
+    fn moves_are_compact() {
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        let mut replay = Replay::new(Difficulty::Hard, Generator::Random, seed, (0, 0));
+        for i in 0..100 {
+            for _ in 0..60 {
+                replay.tick();
+            }
+            replay.record(Action::Uncover(i % 16, i % 14));
+        }
+        assert!(replay.to_bytes().len() <= 10 + 3 * 100);
+    }
+
+    #[test]
+    fn invalid_bytes_are_rejected() {
+        assert_eq!(Replay::from_bytes(&[]), None);
+        assert_eq!(Replay::from_bytes(&[0x07, 0, 0, 0, 0, 0, 0, 0]), None);
+        assert_eq!(Replay::from_bytes(&[0x00, 0, 0, 0]), None);
+        assert_eq!(Replay::from_bytes(&[0xc0, 0, 0, 0, 0, 0, 0, 0]), None);
+        // A move outside the map
+        assert_eq!(
+            Replay::from_bytes(&[0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0x1f]),
+            None
+        );
+    }
+
+    #[test]
+    fn records_frames() {
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        let mut replay = Replay::new(Difficulty::Easy, Generator::Random, seed, (0, 0));
+        replay.record(Action::Flag(1, 1));
+        replay.tick();
+        replay.tick();
+        replay.record(Action::Uncover(2, 2));
+        replay.tick();
+
+        assert_eq!(replay.frames(), 3);
+        assert_eq!(
+            replay.moves(),
+            &[
+                Move {
+                    frame: 0,
+                    action: Action::Flag(1, 1)
+                },
+                Move {
+                    frame: 2,
+                    action: Action::Uncover(2, 2)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rewind_forgets_moves() {
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        let mut replay = Replay::new(Difficulty::Easy, Generator::Random, seed, (0, 0));
+        replay.record(Action::Uncover(1, 1));
+        replay.tick();
+        replay.record(Action::Flag(2, 2));
+        replay.record(Action::Uncover(3, 3));
+        replay.tick();
+
+        replay.rewind(1);
+        assert_eq!(replay.frames(), 2);
+        assert_eq!(
+            replay.moves(),
+            &[Move {
+                frame: 0,
+                action: Action::Uncover(1, 1)
+            }]
+        );
+    }
+
+    #[test]
+    fn mines_avoid_start() {
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        let replay = Replay::new(Difficulty::Hard, Generator::NoGuess, seed, (5, 5));
+        let mines = replay.mines().unwrap();
+        assert_eq!(mines.len(), Difficulty::Hard.mines_count());
+        assert!(!mines.contains(&(5, 5)));
+        assert_eq!(Some(mines), replay.mines());
+    }
+}
@@ -0,0 +1,281 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Versioned storage of persistent data
+
+use crate::debug;
+use crate::wasm4::{diskr, diskw};
+
+use super::HighScores;
+
+/// Magic number at the beginning of stored data
+const MAGIC: [u8; 2] = *b"MS";
+
+/// Version of the storage format
+const VERSION: u8 = 1;
+
+/// Size of the header, i.e. the magic number, the version and the checksum
+const HEADER_SIZE: usize = 5;
+
+/// Size of the header of a section, i.e. the tag and the payload length
+pub const SECTION_HEADER_SIZE: usize = 3;
+
+/// Size of the WASM-4 disk
+pub const DISK_SIZE: usize = 1024;
+
+/// Tag of the section containing custom high scores
+pub const CUSTOM_SECTION: u8 = 2;
+
+/// Tag of the section containing the record of boards of the day
+pub const DAILY_SECTION: u8 = 3;
+
+/// Tag of a section containing a replay of a best game
+pub const REPLAY_SECTION: u8 = 4;
+
+/// Tag of the section containing the leaderboards of easy, medium and hard
+/// games
+pub const LEADERBOARD_SECTION: u8 = 5;
+
+/// Tag of the section containing the lifetime statistics
+pub const STATISTICS_SECTION: u8 = 6;
+
+/// Tag of the section containing the player settings
+pub const SETTINGS_SECTION: u8 = 7;
+
+/// Tag of the section containing the solved puzzles
+pub const PUZZLES_SECTION: u8 = 8;
+
+/// Persistent data
+///
+/// Data is stored as a header followed by a sequence of tagged sections.
+/// The header contains a magic number, the format version and a checksum of
+/// the version and the sections.
+/// Each section contains a tag, the length of its payload and the payload.
+/// Sections with unknown tags are kept untouched, so that data can be added
+/// without breaking older saves.
+///
+/// High scores saved before the introduction of this format are migrated on
+/// load.
+/// Corrupt data, or data saved by a newer version, is discarded.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Save {
+    sections: Vec<(u8, Vec<u8>)>,
+}
+
+impl Save {
+    /// Create an empty save
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the data from disk
+    pub fn load() -> Self {
+        let mut buffer: [u8; DISK_SIZE] = [0; DISK_SIZE];
+        let bytes_read = unsafe { diskr(&mut buffer as *mut u8, buffer.len() as u32) };
+        Self::from_bytes(&buffer[..bytes_read as usize])
+    }
+
+    /// Store the data to disk
+    pub fn store(&self) {
+        let mut buffer = self.to_bytes();
+        if buffer.len() > DISK_SIZE {
+            debug!("Save data is too large");
+            return;
+        }
+
+        let bytes_written = unsafe { diskw(buffer.as_mut_ptr(), buffer.len() as u32) };
+        if bytes_written != buffer.len() as u32 {
+            debug!("Failed to save data");
+        }
+    }
+
+    /// Return the payload of the first section with a tag
+    pub fn section(&self, tag: u8) -> Option<&[u8]> {
+        self.sections(tag).next()
+    }
+
+    /// Return the payloads of all sections with a tag
+    pub fn sections(&self, tag: u8) -> impl Iterator<Item = &[u8]> {
+        self.sections
+            .iter()
+            .filter(move |(other, _)| *other == tag)
+            .map(|(_, payload)| payload.as_slice())
+    }
+
+    /// Replace all sections with a tag by a new one
+    pub fn set_section(&mut self, tag: u8, payload: Vec<u8>) {
+        self.remove_sections(tag);
+        self.push_section(tag, payload);
+    }
+
+    /// Append a section
+    pub fn push_section(&mut self, tag: u8, payload: Vec<u8>) {
+        self.sections.push((tag, payload));
+    }
+
+    /// Remove all sections with a tag
+    pub fn remove_sections(&mut self, tag: u8) {
+        self.sections.retain(|(other, _)| *other != tag);
+    }
+
+    /// Decode data read from disk
+    ///
+    /// An empty buffer results in an empty save, and data without the magic
+    /// number is migrated from the original high scores layout.
+    pub fn from_bytes(buffer: &[u8]) -> Self {
+        if buffer.is_empty() {
+            Self::new()
+        } else if !buffer.starts_with(&MAGIC) {
+            let mut save = Self::new();
+            HighScores::from_legacy_bytes(buffer).write(&mut save);
+            save
+        } else {
+            Self::decode(buffer).unwrap_or_else(|| {
+                debug!("Discarding corrupt save data");
+                Self::new()
+            })
+        }
+    }
+
+    /// Encode data to be written to disk
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(DISK_SIZE);
+        buffer.extend_from_slice(&MAGIC);
+        buffer.push(VERSION);
+        buffer.extend_from_slice(&[0, 0]);
+        for (tag, payload) in &self.sections {
+            buffer.push(*tag);
+            buffer.push(payload.len() as u8);
+            buffer.push((payload.len() >> 8) as u8);
+            buffer.extend_from_slice(payload);
+        }
+
+        let checksum = checksum(VERSION, &buffer[HEADER_SIZE..]);
+        buffer[3] = checksum as u8;
+        buffer[4] = (checksum >> 8) as u8;
+
+        buffer
+    }
+
+    /// Decode data with a header
+    ///
+    /// Return `None` if the data is corrupt or has an unsupported version.
+    fn decode(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < HEADER_SIZE {
+            return None;
+        }
+        let (header, mut body) = buffer.split_at(HEADER_SIZE);
+        let version = header[2];
+        let checksum_stored = (header[3] as u16) | (header[4] as u16) << 8;
+        if version != VERSION || checksum(version, body) != checksum_stored {
+            return None;
+        }
+
+        let mut save = Self::new();
+        while !body.is_empty() {
+            if body.len() < SECTION_HEADER_SIZE {
+                return None;
+            }
+            let tag = body[0];
+            let length = (body[1] as usize) | (body[2] as usize) << 8;
+            let payload = body.get(SECTION_HEADER_SIZE..SECTION_HEADER_SIZE + length)?;
+            save.push_section(tag, payload.to_vec());
+            body = &body[SECTION_HEADER_SIZE + length..];
+        }
+
+        Some(save)
+    }
+}
+
+/// Compute the Fletcher-16 checksum of the version and the sections
+fn checksum(version: u8, body: &[u8]) -> u16 {
+    let (mut low, mut high) = (0u16, 0u16);
+    for byte in std::iter::once(&version).chain(body) {
+        low = (low + *byte as u16) % 255;
+        high = (high + low) % 255;
+    }
+    high << 8 | low
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::Difficulty;
+
+    #[test]
+    fn round_trip() {
+        let mut save = Save::new();
+        save.push_section(REPLAY_SECTION, vec![1, 2, 3]);
+        save.push_section(REPLAY_SECTION, vec![4]);
+        save.set_section(DAILY_SECTION, vec![]);
+        save.set_section(200, vec![0xaa; 300]);
+
+        let bytes = save.to_bytes();
+        assert!(bytes.starts_with(&MAGIC));
+        let loaded = Save::from_bytes(&bytes);
+        assert_eq!(loaded, save);
+        assert_eq!(
+            loaded.sections(REPLAY_SECTION).collect::<Vec<_>>(),
+            vec![&[1, 2, 3][..], &[4][..]]
+        );
+        assert_eq!(loaded.section(DAILY_SECTION), Some(&[][..]));
+        assert_eq!(loaded.section(200).map(|payload| payload.len()), Some(300));
+        assert_eq!(loaded.section(CUSTOM_SECTION), None);
+    }
+
+    #[test]
+    fn set_section_replaces() {
+        let mut save = Save::new();
+        save.push_section(REPLAY_SECTION, vec![1]);
+        save.push_section(REPLAY_SECTION, vec![2]);
+        save.set_section(REPLAY_SECTION, vec![3]);
+        assert_eq!(
+            save.sections(REPLAY_SECTION).collect::<Vec<_>>(),
+            vec![&[3][..]]
+        );
+    }
+
+    #[test]
+    fn empty_disk() {
+        assert_eq!(Save::from_bytes(&[]), Save::new());
+    }
+
+    #[test]
+    fn corrupt_data_is_discarded() {
+        let mut save = Save::new();
+        save.set_section(
+            CUSTOM_SECTION,
+            vec![8, 8, 10, 0, 0x2c, 0x01, 0, 0, 0, 0, 0, 0, 0],
+        );
+        let bytes = save.to_bytes();
+
+        // Flipped bit
+        let mut corrupt = bytes.clone();
+        corrupt[HEADER_SIZE + 4] ^= 0x10;
+        assert_eq!(Save::from_bytes(&corrupt), Save::new());
+
+        // Truncated data
+        assert_eq!(Save::from_bytes(&bytes[..bytes.len() - 1]), Save::new());
+        assert_eq!(Save::from_bytes(&bytes[..3]), Save::new());
+
+        // Newer version
+        let mut newer = bytes;
+        newer[2] = VERSION + 1;
+        assert_eq!(Save::from_bytes(&newer), Save::new());
+    }
+
+    #[test]
+    fn legacy_data_is_migrated() {
+        let buffer = [0xff, 0x2c, 0x01, 0x00, 0x00, 0x00, 0xff, 0x10, 0x00];
+        let save = Save::from_bytes(&buffer);
+        assert!(save.section(LEADERBOARD_SECTION).is_some());
+
+        let save = Save::from_bytes(&save.to_bytes());
+        let highscores = HighScores::from_save(&save);
+        assert_eq!(highscores.get(Difficulty::Easy).unwrap().time, 300 * 60);
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Seeds for mine layouts
+
+use core::ptr::addr_of_mut;
+
+use rand_core::{RngCore, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+use crate::random::RNG;
+
+/// Characters used in seed codes
+///
+/// Letters and digits that are easily confused, i.e. `I`, `O`, `0` and `1`,
+/// are not used.
+pub const CODE_ALPHABET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Number of characters in a seed code
+pub const CODE_LENGTH: usize = 6;
+
+/// Number of bits encoded by each character of a seed code
+const BITS_PER_CHARACTER: usize = 5;
+
+/// Number of distinct seeds, i.e. all seeds that fit in a seed code
+const SEEDS_COUNT: u32 = 1 << (BITS_PER_CHARACTER * CODE_LENGTH);
+
+/// A seed for the mine layout generator
+///
+/// A game is fully determined by its seed, its difficulty, its mine layout
+/// generator and the first uncovered tile.
+/// Seeds can be shared as short codes, so that other players can play the
+/// same map.
+///
+/// The mine layout for a given seed must never change across versions, so
+/// the random generator constructed from it must not change either.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Seed(u32);
+
+impl Seed {
+    /// Create a new seed
+    ///
+    /// Values that do not fit in a seed code are wrapped around.
+    pub fn new(value: u32) -> Self {
+        Self(value % SEEDS_COUNT)
+    }
+
+    /// Create a new random seed from the game random generator
+    pub fn random() -> Self {
+        let generator = unsafe { &mut **addr_of_mut!(RNG) };
+        Self::new(generator.next_u32())
+    }
+
+    /// Parse a seed from its code
+    ///
+    /// Codes are case insensitive.
+    /// Return `None` if the code is not valid.
+    pub fn from_code(code: &str) -> Option<Self> {
+        if code.len() != CODE_LENGTH {
+            return None;
+        }
+        code.bytes()
+            .try_fold(0, |value, character| {
+                let character = character.to_ascii_uppercase();
+                let digit = CODE_ALPHABET.iter().position(|c| *c == character)?;
+                Some((value << BITS_PER_CHARACTER) | digit as u32)
+            })
+            .map(Self)
+    }
+
+    /// Return the seed value
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+
+    /// Return the seed code
+    pub fn code(&self) -> String {
+        (0..CODE_LENGTH)
+            .rev()
+            .map(|i| {
+                let digit = (self.0 >> (i * BITS_PER_CHARACTER)) & (CODE_ALPHABET.len() as u32 - 1);
+                CODE_ALPHABET[digit as usize] as char
+            })
+            .collect()
+    }
+
+    /// Construct a random generator from this seed
+    pub fn generator(&self) -> XorShiftRng {
+        XorShiftRng::seed_from_u64(self.0.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::{Difficulty, FirstClick, Generator};
+
+    #[test]
+    fn code_round_trip() {
+        for value in [0, 1, 31, 32, 123_456, SEEDS_COUNT - 1].iter() {
+            let seed = Seed::new(*value);
+            let code = seed.code();
+            assert_eq!(code.len(), CODE_LENGTH);
+            assert_eq!(Seed::from_code(&code), Some(seed));
+        }
+    }
+
+    #[test]
+    fn code_examples() {
+        assert_eq!(Seed::new(0).code(), "AAAAAA");
+        assert_eq!(Seed::new(SEEDS_COUNT - 1).code(), "999999");
+        assert_eq!(Seed::new(SEEDS_COUNT + 1), Seed::new(1));
+    }
+
+    #[test]
+    fn codes_are_case_insensitive() {
+        assert_eq!(Seed::from_code("bcdefg"), Seed::from_code("BCDEFG"));
+    }
+
+    #[test]
+    fn invalid_codes_are_rejected() {
+        assert_eq!(Seed::from_code(""), None);
+        assert_eq!(Seed::from_code("ABCDE"), None);
+        assert_eq!(Seed::from_code("ABCDEFG"), None);
+        assert_eq!(Seed::from_code("ABCDE0"), None);
+        assert_eq!(Seed::from_code("ABCDÉ"), None);
+    }
+
+    /// Generate the mine layout of the seed "SWEEP2"
+    fn layout(
+        generator: Generator,
+        first_click: FirstClick,
+        difficulty: Difficulty,
+        start: (usize, usize),
+    ) -> Vec<(usize, usize)> {
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        generator
+            .generate(
+                &mut seed.generator(),
+                difficulty.width(),
+                difficulty.height(),
+                difficulty.mines_count(),
+                start,
+                first_click,
+            )
+            .unwrap()
+    }
+
+    /// Check that mine layouts never change across versions
+    ///
+    /// Changing this test invalidates all shared seed codes.
+    #[test]
+    fn layouts_are_stable() {
+        let mines = layout(
+            Generator::Random,
+            FirstClick::Safe,
+            Difficulty::Easy,
+            (0, 0),
+        );
+        let expected = vec![
+            (10, 11),
+            (4, 0),
+            (7, 10),
+            (13, 7),
+            (11, 0),
+            (8, 13),
+            (14, 9),
+            (2, 13),
+            (9, 3),
+            (8, 11),
+        ];
+        assert_eq!(mines, expected);
+
+        // The mine at (9, 3) is next to the first tile
+        let mines = layout(
+            Generator::Random,
+            FirstClick::Opening,
+            Difficulty::Easy,
+            (9, 4),
+        );
+        let expected = vec![
+            (10, 11),
+            (4, 0),
+            (7, 10),
+            (13, 7),
+            (11, 0),
+            (8, 13),
+            (14, 9),
+            (2, 13),
+            (8, 11),
+            (11, 12),
+        ];
+        assert_eq!(mines, expected);
+
+        // The first mine is on the first tile
+        let mines = layout(
+            Generator::Random,
+            FirstClick::Classic,
+            Difficulty::Easy,
+            (10, 11),
+        );
+        let expected = vec![
+            (10, 11),
+            (4, 0),
+            (7, 10),
+            (13, 7),
+            (11, 0),
+            (8, 13),
+            (14, 9),
+            (2, 13),
+            (9, 3),
+            (8, 11),
+        ];
+        assert_eq!(mines, expected);
+
+        let custom = Difficulty::Custom {
+            width: 9,
+            height: 7,
+            mines: 8,
+        };
+        let mines = layout(Generator::Random, FirstClick::Safe, custom, (4, 3));
+        let expected = vec![
+            (7, 4),
+            (4, 0),
+            (6, 3),
+            (8, 0),
+            (5, 0),
+            (8, 6),
+            (5, 2),
+            (0, 3),
+        ];
+        assert_eq!(mines, expected);
+    }
+
+    /// Check that no-guess layouts never change across versions
+    ///
+    /// Changing this test invalidates all shared seed codes and all boards of
+    /// the day, which are medium games starting at the centre of the map.
+    #[test]
+    fn no_guess_layouts_are_stable() {
+        let mines = layout(
+            Generator::NoGuess,
+            FirstClick::Safe,
+            Difficulty::Medium,
+            (8, 7),
+        );
+        let expected = vec![
+            (10, 11),
+            (4, 0),
+            (7, 10),
+            (13, 7),
+            (11, 0),
+            (8, 13),
+            (14, 9),
+            (2, 13),
+            (9, 3),
+            (8, 11),
+            (11, 12),
+            (15, 11),
+            (4, 6),
+            (2, 7),
+            (7, 2),
+            (1, 4),
+            (11, 2),
+            (0, 5),
+            (12, 7),
+            (11, 1),
+            (3, 11),
+            (2, 1),
+            (14, 11),
+            (5, 12),
+            (6, 2),
+            (0, 6),
+            (1, 5),
+            (4, 2),
+            (6, 12),
+            (9, 2),
+        ];
+        assert_eq!(mines, expected);
+
+        // Some mines of this layout are moved to make it solvable
+        let custom = Difficulty::Custom {
+            width: 9,
+            height: 7,
+            mines: 14,
+        };
+        let mines = layout(Generator::NoGuess, FirstClick::Safe, custom, (4, 3));
+        let expected = vec![
+            (7, 4),
+            (4, 0),
+            (6, 3),
+            (8, 0),
+            (5, 0),
+            (8, 6),
+            (0, 3),
+            (0, 4),
+            (4, 5),
+            (6, 4),
+            (0, 6),
+            (2, 6),
+            (0, 0),
+            (0, 2),
+        ];
+        assert_eq!(mines, expected);
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Player settings
+
+use super::save::{Save, SECTION_HEADER_SIZE, SETTINGS_SECTION};
+use super::{Difficulty, FirstClick, Generator};
+
+/// Number of difficulties with their own mine layout generator
+const DIFFICULTIES_COUNT: usize = 4;
+
+/// Size of the stored settings, i.e. a set of flags and the first click
+/// policy
+const RECORD_SIZE: usize = 1;
+
+/// Size of the stored settings, including the section header
+pub const SETTINGS_STORED_SIZE: usize = SECTION_HEADER_SIZE + RECORD_SIZE;
+
+/// Flag marking the tenths of seconds as shown
+const SHOW_TENTHS_FLAG: u8 = 0b1;
+
+/// Position of the first click policy in the flags
+const FIRST_CLICK_SHIFT: u8 = 1;
+
+/// Flag marking the practice mode as enabled
+const PRACTICE_FLAG: u8 = 0b1000;
+
+/// Position of the flags marking the no-guess generator as chosen, one for
+/// each difficulty
+const NO_GUESS_SHIFT: u8 = 4;
+
+/// Player settings
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Settings {
+    /// Whether the elapsed time is shown with tenths of seconds
+    pub show_tenths: bool,
+
+    /// Protection given to the first uncovered tile of new games
+    pub first_click: FirstClick,
+
+    /// Whether moves can be undone, at the cost of making games unranked
+    pub practice: bool,
+
+    /// Whether mines are placed without guessing, for easy, medium, hard and
+    /// custom games
+    pub no_guess: [bool; DIFFICULTIES_COUNT],
+}
+
+impl Settings {
+    /// Load the settings
+    pub fn load() -> Self {
+        Self::from_save(&Save::load())
+    }
+
+    /// Save the settings
+    ///
+    /// Other stored data is kept.
+    pub fn save(&self) {
+        let mut save = Save::load();
+        self.write(&mut save);
+        save.store();
+    }
+
+    /// Return the mine layout generator chosen for a difficulty
+    ///
+    /// The no-guess generator is only returned when it is available for the
+    /// size and mines count of the difficulty.
+    pub fn generator(&self, difficulty: Difficulty) -> Generator {
+        let available = Generator::NoGuess.is_available(
+            difficulty.width(),
+            difficulty.height(),
+            difficulty.mines_count(),
+        );
+        if self.is_no_guess(difficulty) && available {
+            Generator::NoGuess
+        } else {
+            Generator::Random
+        }
+    }
+
+    /// Check whether mines are placed without guessing for a difficulty
+    ///
+    /// The no-guess generator might still be unavailable, see
+    /// [`Settings::generator`].
+    pub fn is_no_guess(&self, difficulty: Difficulty) -> bool {
+        self.no_guess[index(difficulty)]
+    }
+
+    /// Choose whether mines are placed without guessing for a difficulty
+    pub fn set_no_guess(&mut self, difficulty: Difficulty, no_guess: bool) {
+        self.no_guess[index(difficulty)] = no_guess;
+    }
+
+    /// Read the settings from their section
+    ///
+    /// A section with unexpected size is ignored.
+    pub fn from_save(save: &Save) -> Self {
+        match save.section(SETTINGS_SECTION) {
+            Some(section) if section.len() == RECORD_SIZE => Self {
+                show_tenths: section[0] & SHOW_TENTHS_FLAG != 0,
+                first_click: match (section[0] >> FIRST_CLICK_SHIFT) & 0b11 {
+                    1 => FirstClick::Opening,
+                    2 => FirstClick::Classic,
+                    _ => FirstClick::Safe,
+                },
+                practice: section[0] & PRACTICE_FLAG != 0,
+                no_guess: [0, 1, 2, 3].map(|i| (section[0] >> (NO_GUESS_SHIFT + i)) & 1 != 0),
+            },
+            _ => Self::default(),
+        }
+    }
+
+    /// Write the settings to their section
+    pub fn write(&self, save: &mut Save) {
+        let mut flags = 0;
+        if self.show_tenths {
+            flags |= SHOW_TENTHS_FLAG;
+        }
+        let first_click_tag = match self.first_click {
+            FirstClick::Safe => 0,
+            FirstClick::Opening => 1,
+            FirstClick::Classic => 2,
+        };
+        flags |= first_click_tag << FIRST_CLICK_SHIFT;
+        if self.practice {
+            flags |= PRACTICE_FLAG;
+        }
+        for (i, no_guess) in self.no_guess.iter().enumerate() {
+            if *no_guess {
+                flags |= 1 << (NO_GUESS_SHIFT + i as u8);
+            }
+        }
+        save.set_section(SETTINGS_SECTION, vec![flags]);
+    }
+}
+
+/// Return the index of the no-guess flag of a difficulty
+fn index(difficulty: Difficulty) -> usize {
+    match difficulty {
+        Difficulty::Easy => 0,
+        Difficulty::Medium => 1,
+        Difficulty::Hard => 2,
+        Difficulty::Custom { .. } => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let settings = Settings {
+            show_tenths: true,
+            first_click: FirstClick::Classic,
+            practice: true,
+            no_guess: [false, true, false, true],
+        };
+
+        let mut save = Save::new();
+        assert_eq!(Settings::from_save(&save), Settings::default());
+        settings.write(&mut save);
+        let section = save.section(SETTINGS_SECTION).unwrap();
+        assert_eq!(SECTION_HEADER_SIZE + section.len(), SETTINGS_STORED_SIZE);
+        let loaded = Settings::from_save(&Save::from_bytes(&save.to_bytes()));
+        assert_eq!(loaded, settings);
+    }
+
+    #[test]
+    fn generator_per_difficulty() {
+        let mut settings = Settings::default();
+        settings.set_no_guess(Difficulty::Medium, true);
+        settings.set_no_guess(
+            Difficulty::Custom {
+                width: 8,
+                height: 8,
+                mines: 10,
+            },
+            true,
+        );
+        assert_eq!(settings.generator(Difficulty::Easy), Generator::Random);
+        assert_eq!(settings.generator(Difficulty::Medium), Generator::NoGuess);
+
+        let sparse = Difficulty::Custom {
+            width: 8,
+            height: 8,
+            mines: 14,
+        };
+        assert_eq!(settings.generator(sparse), Generator::NoGuess);
+
+        // No-guess layouts are not available for this many mines
+        let dense = Difficulty::Custom {
+            width: 8,
+            height: 8,
+            mines: 15,
+        };
+        assert_eq!(settings.generator(dense), Generator::Random);
+    }
+}
@@ -0,0 +1,475 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Logical solver
+
+use crate::graphics::Tile;
+
+use super::Map;
+
+/// Tiles whose content can be deduced from the visible state of a map
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Deductions {
+    /// Covered tiles that cannot contain a mine
+    pub safe: Vec<(usize, usize)>,
+
+    /// Covered tiles that must contain a mine
+    pub mines: Vec<(usize, usize)>,
+}
+
+impl Deductions {
+    /// Check whether nothing could be deduced
+    pub fn is_empty(&self) -> bool {
+        self.safe.is_empty() && self.mines.is_empty()
+    }
+}
+
+/// The content of a tile as known by the solver
+#[derive(Clone, Copy, PartialEq)]
+enum Knowledge {
+    Unknown,
+    Safe,
+    Mine,
+}
+
+/// A constraint stating that a set of tiles contains a number of mines
+///
+/// Tiles are stored by index, and there are at most eight of them since a
+/// constraint comes from the neighbours of an uncovered tile.
+#[derive(Clone, Copy)]
+struct Constraint {
+    tiles: [u16; 8],
+    len: u8,
+    mines: u8,
+}
+
+impl Constraint {
+    /// Return the tiles of the constraint, sorted by index
+    fn tiles(&self) -> &[u16] {
+        &self.tiles[..self.len as usize]
+    }
+}
+
+/// Deduce which covered tiles are safe and which contain a mine
+///
+/// Only the visible state of the map is used: the numbers shown on uncovered
+/// tiles and the total number of mines.
+/// Flags are not trusted, so flagged tiles are deduced like covered tiles,
+/// but they are never part of the result.
+///
+/// Each uncovered tile constrains how many mines are among its covered
+/// neighbours.
+/// The total number of mines constrains all tiles whose content is unknown.
+/// Constraints are examined one at a time, and in pairs by comparing tiles
+/// that only appear in one of two overlapping constraints.
+/// Deduced tiles are fed back into the constraints until nothing more can be
+/// deduced.
+pub fn deduce(map: &Map) -> Deductions {
+    let width = map.width();
+    let mut deductions = Deductions::default();
+    for (i, k) in deduce_knowledge(map).iter().enumerate() {
+        let (x, y) = (i % width, i / width);
+        if !matches!(map.tile(x, y), Tile::Covered) {
+            continue;
+        }
+        match k {
+            Knowledge::Unknown => {}
+            Knowledge::Safe => deductions.safe.push((x, y)),
+            Knowledge::Mine => deductions.mines.push((x, y)),
+        }
+    }
+    deductions
+}
+
+/// Play a map without guessing
+///
+/// The tiles found by [`deduce`] are repeatedly flagged and uncovered, until
+/// either all safe tiles are uncovered or nothing more can be deduced.
+/// Return whether all safe tiles were uncovered.
+pub fn solve(map: &mut Map) -> bool {
+    loop {
+        if let Some(solved) = solve_step(map) {
+            return solved;
+        }
+    }
+}
+
+/// Flag and uncover the tiles found by a single deduction
+///
+/// Return whether all safe tiles were uncovered when the map is cleared or
+/// nothing more can be deduced, and `None` while [`solve`] would go on.
+pub fn solve_step(map: &mut Map) -> Option<bool> {
+    let width = map.width();
+    if map.has_stepped_on_mine() {
+        return Some(false);
+    }
+    if map.is_cleared() {
+        return Some(true);
+    }
+
+    let knowledge = deduce_knowledge(map);
+    let mut progress = false;
+    for (i, k) in knowledge.iter().enumerate() {
+        let (x, y) = (i % width, i / width);
+        if *k == Knowledge::Mine && *map.tile(x, y) == Tile::Covered {
+            map.flag_tile(x, y);
+            progress = true;
+        }
+    }
+    for (i, k) in knowledge.iter().enumerate() {
+        let (x, y) = (i % width, i / width);
+        if *k == Knowledge::Safe && *map.tile(x, y) == Tile::Covered {
+            map.uncover_tile(x, y);
+            progress = true;
+        }
+    }
+    if progress {
+        None
+    } else {
+        Some(false)
+    }
+}
+
+/// Deduce the content of every tile of a map
+///
+/// Memory is scarce, so the only buffer is the knowledge of each tile, and
+/// constraints are built from it when needed instead of being stored.
+fn deduce_knowledge(map: &Map) -> Vec<Knowledge> {
+    let width = map.width();
+    let height = map.height();
+
+    let mut knowledge: Vec<Knowledge> = (0..width * height)
+        .map(|i| match map.tile(i % width, i / width) {
+            Tile::Uncovered => Knowledge::Safe,
+            Tile::Covered | Tile::Flagged => Knowledge::Unknown,
+        })
+        .collect();
+
+    loop {
+        let mut progress = false;
+        for i in 0..width * height {
+            if let Some(constraint) = build_constraint(map, &knowledge, i) {
+                progress |= apply_single(constraint.tiles(), constraint.mines, &mut knowledge);
+            }
+        }
+
+        // Pairs are only compared once single constraints are exhausted,
+        // since comparing them is much slower.
+        if progress {
+            continue;
+        }
+        for i in 0..width * height {
+            let a = match build_constraint(map, &knowledge, i) {
+                Some(constraint) => constraint,
+                None => continue,
+            };
+            for j in nearby_tiles(i, width, height).filter(|j| *j > i) {
+                if let Some(b) = build_constraint(map, &knowledge, j) {
+                    progress |= apply_pair(&a, &b, &mut knowledge);
+                    progress |= apply_pair(&b, &a, &mut knowledge);
+                }
+            }
+        }
+
+        // The total number of mines is only used when nothing else can be
+        // deduced, since it must be compared with up to date constraints.
+        if !progress {
+            let known_mines = knowledge.iter().filter(|k| **k == Knowledge::Mine).count();
+            let remaining_mines = map.mines_count().saturating_sub(known_mines);
+            let unknown = knowledge
+                .iter()
+                .filter(|k| **k == Knowledge::Unknown)
+                .count();
+            progress = apply_total(unknown, remaining_mines, &[], &mut knowledge);
+            for i in 0..width * height {
+                if progress {
+                    break;
+                }
+                let constraint = match build_constraint(map, &knowledge, i) {
+                    Some(constraint) => constraint,
+                    None => continue,
+                };
+                if let Some(outside_mines) = remaining_mines.checked_sub(constraint.mines.into()) {
+                    progress =
+                        apply_total(unknown, outside_mines, constraint.tiles(), &mut knowledge);
+                }
+            }
+        }
+
+        if !progress {
+            return knowledge;
+        }
+    }
+}
+
+/// Build the constraint of an uncovered tile on its unknown neighbours
+///
+/// Return `None` if the tile is not uncovered, or if it has no unknown
+/// neighbours.
+fn build_constraint(map: &Map, knowledge: &[Knowledge], i: usize) -> Option<Constraint> {
+    let width = map.width();
+    let (x, y) = (i % width, i / width);
+    if *map.tile(x, y) != Tile::Uncovered {
+        return None;
+    }
+    let mut constraint = Constraint {
+        tiles: [0; 8],
+        len: 0,
+        mines: map.count_neighbour_mines(x, y) as u8,
+    };
+    for j in neighbours(x, y, width, map.height()) {
+        match knowledge[j] {
+            Knowledge::Unknown => {
+                constraint.tiles[constraint.len as usize] = j as u16;
+                constraint.len += 1;
+            }
+            Knowledge::Safe => {}
+            Knowledge::Mine => constraint.mines = constraint.mines.saturating_sub(1),
+        }
+    }
+    if constraint.len > 0 {
+        Some(constraint)
+    } else {
+        None
+    }
+}
+
+/// Record the content of a tile
+///
+/// Return whether the content was not known yet.
+fn learn(knowledge: &mut [Knowledge], tile: u16, content: Knowledge) -> bool {
+    let k = &mut knowledge[tile as usize];
+    if *k == Knowledge::Unknown {
+        *k = content;
+        true
+    } else {
+        false
+    }
+}
+
+/// Deduce tiles from a single constraint
+fn apply_single(tiles: &[u16], mines: u8, knowledge: &mut [Knowledge]) -> bool {
+    let content = if mines == 0 {
+        Knowledge::Safe
+    } else if mines as usize == tiles.len() {
+        Knowledge::Mine
+    } else {
+        return false;
+    };
+    let mut progress = false;
+    for tile in tiles {
+        progress |= learn(knowledge, *tile, content);
+    }
+    progress
+}
+
+/// Deduce tiles from a pair of overlapping constraints
+///
+/// If `b` has as many more mines than `a` as it has tiles not in `a`, then
+/// all those tiles contain mines, and all tiles of `a` not in `b` are safe.
+/// The rule must be applied in both directions: with `a` and `b` swapped, it
+/// also finds that the tiles of `b` not in `a` are safe when `a` is a subset
+/// of `b` with the same number of mines.
+fn apply_pair(a: &Constraint, b: &Constraint, knowledge: &mut [Knowledge]) -> bool {
+    let shared = a.tiles().iter().filter(|t| b.tiles().contains(t)).count();
+    if shared == 0 {
+        return false;
+    }
+    let only_b = b.tiles().len() - shared;
+    if b.mines < a.mines || (b.mines - a.mines) as usize != only_b {
+        return false;
+    }
+
+    let mut progress = false;
+    for tile in b.tiles().iter().filter(|t| !a.tiles().contains(t)) {
+        progress |= learn(knowledge, *tile, Knowledge::Mine);
+    }
+    for tile in a.tiles().iter().filter(|t| !b.tiles().contains(t)) {
+        progress |= learn(knowledge, *tile, Knowledge::Safe);
+    }
+    progress
+}
+
+/// Deduce the unknown tiles outside a constraint from the total number of
+/// mines
+///
+/// There are `unknown` tiles whose content is unknown, and `outside_mines`
+/// mines among them that are not in the constraint.
+/// This is the same as [`apply_pair`] between the constraint and the set of
+/// all unknown tiles, which always includes the constraint, or the same as
+/// [`apply_single`] on all unknown tiles when the constraint is empty.
+/// Only counts are compared, since the set of unknown tiles is large.
+fn apply_total(
+    unknown: usize,
+    outside_mines: usize,
+    tiles: &[u16],
+    knowledge: &mut [Knowledge],
+) -> bool {
+    let outside_tiles = unknown - tiles.len();
+    let content = if outside_tiles == 0 {
+        return false;
+    } else if outside_mines == 0 {
+        Knowledge::Safe
+    } else if outside_mines == outside_tiles {
+        Knowledge::Mine
+    } else {
+        return false;
+    };
+    for (i, k) in knowledge.iter_mut().enumerate() {
+        if *k == Knowledge::Unknown && !tiles.contains(&(i as u16)) {
+            *k = content;
+        }
+    }
+    true
+}
+
+/// Return the indices of the tiles adjacent to a tile
+fn neighbours(x: usize, y: usize, width: usize, height: usize) -> impl Iterator<Item = usize> {
+    let xs = x.saturating_sub(1)..core::cmp::min(x + 2, width);
+    let ys = y.saturating_sub(1)..core::cmp::min(y + 2, height);
+    ys.flat_map(move |ny| xs.clone().map(move |nx| (nx, ny)))
+        .filter(move |(nx, ny)| (*nx, *ny) != (x, y))
+        .map(move |(nx, ny)| nx + ny * width)
+}
+
+/// Return the indices of the tiles close enough to a tile to share a
+/// neighbour with it
+fn nearby_tiles(i: usize, width: usize, height: usize) -> impl Iterator<Item = usize> {
+    let (x, y) = (i % width, i / width);
+    let xs = x.saturating_sub(2)..core::cmp::min(x + 3, width);
+    let ys = y.saturating_sub(2)..core::cmp::min(y + 3, height);
+    ys.flat_map(move |ny| xs.clone().map(move |nx| nx + ny * width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(text: &str) -> Map {
+        Map::from_ascii(text, 10, (0, 0)).unwrap()
+    }
+
+    #[test]
+    fn single_constraints() {
+        // The top-left tile is the only covered neighbour of the tile below
+        // it, so it contains a mine, and the rightmost column is then safe.
+        let map = board(
+            "
+            * 1 .
+            1 1 .
+            ",
+        );
+
+        let deductions = deduce(&map);
+        assert_eq!(deductions.mines, vec![(0, 0)]);
+        assert_eq!(deductions.safe, vec![(2, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn pairwise_constraints() {
+        // The classic 1-2-1 pattern requires comparing constraints.
+        let map = board(
+            "
+            . * . * .
+            1 1 2 1 1
+            ",
+        );
+
+        let deductions = deduce(&map);
+        assert_eq!(deductions.safe, vec![(0, 0), (2, 0), (4, 0)]);
+        assert_eq!(deductions.mines, vec![(1, 0), (3, 0)]);
+    }
+
+    #[test]
+    fn total_mines_count() {
+        // The only mine is next to the uncovered tile, so the leftmost column
+        // is safe.
+        // The flag is not trusted, so the mine itself cannot be located.
+        let map = board(
+            "
+            . . F
+            . . 1
+            . . .
+            ",
+        );
+
+        let deductions = deduce(&map);
+        assert_eq!(deductions.safe, vec![(0, 0), (0, 1), (0, 2)]);
+        assert!(deductions.mines.is_empty());
+    }
+
+    #[test]
+    fn nothing_deducible() {
+        let map = board(
+            "
+            * .
+            1 1
+            ",
+        );
+
+        assert!(deduce(&map).is_empty());
+    }
+
+    #[test]
+    fn solvable_without_mines() {
+        let mut map = board(
+            "
+            0 . . .
+            . . . .
+            . . . .
+            . . . .
+            ",
+        );
+        assert!(solve(&mut map));
+        assert_eq!(map.count_uncovered_tiles(), 16);
+    }
+
+    #[test]
+    fn unsolvable_fifty_fifty() {
+        let mut map = board(
+            "
+            0 1 *
+            0 1 .
+            ",
+        );
+        assert!(!solve(&mut map));
+        assert!(!map.has_stepped_on_mine());
+    }
+
+    #[cfg(not(feature = "buddy-alloc"))]
+    #[test]
+    fn largest_map_fits_console_heap() {
+        use rand_core::SeedableRng;
+        use rand_xorshift::XorShiftRng;
+
+        use crate::game::{FirstClick, Generator, MAX_HEIGHT, MAX_WIDTH};
+        use crate::harness::with_console_heap;
+
+        let (width, height) = (MAX_WIDTH, MAX_HEIGHT);
+        let mines_count = 211;
+        assert!(Generator::NoGuess.is_available(width, height, mines_count));
+
+        let mut generator = XorShiftRng::seed_from_u64(42);
+        let start = (width / 2, height / 2);
+        let mines = Generator::NoGuess
+            .generate(
+                &mut generator,
+                width,
+                height,
+                mines_count,
+                start,
+                FirstClick::Safe,
+            )
+            .unwrap();
+
+        let solved = with_console_heap(|| {
+            let mut map = Map::new(width, height, 10, (0, 0)).with_mines(&mines);
+            map.uncover_tile(start.0, start.1);
+            solve(&mut map)
+        });
+        assert!(solved);
+    }
+}
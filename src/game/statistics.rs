@@ -0,0 +1,248 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Lifetime statistics
+
+use super::save::{Save, SECTION_HEADER_SIZE, STATISTICS_SECTION};
+use super::Difficulty;
+
+/// Number of tracked difficulties, i.e. easy, medium, hard and custom
+const DIFFICULTIES_COUNT: usize = 4;
+
+/// Size of the stored statistics of a difficulty, i.e. the numbers of won
+/// and lost games, the current and longest win streaks, the total winning
+/// time and the total number of uncovered tiles
+const RECORD_SIZE: usize = 16;
+
+/// Size of the stored statistics, including the section header
+pub const STATISTICS_STORED_SIZE: usize = SECTION_HEADER_SIZE + DIFFICULTIES_COUNT * RECORD_SIZE;
+
+/// Statistics of games of a difficulty
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DifficultyStatistics {
+    /// Number of won games
+    pub won: u16,
+
+    /// Number of lost games
+    pub lost: u16,
+
+    /// Number of consecutive won games up to the last one
+    pub streak: u16,
+
+    /// Longest number of consecutive won games
+    pub longest_streak: u16,
+
+    /// Total time taken to win games in seconds
+    pub total_time: u32,
+
+    /// Total number of safe tiles uncovered in won and lost games
+    pub tiles: u32,
+}
+
+impl DifficultyStatistics {
+    /// Return the number of finished games
+    pub fn played(&self) -> u32 {
+        self.won as u32 + self.lost as u32
+    }
+
+    /// Return the percentage of won games, rounded down
+    ///
+    /// Return `None` if no game was finished.
+    pub fn win_rate(&self) -> Option<u32> {
+        match self.played() {
+            0 => None,
+            played => Some(100 * self.won as u32 / played),
+        }
+    }
+
+    /// Return the average time taken to win a game in seconds, rounded
+    ///
+    /// Return `None` if no game was won.
+    pub fn average_time(&self) -> Option<u32> {
+        match self.won {
+            0 => None,
+            won => Some((self.total_time + won as u32 / 2) / won as u32),
+        }
+    }
+}
+
+/// Lifetime statistics
+///
+/// Statistics are tracked separately for easy, medium and hard games, while
+/// all custom games share the same statistics.
+/// Boards of the day count as medium games.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Statistics {
+    difficulties: [DifficultyStatistics; DIFFICULTIES_COUNT],
+}
+
+impl Statistics {
+    /// Load the statistics
+    pub fn load() -> Self {
+        Self::from_save(&Save::load())
+    }
+
+    /// Save the statistics
+    ///
+    /// Other stored data is kept.
+    pub fn save(&self) {
+        let mut save = Save::load();
+        self.write(&mut save);
+        save.store();
+    }
+
+    /// Return the statistics of a difficulty
+    pub fn get(&self, difficulty: Difficulty) -> DifficultyStatistics {
+        self.difficulties[index(difficulty)]
+    }
+
+    /// Record a won game
+    pub fn record_win(&mut self, difficulty: Difficulty, time: u16, tiles: usize) {
+        let statistics = &mut self.difficulties[index(difficulty)];
+        statistics.won = statistics.won.saturating_add(1);
+        statistics.streak = statistics.streak.saturating_add(1);
+        statistics.longest_streak = statistics.longest_streak.max(statistics.streak);
+        statistics.total_time = statistics.total_time.saturating_add(time.into());
+        statistics.tiles = statistics.tiles.saturating_add(tiles as u32);
+    }
+
+    /// Record a lost game
+    pub fn record_loss(&mut self, difficulty: Difficulty, tiles: usize) {
+        let statistics = &mut self.difficulties[index(difficulty)];
+        statistics.lost = statistics.lost.saturating_add(1);
+        statistics.streak = 0;
+        statistics.tiles = statistics.tiles.saturating_add(tiles as u32);
+    }
+
+    /// Reset all statistics
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Read the statistics from their section
+    ///
+    /// A section with unexpected size is ignored.
+    pub fn from_save(save: &Save) -> Self {
+        let mut statistics = Self::default();
+        let section = match save.section(STATISTICS_SECTION) {
+            Some(section) if section.len() == DIFFICULTIES_COUNT * RECORD_SIZE => section,
+            _ => return statistics,
+        };
+
+        for (difficulty, record) in statistics
+            .difficulties
+            .iter_mut()
+            .zip(section.chunks_exact(RECORD_SIZE))
+        {
+            let read_u16 = |i: usize| (record[i] as u16) | (record[i + 1] as u16) << 8;
+            let read_u32 = |i: usize| read_u16(i) as u32 | (read_u16(i + 2) as u32) << 16;
+            *difficulty = DifficultyStatistics {
+                won: read_u16(0),
+                lost: read_u16(2),
+                streak: read_u16(4),
+                longest_streak: read_u16(6),
+                total_time: read_u32(8),
+                tiles: read_u32(12),
+            };
+        }
+
+        statistics
+    }
+
+    /// Write the statistics to their section
+    pub fn write(&self, save: &mut Save) {
+        let mut section = Vec::with_capacity(DIFFICULTIES_COUNT * RECORD_SIZE);
+        for statistics in self.difficulties.iter() {
+            for value in [
+                statistics.won,
+                statistics.lost,
+                statistics.streak,
+                statistics.longest_streak,
+            ]
+            .iter()
+            {
+                section.extend_from_slice(&value.to_le_bytes());
+            }
+            section.extend_from_slice(&statistics.total_time.to_le_bytes());
+            section.extend_from_slice(&statistics.tiles.to_le_bytes());
+        }
+        save.set_section(STATISTICS_SECTION, section);
+    }
+}
+
+/// Return the index of the statistics of a difficulty
+fn index(difficulty: Difficulty) -> usize {
+    match difficulty {
+        Difficulty::Easy => 0,
+        Difficulty::Medium => 1,
+        Difficulty::Hard => 2,
+        Difficulty::Custom { .. } => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_games() {
+        let mut statistics = Statistics::default();
+        statistics.record_win(Difficulty::Easy, 30, 71);
+        statistics.record_win(Difficulty::Easy, 41, 71);
+        statistics.record_loss(Difficulty::Easy, 12);
+        statistics.record_win(Difficulty::Easy, 20, 71);
+
+        let easy = statistics.get(Difficulty::Easy);
+        assert_eq!(easy.played(), 4);
+        assert_eq!(easy.won, 3);
+        assert_eq!(easy.lost, 1);
+        assert_eq!(easy.win_rate(), Some(75));
+        assert_eq!(easy.streak, 1);
+        assert_eq!(easy.longest_streak, 2);
+        assert_eq!(easy.average_time(), Some(30));
+        assert_eq!(easy.tiles, 3 * 71 + 12);
+
+        assert_eq!(statistics.get(Difficulty::Hard), DifficultyStatistics::default());
+        assert_eq!(statistics.get(Difficulty::Hard).win_rate(), None);
+        assert_eq!(statistics.get(Difficulty::Hard).average_time(), None);
+    }
+
+    #[test]
+    fn custom_games_share_statistics() {
+        let mut statistics = Statistics::default();
+        let small = Difficulty::Custom {
+            width: 8,
+            height: 8,
+            mines: 10,
+        };
+        let large = Difficulty::Custom {
+            width: 32,
+            height: 32,
+            mines: 300,
+        };
+        statistics.record_loss(small, 5);
+        statistics.record_loss(large, 50);
+        assert_eq!(statistics.get(small).lost, 2);
+        assert_eq!(statistics.get(large).tiles, 55);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut statistics = Statistics::default();
+        statistics.record_win(Difficulty::Medium, 120, 216);
+        statistics.record_loss(Difficulty::Hard, 100);
+
+        let mut save = Save::new();
+        statistics.write(&mut save);
+        let section = save.section(STATISTICS_SECTION).unwrap();
+        assert_eq!(SECTION_HEADER_SIZE + section.len(), STATISTICS_STORED_SIZE);
+        let loaded = Statistics::from_save(&Save::from_bytes(&save.to_bytes()));
+        assert_eq!(loaded, statistics);
+
+        statistics.reset();
+        assert_eq!(statistics, Statistics::default());
+    }
+}
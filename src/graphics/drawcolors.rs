@@ -16,28 +16,20 @@ impl DrawColors {
     /// Set colour for a specific palette index
     pub fn set_one(&mut self, index: usize, value: u8) -> &mut Self {
         let drawcolorsimpl = unsafe { &mut *(DRAW_COLORS as *mut DrawColorsImpl) };
-        if value < 5 {
-            let value = value as u16;
-            let mask_shift = (index) * 4;
-            let mask: u16 = 0xf000 >> mask_shift;
-            let rest = drawcolorsimpl.0 & !mask;
-            let new_shift = (3 - index) * 4;
-            let new = value << new_shift;
-            drawcolorsimpl.0 = rest | new;
-        }
+        drawcolorsimpl.set_one(index, value);
         self
     }
 
     /// Set all colours
     pub fn set(&mut self, value: u16) {
         let drawcolorsimpl = unsafe { &mut *(DRAW_COLORS as *mut DrawColorsImpl) };
-        drawcolorsimpl.0 = value;
+        drawcolorsimpl.set(value);
     }
 
     /// Set all colours
     pub fn get(&self) -> u16 {
         let drawcolorsimpl = unsafe { &mut *(DRAW_COLORS as *mut DrawColorsImpl) };
-        drawcolorsimpl.0
+        drawcolorsimpl.get()
     }
 }
 
@@ -4,9 +4,12 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use crate::assets::FONT_SPRITE;
+use crate::assets::{FONT_SPRITE, SMALL_FONT_SPRITE};
 use crate::graphics::{draw_horizontal_line, draw_rect, draw_vertical_line, DrawColors};
 
+/// Tiles smaller than this size are drawn with the small font
+const SMALL_FONT_THRESHOLD: u32 = 10;
+
 /// A font character
 ///
 /// Font characters are used to draw some tiles, such as numbers, mines and
@@ -26,7 +29,7 @@ pub enum Character {
 ///
 /// Map tiles can be covered, uncovered or flagged regardless of the possible
 /// mine below them.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Tile {
     /// A covered map tile
     Covered,
@@ -75,18 +78,29 @@ impl Tile {
         draw_rect(x + 1, y + 1, tile_size - 2, tile_size - 2);
     }
 
+    /// Draw a font character centred inside a tile
+    ///
+    /// Tiles smaller than the regular font use a smaller 3x5 font.
     fn draw_tile_character(&self, x: i32, y: i32, tile_size: u32, c: Character) {
-        let offset = ((tile_size - 8) / 2) as i32;
-        DrawColors.set(0x2240);
+        let index = match c {
+            Character::Number(n) => n as u32,
+            Character::Flag => 10,
+            Character::Mine => 11,
+        };
+
         match c {
-            Character::Number(n) => {
-                FONT_SPRITE.blit_sub(x + offset, y + offset, 8, 8, 8 * n as u32, 0)
-            }
-            Character::Mine => {
-                DrawColors.set(0x1142);
-                FONT_SPRITE.blit_sub(x + offset, y + offset, 8, 8, 8 * 11, 0);
-            }
-            Character::Flag => FONT_SPRITE.blit_sub(x + offset, y + offset, 8, 8, 8 * 10, 0),
+            Character::Mine => DrawColors.set(0x1142),
+            Character::Number(_) | Character::Flag => DrawColors.set(0x2240),
+        }
+
+        if tile_size < SMALL_FONT_THRESHOLD {
+            // Small characters are centred inside the tile border
+            let offset_x = 1 + ((tile_size - 1 - 3) / 2) as i32;
+            let offset_y = 1 + ((tile_size - 1 - 5) / 2) as i32;
+            SMALL_FONT_SPRITE.blit_sub(x + offset_x, y + offset_y, 3, 5, 4 * index, 0);
+        } else {
+            let offset = ((tile_size - 8) / 2) as i32;
+            FONT_SPRITE.blit_sub(x + offset, y + offset, 8, 8, 8 * index, 0);
         }
     }
 }
@@ -0,0 +1,451 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Host-side test harness
+//!
+//! The harness runs the game on the native backend of the WASM-4 runtime,
+//! one frame at a time, feeding it a scripted input.
+
+use std::sync::MutexGuard;
+
+#[cfg(all(test, not(feature = "buddy-alloc")))]
+mod heap;
+#[cfg(test)]
+mod snapshot;
+
+#[cfg(all(test, not(feature = "buddy-alloc")))]
+pub use heap::with_console_heap;
+
+use crate::input::{InputSource, ScriptedInput};
+use crate::statemachine::Machine;
+use crate::time::{BigTicker, Ticker};
+use crate::wasm4::native;
+
+/// A harness running the game state machine without WASM-4
+///
+/// The harness holds exclusive access to the emulated console, which is
+/// reset on creation.
+pub struct Harness {
+    machine: Machine,
+    input: ScriptedInput,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Harness {
+    /// Create a new harness with the state machine in its initial state
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let lock = native::lock();
+        Self {
+            machine: Machine::new(),
+            input: ScriptedInput::new(),
+            _lock: lock,
+        }
+    }
+
+    /// Replace the state machine
+    ///
+    /// States access the console when they are created, so they must be
+    /// created after the harness.
+    pub fn set_machine(&mut self, machine: Machine) {
+        self.machine = machine;
+    }
+
+    /// Create a new harness with some content in the persistent storage
+    pub fn with_disk(content: &[u8]) -> Self {
+        let harness = Self::new();
+        native::set_disk(content);
+        harness
+    }
+
+    /// Return the input script, to append more frames to it
+    pub fn input(&mut self) -> &mut ScriptedInput {
+        &mut self.input
+    }
+
+    /// Run a single frame
+    ///
+    /// As on the console, the framebuffer is cleared, then all states are
+    /// drawn and the top state is updated.
+    pub fn step(&mut self) {
+        let input = self.input.next_frame();
+        native::set_mouse(input.pointer.0, input.pointer.1, input.mouse_buttons);
+        native::set_gamepad(input.gamepad_buttons);
+
+        native::clear_framebuffer();
+        self.machine.draw(&input);
+        self.machine.update(&input);
+
+        Ticker.update();
+        BigTicker.update();
+    }
+
+    /// Run a number of frames
+    pub fn run(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.step();
+        }
+    }
+
+    /// Run frames until the whole input script is replayed
+    ///
+    /// One more frame is run afterwards, so that the framebuffer shows the
+    /// result of the last input.
+    pub fn run_script(&mut self) {
+        while !self.input.is_finished() {
+            self.step();
+        }
+        self.step();
+    }
+
+    /// Run frames while a state is the current one, up to a given number
+    ///
+    /// One more frame is run afterwards, so that the framebuffer shows the
+    /// next state.
+    pub fn run_while(&mut self, name: &str, frames: usize) {
+        for _ in 0..frames {
+            if self.state_name() != name {
+                break;
+            }
+            self.step();
+        }
+        self.step();
+    }
+
+    /// Return the name of the current state
+    pub fn state_name(&self) -> &'static str {
+        self.machine.state_name()
+    }
+
+    /// Return the names of all states in the stack, from bottom to top
+    pub fn state_names(&self) -> Vec<&'static str> {
+        self.machine.state_names()
+    }
+
+    /// Return the palette index of a pixel in the framebuffer
+    pub fn pixel(&self, x: u32, y: u32) -> u8 {
+        native::pixel(x, y)
+    }
+
+    /// Return the current palette
+    pub fn palette(&self) -> [u32; 4] {
+        native::palette()
+    }
+
+    /// Return the content of the persistent storage
+    pub fn disk(&self) -> Vec<u8> {
+        native::disk()
+    }
+
+    /// Compare the framebuffer with a stored snapshot
+    ///
+    /// See [`snapshot::assert_snapshot`] for details.
+    #[cfg(test)]
+    pub fn assert_snapshot(&self, name: &str) {
+        let size = crate::wasm4::SCREEN_SIZE;
+        let pixels = (0..size)
+            .flat_map(|y| (0..size).map(move |x| native::pixel(x, y)))
+            .collect();
+        let image = snapshot::Image {
+            pixels,
+            palette: self.palette(),
+        };
+        snapshot::assert_snapshot(name, &image);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::{
+        Date, Difficulty, Generator, HighScore, HighScores, Replay, Seed, Settings, Statistics,
+    };
+    use crate::wasm4::{BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_UP, SCREEN_SIZE};
+
+    /// Create a harness and run it until the main menu is shown
+    fn main_menu() -> Harness {
+        let mut harness = Harness::new();
+        harness.run(2);
+        harness
+    }
+
+    #[test]
+    fn starts_in_main_menu() {
+        let harness = main_menu();
+        assert_eq!(harness.state_names(), vec!["initial", "main_menu"]);
+        assert_eq!(harness.palette(), [0xfffad6, 0xe6c12b, 0x7a3921, 0x240024]);
+    }
+
+    #[test]
+    fn main_menu_highlights_entry_under_mouse() {
+        let mut harness = main_menu();
+        harness.input().idle(80, 57);
+        harness.run_script();
+        assert_eq!(harness.pixel(150, 55), 1);
+        assert_eq!(harness.pixel(150, 67), 0);
+    }
+
+    #[test]
+    fn first_click_starts_game() {
+        let mut harness = main_menu();
+        harness.input().left_click(80, 20);
+        harness.run_script();
+        assert_eq!(harness.state_name(), "pre_game");
+
+        harness.input().left_click(80, 90);
+        harness.run_script();
+        assert_eq!(harness.state_name(), "in_game");
+
+        // The uncovered tile is drawn without cover, possibly with a number
+        assert_ne!(harness.pixel(85, 95), 2);
+        // Covered tiles are drawn with cover
+        let covered = (0..160)
+            .flat_map(|x| (20..160).map(move |y| (x, y)))
+            .filter(|(x, y)| harness.pixel(*x, *y) == 2)
+            .count();
+        assert!(covered > 0);
+    }
+
+    #[test]
+    fn click_outside_map_pauses_game() {
+        let mut harness = main_menu();
+        harness.input().left_click(80, 20).left_click(80, 90);
+        harness.run_script();
+        harness.input().left_click(118, 10);
+        harness.run_script();
+        assert_eq!(harness.state_names(), vec!["initial", "in_game", "pause"]);
+
+        harness.input().press(BUTTON_1);
+        harness.run_script();
+        assert_eq!(harness.state_names(), vec!["initial", "in_game"]);
+    }
+
+    #[test]
+    fn click_on_timer_toggles_tenths() {
+        let mut harness = main_menu();
+        harness.input().left_click(80, 20).left_click(80, 90);
+        harness.run_script();
+        assert!(!Settings::load().show_tenths);
+
+        harness.input().left_click(30, 10);
+        harness.run_script();
+        assert_eq!(harness.state_names(), vec!["initial", "in_game"]);
+        assert!(Settings::load().show_tenths);
+
+        harness.input().left_click(30, 10);
+        harness.run_script();
+        assert!(!Settings::load().show_tenths);
+    }
+
+    /// Start a seeded game by uncovering the central tile
+    fn start_seeded_game() -> Harness {
+        start_seeded_game_with(Settings::default())
+    }
+
+    /// Start a seeded game with given settings by uncovering the central tile
+    fn start_seeded_game_with(settings: Settings) -> Harness {
+        let mut harness = main_menu();
+        settings.save();
+        harness.input().left_click(80, 57);
+        harness.run_script();
+        assert_eq!(harness.state_name(), "play_seed");
+
+        // Change the first character of the code and start, the game starts
+        // without waiting for a click
+        harness.input().left_click(24, 54).left_click(80, 118);
+        harness.run_script();
+        harness.run(1);
+        assert_eq!(harness.state_name(), "in_game");
+        harness
+    }
+
+    /// Play a seeded game and return the screen after the first click
+    fn play_seed() -> Vec<u8> {
+        let harness = start_seeded_game();
+        (0..SCREEN_SIZE)
+            .flat_map(|y| (0..SCREEN_SIZE).map(move |x| (x, y)))
+            .map(|(x, y)| harness.pixel(x, y))
+            .collect()
+    }
+
+    #[test]
+    fn seeded_games_are_identical() {
+        assert_eq!(play_seed(), play_seed());
+    }
+
+    #[test]
+    fn game_over_opens_replay() {
+        let mut harness = start_seeded_game();
+        let seed = Seed::from_code("BAAAAA").unwrap();
+        let mines = Replay::new(Difficulty::Easy, Generator::Random, seed, (8, 7))
+            .mines()
+            .unwrap();
+        let (x, y) = mines[0];
+        harness
+            .input()
+            .left_click(10 * x as i16 + 5, 20 + 10 * y as i16 + 5);
+        harness.run_script();
+        assert_eq!(harness.state_names(), vec!["initial", "game_over"]);
+
+        // The lost game is recorded in the statistics
+        let statistics = Statistics::load().get(Difficulty::Easy);
+        assert_eq!(statistics.lost, 1);
+        assert!(statistics.tiles > 0);
+
+        harness.input().press(BUTTON_2);
+        harness.run_script();
+        assert_eq!(
+            harness.state_names(),
+            vec!["initial", "game_over", "replay"]
+        );
+
+        harness.input().press(BUTTON_2);
+        harness.run_script();
+        assert_eq!(harness.state_names(), vec!["initial", "game_over"]);
+    }
+
+    #[test]
+    fn practice_mode_undoes_fatal_move() {
+        let mut harness = start_seeded_game_with(Settings {
+            practice: true,
+            ..Settings::default()
+        });
+        let seed = Seed::from_code("BAAAAA").unwrap();
+        let mines = Replay::new(Difficulty::Easy, Generator::Random, seed, (8, 7))
+            .mines()
+            .unwrap();
+        let (x, y) = mines[0];
+        let (mouse_x, mouse_y) = (10 * x as i16 + 5, 20 + 10 * y as i16 + 5);
+        harness.input().left_click(mouse_x, mouse_y);
+        harness.run_script();
+        assert_eq!(harness.state_names(), vec!["initial", "game_over"]);
+        assert_eq!(Statistics::load().get(Difficulty::Easy).lost, 1);
+
+        harness.input().press(BUTTON_UP);
+        harness.run_script();
+        assert_eq!(harness.state_names(), vec!["initial", "in_game"]);
+
+        // The game is now unranked, so losing again is not recorded
+        harness.input().left_click(mouse_x, mouse_y);
+        harness.run_script();
+        assert_eq!(harness.state_names(), vec!["initial", "game_over"]);
+        assert_eq!(Statistics::load().get(Difficulty::Easy).lost, 1);
+    }
+
+    #[test]
+    fn daily_board_starts_at_centre() {
+        let mut harness = main_menu();
+        harness.input().left_click(80, 67);
+        harness.run_script();
+        assert_eq!(harness.state_name(), "daily");
+
+        // The game starts without waiting for a click, once the mines are
+        // placed
+        harness.input().left_click(80, 118);
+        harness.run_script();
+        harness.run_while("pre_game", 100);
+        assert_eq!(harness.state_name(), "in_game");
+
+        // Tile (8, 7) is at the centre of the viewport
+        assert_eq!(harness.pixel(85, 95), 0);
+
+        // The date is stored for the next board
+        let highscores = HighScores::load();
+        assert_eq!(highscores.daily_date(), Date::new(2021, 1, 1));
+    }
+
+    #[test]
+    fn main_menu_opens_puzzles() {
+        let mut harness = main_menu();
+        harness.input().left_click(80, 77);
+        harness.run_script();
+        assert_eq!(harness.state_name(), "puzzle_select");
+
+        harness.input().left_click(80, 20);
+        harness.run_script();
+        assert_eq!(harness.state_names(), vec!["initial", "puzzle"]);
+    }
+
+    #[test]
+    fn gamepad_opens_instructions() {
+        let mut harness = main_menu();
+        for _ in 0..11 {
+            harness.input().press(BUTTON_DOWN);
+        }
+        harness.input().press(BUTTON_1);
+        harness.run_script();
+        assert_eq!(
+            harness.state_names(),
+            vec!["initial", "main_menu", "instructions"]
+        );
+    }
+
+    #[test]
+    fn high_score_opens_replay() {
+        let mut harness = Harness::new();
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        let mut highscores = HighScores::load();
+        highscores.set(
+            Difficulty::Medium,
+            HighScore {
+                time: 100,
+                hints: 0,
+                performance: None,
+            },
+        );
+        highscores.set_replay(Replay::new(
+            Difficulty::Medium,
+            Generator::Random,
+            seed,
+            (0, 0),
+        ));
+        highscores.save();
+        harness.run(2);
+
+        // Unbeaten difficulties have no replay
+        harness.input().left_click(80, 136);
+        harness.run_script();
+        assert_eq!(harness.state_names(), vec!["initial", "main_menu"]);
+
+        harness.input().left_click(80, 145);
+        harness.run_script();
+        assert_eq!(
+            harness.state_names(),
+            vec!["initial", "main_menu", "replay"]
+        );
+
+        harness.input().press(BUTTON_2);
+        harness.run_script();
+        assert_eq!(harness.state_names(), vec!["initial", "main_menu"]);
+    }
+
+    #[test]
+    fn legacy_high_scores_are_migrated() {
+        let legacy = [0xff, 0x2c, 0x01, 0x00, 0x00, 0x00, 0xff, 0x10, 0x00];
+        let mut harness = Harness::with_disk(&legacy);
+        harness.run(2);
+
+        let mut highscores = HighScores::load();
+        assert_eq!(highscores.get(Difficulty::Easy).unwrap().time, 300 * 60);
+        assert_eq!(highscores.get(Difficulty::Hard).unwrap().time, 16 * 60);
+
+        highscores.set(
+            Difficulty::Medium,
+            HighScore {
+                time: 100,
+                hints: 0,
+                performance: None,
+            },
+        );
+        highscores.save();
+        assert!(harness.disk().starts_with(b"MS"));
+
+        let highscores = HighScores::load();
+        assert_eq!(highscores.get(Difficulty::Easy).unwrap().time, 300 * 60);
+        assert_eq!(highscores.get(Difficulty::Medium).unwrap().time, 100);
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Heap as small as the one of the console
+//!
+//! Tests use a global allocator that normally forwards to the system one,
+//! but that can serve the allocations of a thread from a heap with the same
+//! sizes as the one used on WASM-4.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{Cell, UnsafeCell};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use buddy_alloc::{BuddyAllocParam, FastAllocParam, NonThreadsafeAlloc};
+use once_cell::sync::Lazy;
+
+use crate::alloc::{FAST_HEAP_SIZE, HEAP_SIZE, LEAF_SIZE};
+
+#[global_allocator]
+static ALLOC: ConsoleHeap = ConsoleHeap::new();
+
+static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+thread_local! {
+    static ENABLED: Cell<bool> = Cell::new(false);
+}
+
+/// Memory of a heap, aligned as the allocator requires
+#[repr(align(16))]
+struct Memory<const N: usize>([u8; N]);
+
+/// An allocator serving the current thread from a console heap on request
+struct ConsoleHeap {
+    fast_memory: UnsafeCell<Memory<FAST_HEAP_SIZE>>,
+    memory: UnsafeCell<Memory<HEAP_SIZE>>,
+    allocator: UnsafeCell<Option<NonThreadsafeAlloc>>,
+    exhausted: AtomicBool,
+}
+
+// The console heap is only used by the thread holding the lock.
+unsafe impl Sync for ConsoleHeap {}
+
+impl ConsoleHeap {
+    const fn new() -> Self {
+        Self {
+            fast_memory: UnsafeCell::new(Memory([0; FAST_HEAP_SIZE])),
+            memory: UnsafeCell::new(Memory([0; HEAP_SIZE])),
+            allocator: UnsafeCell::new(None),
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    /// Replace the console heap with an empty one
+    ///
+    /// # Safety
+    ///
+    /// No memory can be allocated from the console heap at this point.
+    unsafe fn reset(&self) {
+        let fast_param = FastAllocParam::new(self.fast_memory.get() as *const u8, FAST_HEAP_SIZE);
+        let buddy_param =
+            BuddyAllocParam::new(self.memory.get() as *const u8, HEAP_SIZE, LEAF_SIZE);
+        *self.allocator.get() = Some(NonThreadsafeAlloc::new(fast_param, buddy_param));
+        self.exhausted.store(false, Ordering::SeqCst);
+    }
+
+    fn contains(&self, ptr: *mut u8) -> bool {
+        let contains = |start: *mut u8, size: usize| {
+            let start = start as usize;
+            (start..start + size).contains(&(ptr as usize))
+        };
+        contains(self.fast_memory.get() as *mut u8, FAST_HEAP_SIZE)
+            || contains(self.memory.get() as *mut u8, HEAP_SIZE)
+    }
+}
+
+unsafe impl GlobalAlloc for ConsoleHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if ENABLED.try_with(Cell::get).unwrap_or(false) {
+            if let Some(allocator) = &*self.allocator.get() {
+                let ptr = allocator.alloc(layout);
+                if !ptr.is_null() {
+                    return ptr;
+                }
+            }
+            // Running out of memory is reported after the closure returns,
+            // since panicking inside the allocator aborts.
+            self.exhausted.store(true, Ordering::SeqCst);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if self.contains(ptr) {
+            if let Some(allocator) = &*self.allocator.get() {
+                allocator.dealloc(ptr, layout);
+            }
+        } else {
+            System.dealloc(ptr, layout);
+        }
+    }
+}
+
+/// Restore the system allocator when dropped, even when unwinding
+struct Enabled;
+
+impl Drop for Enabled {
+    fn drop(&mut self) {
+        ENABLED.with(|enabled| enabled.set(false));
+    }
+}
+
+/// Run a closure allocating from a heap as large as the one of the console
+///
+/// The heap starts empty, so the closure should create all the data it
+/// uses.
+/// The result cannot hold allocated memory, since the heap is discarded
+/// afterwards.
+///
+/// # Panics
+///
+/// Panics if the closure runs out of memory.
+pub fn with_console_heap<T: Copy>(f: impl FnOnce() -> T) -> T {
+    let _lock = LOCK.lock().unwrap_or_else(|error| error.into_inner());
+    unsafe {
+        ALLOC.reset();
+    }
+
+    let result = {
+        ENABLED.with(|enabled| enabled.set(true));
+        let _enabled = Enabled;
+        f()
+    };
+
+    assert!(
+        !ALLOC.exhausted.load(Ordering::SeqCst),
+        "Console heap exhausted"
+    );
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_allocations_fit() {
+        let sum = with_console_heap(|| (0..1000).collect::<Vec<usize>>().iter().sum::<usize>());
+        assert_eq!(sum, 1000 * 999 / 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Console heap exhausted")]
+    fn large_allocations_do_not_fit() {
+        with_console_heap(|| vec![0u8; HEAP_SIZE].len());
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Golden-image snapshots
+//!
+//! Snapshots are indexed PNG images of the framebuffer stored in the
+//! `snapshots` directory, using the palette active when they were taken.
+
+use std::env::var_os;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+use crate::wasm4::SCREEN_SIZE;
+
+/// Environment variable forcing snapshots to be rewritten
+const UPDATE_VARIABLE: &str = "UPDATE_SNAPSHOTS";
+
+/// A framebuffer image
+#[derive(Debug, PartialEq)]
+pub struct Image {
+    /// The palette index of each pixel, row by row
+    pub pixels: Vec<u8>,
+
+    /// The palette
+    pub palette: [u32; 4],
+}
+
+/// Compare an image with a stored snapshot
+///
+/// If the environment variable `UPDATE_SNAPSHOTS` is set, the image is
+/// stored as the new snapshot instead.
+///
+/// # Panics
+///
+/// Panic if the snapshot does not exist or if the image differs from it.
+/// The image is then stored next to the snapshot with extension
+/// `.actual.png`, so that they can be compared.
+pub fn assert_snapshot(name: &str, image: &Image) {
+    let path = snapshot_path(name, "png");
+
+    if var_os(UPDATE_VARIABLE).is_some() {
+        write_png(&path, image);
+        return;
+    }
+
+    let actual_path = snapshot_path(name, "actual.png");
+    if !path.exists() {
+        write_png(&actual_path, image);
+        panic!(
+            "Snapshot {} does not exist, set {} to create it",
+            path.display(),
+            UPDATE_VARIABLE,
+        );
+    }
+
+    let expected = read_png(&path);
+    if expected != *image {
+        write_png(&actual_path, image);
+        panic!(
+            "Snapshot {} differs from {}, set {} to update it",
+            actual_path.display(),
+            path.display(),
+            UPDATE_VARIABLE,
+        );
+    }
+}
+
+fn snapshot_path(name: &str, extension: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("snapshots")
+        .join(format!("{}.{}", name, extension))
+}
+
+fn write_png(path: &PathBuf, image: &Image) {
+    let file = File::create(path).expect("Cannot create snapshot file");
+    let mut encoder = png::Encoder::new(BufWriter::new(file), SCREEN_SIZE, SCREEN_SIZE);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    let palette: Vec<u8> = image
+        .palette
+        .iter()
+        .flat_map(|color| {
+            let [_, red, green, blue] = color.to_be_bytes();
+            vec![red, green, blue]
+        })
+        .collect();
+    encoder.set_palette(palette);
+
+    let mut writer = encoder.write_header().expect("Cannot write snapshot");
+    writer
+        .write_image_data(&image.pixels)
+        .expect("Cannot write snapshot");
+}
+
+fn read_png(path: &PathBuf) -> Image {
+    let file = File::open(path).expect("Cannot open snapshot file");
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::IDENTITY);
+    let mut reader = decoder.read_info().expect("Cannot read snapshot");
+
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    reader
+        .next_frame(&mut pixels)
+        .expect("Cannot read snapshot");
+
+    let mut palette = [0; 4];
+    let colors = reader
+        .info()
+        .palette
+        .as_ref()
+        .expect("Snapshot has no palette");
+    for (color, rgb) in palette.iter_mut().zip(colors.chunks(3)) {
+        *color = u32::from_be_bytes([0, rgb[0], rgb[1], rgb[2]]);
+    }
+
+    Image { pixels, palette }
+}
@@ -6,5 +6,14 @@
 
 //! Input primitives and subsystems
 
-mod mouse;
-pub use mouse::Mouse;
+mod drag;
+pub use drag::Drag;
+
+mod frame;
+pub use frame::InputFrame;
+
+mod scripted;
+pub use scripted::ScriptedInput;
+
+mod source;
+pub use source::{InputSource, Wasm4Input};
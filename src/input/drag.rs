@@ -0,0 +1,68 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Mouse drag
+
+use super::InputFrame;
+
+/// Distance in pixels the mouse must move before a press becomes a drag
+const DRAG_THRESHOLD: i16 = 4;
+
+/// A mouse drag tracker
+///
+/// A drag starts when the left button is pressed and the mouse moves farther
+/// than a small threshold.
+/// The drag lasts until the button is released, including the frame of the
+/// release, so that the release is not mistaken for a click.
+#[derive(Clone, Default)]
+pub struct Drag {
+    origin: Option<(i16, i16)>,
+    previous: (i16, i16),
+    is_dragging: bool,
+}
+
+impl Drag {
+    /// Create a new drag tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update the drag tracker
+    ///
+    /// This function must be called at each frame.
+    /// It returns the mouse movement since the previous frame while dragging,
+    /// and `(0, 0)` otherwise.
+    pub fn update(&mut self, input: &InputFrame) -> (i16, i16) {
+        let position = input.coordinates();
+
+        if input.left_pressed() {
+            let origin = *self.origin.get_or_insert(position);
+            if !self.is_dragging {
+                let distance = (position.0 - origin.0).abs() + (position.1 - origin.1).abs();
+                if distance > DRAG_THRESHOLD {
+                    self.is_dragging = true;
+                    self.previous = origin;
+                }
+            }
+        } else if !input.left_clicked() {
+            self.origin = None;
+            self.is_dragging = false;
+        }
+
+        if self.is_dragging {
+            let movement = (position.0 - self.previous.0, position.1 - self.previous.1);
+            self.previous = position;
+            movement
+        } else {
+            (0, 0)
+        }
+    }
+
+    /// Check whether the mouse is being dragged
+    pub fn is_dragging(&self) -> bool {
+        self.is_dragging
+    }
+}
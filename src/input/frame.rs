@@ -0,0 +1,142 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Input frame
+
+use crate::wasm4::{MOUSE_LEFT, MOUSE_MIDDLE, MOUSE_RIGHT};
+
+/// The status of all inputs during a frame
+///
+/// Besides the current status of the mouse and of the gamepad, a frame also
+/// contains their status during the previous frame, which is used to detect
+/// clicks and presses.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputFrame {
+    /// The pointer coordinates
+    pub pointer: (i16, i16),
+
+    /// The mouse buttons held down, as `MOUSE_*` flags
+    pub mouse_buttons: u8,
+
+    /// The mouse buttons held down during the previous frame
+    pub previous_mouse_buttons: u8,
+
+    /// The gamepad buttons held down, as `BUTTON_*` flags
+    pub gamepad_buttons: u8,
+
+    /// The gamepad buttons held down during the previous frame
+    pub previous_gamepad_buttons: u8,
+}
+
+impl InputFrame {
+    /// Get the pointer coordinates
+    pub fn coordinates(&self) -> (i16, i16) {
+        self.pointer
+    }
+
+    /// Check whether left button is pressed
+    pub fn left_pressed(&self) -> bool {
+        is_down(self.mouse_buttons, MOUSE_LEFT)
+    }
+
+    /// Check whether right button is pressed
+    pub fn right_pressed(&self) -> bool {
+        is_down(self.mouse_buttons, MOUSE_RIGHT)
+    }
+
+    /// Check whether middle button is pressed
+    pub fn middle_pressed(&self) -> bool {
+        is_down(self.mouse_buttons, MOUSE_MIDDLE)
+    }
+
+    /// Check whether left button was clicked
+    ///
+    /// A click happens when the previous status was down and the current
+    /// status is up.
+    pub fn left_clicked(&self) -> bool {
+        self.mouse_released(MOUSE_LEFT)
+    }
+
+    /// Check whether right button was clicked
+    pub fn right_clicked(&self) -> bool {
+        self.mouse_released(MOUSE_RIGHT)
+    }
+
+    /// Check whether middle button was clicked
+    pub fn middle_clicked(&self) -> bool {
+        self.mouse_released(MOUSE_MIDDLE)
+    }
+
+    /// Check whether a gamepad button is held down
+    pub fn held(&self, button: u8) -> bool {
+        is_down(self.gamepad_buttons, button)
+    }
+
+    /// Check whether a gamepad button was pressed
+    ///
+    /// A press happens when the previous status was up and the current
+    /// status is down.
+    pub fn pressed(&self, button: u8) -> bool {
+        is_down(self.gamepad_buttons, button) && !is_down(self.previous_gamepad_buttons, button)
+    }
+
+    /// Check whether any gamepad button was pressed
+    pub fn any_pressed(&self) -> bool {
+        self.gamepad_buttons & !self.previous_gamepad_buttons != 0
+    }
+
+    fn mouse_released(&self, button: u8) -> bool {
+        !is_down(self.mouse_buttons, button) && is_down(self.previous_mouse_buttons, button)
+    }
+}
+
+fn is_down(value: u8, button: u8) -> bool {
+    (value & button) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::wasm4::{BUTTON_1, BUTTON_2};
+
+    #[test]
+    fn click_happens_on_release() {
+        let pressing = InputFrame {
+            mouse_buttons: MOUSE_LEFT,
+            ..InputFrame::default()
+        };
+        assert!(pressing.left_pressed());
+        assert!(!pressing.left_clicked());
+
+        let releasing = InputFrame {
+            previous_mouse_buttons: MOUSE_LEFT,
+            ..InputFrame::default()
+        };
+        assert!(!releasing.left_pressed());
+        assert!(releasing.left_clicked());
+        assert!(!releasing.right_clicked());
+    }
+
+    #[test]
+    fn press_happens_once() {
+        let pressing = InputFrame {
+            gamepad_buttons: BUTTON_1,
+            ..InputFrame::default()
+        };
+        assert!(pressing.pressed(BUTTON_1));
+        assert!(pressing.any_pressed());
+
+        let holding = InputFrame {
+            gamepad_buttons: BUTTON_1 | BUTTON_2,
+            previous_gamepad_buttons: BUTTON_1,
+            ..InputFrame::default()
+        };
+        assert!(!holding.pressed(BUTTON_1));
+        assert!(holding.held(BUTTON_1));
+        assert!(holding.pressed(BUTTON_2));
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Scripted input
+
+use std::collections::VecDeque;
+
+use crate::wasm4::{MOUSE_LEFT, MOUSE_RIGHT};
+
+use super::{InputFrame, InputSource};
+
+/// An input source replaying a script
+///
+/// The script is a sequence of frames, each one with a pointer position and
+/// the mouse and gamepad buttons held down.
+/// Once the script is over, the pointer stays still and no button is held.
+#[derive(Clone, Default)]
+pub struct ScriptedInput {
+    steps: VecDeque<((i16, i16), u8, u8)>,
+    previous: InputFrame,
+}
+
+impl ScriptedInput {
+    /// Create a new empty script
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a frame to the script
+    pub fn push(
+        &mut self,
+        pointer: (i16, i16),
+        mouse_buttons: u8,
+        gamepad_buttons: u8,
+    ) -> &mut Self {
+        self.steps
+            .push_back((pointer, mouse_buttons, gamepad_buttons));
+        self
+    }
+
+    /// Append a frame with no button held
+    pub fn idle(&mut self, x: i16, y: i16) -> &mut Self {
+        self.push((x, y), 0, 0)
+    }
+
+    /// Append the frames of a left click at a position
+    pub fn left_click(&mut self, x: i16, y: i16) -> &mut Self {
+        self.push((x, y), MOUSE_LEFT, 0).idle(x, y)
+    }
+
+    /// Append the frames of a right click at a position
+    pub fn right_click(&mut self, x: i16, y: i16) -> &mut Self {
+        self.push((x, y), MOUSE_RIGHT, 0).idle(x, y)
+    }
+
+    /// Append the frames of a gamepad button press
+    pub fn press(&mut self, button: u8) -> &mut Self {
+        let pointer = self.last_pointer();
+        self.push(pointer, 0, button).idle(pointer.0, pointer.1)
+    }
+
+    /// Check whether the whole script was replayed
+    pub fn is_finished(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    fn last_pointer(&self) -> (i16, i16) {
+        self.steps
+            .back()
+            .map(|(pointer, _, _)| *pointer)
+            .unwrap_or(self.previous.pointer)
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn next_frame(&mut self) -> InputFrame {
+        let (pointer, mouse_buttons, gamepad_buttons) =
+            self.steps
+                .pop_front()
+                .unwrap_or((self.previous.pointer, 0, 0));
+
+        let frame = InputFrame {
+            pointer,
+            mouse_buttons,
+            previous_mouse_buttons: self.previous.mouse_buttons,
+            gamepad_buttons,
+            previous_gamepad_buttons: self.previous.gamepad_buttons,
+        };
+        self.previous = frame;
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::wasm4::BUTTON_1;
+
+    #[test]
+    fn left_click_is_detected_once() {
+        let mut input = ScriptedInput::new();
+        input.left_click(10, 20);
+
+        let frame = input.next_frame();
+        assert_eq!(frame.coordinates(), (10, 20));
+        assert!(frame.left_pressed());
+        assert!(!frame.left_clicked());
+
+        let frame = input.next_frame();
+        assert!(frame.left_clicked());
+        assert!(input.is_finished());
+
+        let frame = input.next_frame();
+        assert_eq!(frame.coordinates(), (10, 20));
+        assert!(!frame.left_clicked());
+    }
+
+    #[test]
+    fn press_keeps_pointer() {
+        let mut input = ScriptedInput::new();
+        input.idle(30, 40).press(BUTTON_1);
+
+        assert!(!input.next_frame().any_pressed());
+        let frame = input.next_frame();
+        assert!(frame.pressed(BUTTON_1));
+        assert_eq!(frame.coordinates(), (30, 40));
+        assert!(!input.next_frame().pressed(BUTTON_1));
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Input sources
+
+use crate::wasm4::{GAMEPAD1, MOUSE_BUTTONS, MOUSE_X, MOUSE_Y};
+
+use super::InputFrame;
+
+/// A source of input frames
+pub trait InputSource {
+    /// Read the inputs for the next frame
+    ///
+    /// This function must be called exactly once per frame.
+    fn next_frame(&mut self) -> InputFrame;
+}
+
+/// The inputs of the WASM-4 console
+///
+/// The mouse and the first gamepad are read from the console memory.
+#[derive(Clone, Copy, Default)]
+pub struct Wasm4Input {
+    previous_mouse_buttons: u8,
+    previous_gamepad_buttons: u8,
+}
+
+impl Wasm4Input {
+    /// Create a new input source
+    pub const fn new() -> Self {
+        Self {
+            previous_mouse_buttons: 0,
+            previous_gamepad_buttons: 0,
+        }
+    }
+}
+
+impl InputSource for Wasm4Input {
+    fn next_frame(&mut self) -> InputFrame {
+        let (pointer, mouse_buttons, gamepad_buttons) =
+            unsafe { ((*MOUSE_X, *MOUSE_Y), *MOUSE_BUTTONS, *GAMEPAD1) };
+
+        let frame = InputFrame {
+            pointer,
+            mouse_buttons,
+            previous_mouse_buttons: self.previous_mouse_buttons,
+            gamepad_buttons,
+            previous_gamepad_buttons: self.previous_gamepad_buttons,
+        };
+
+        self.previous_mouse_buttons = mouse_buttons;
+        self.previous_gamepad_buttons = gamepad_buttons;
+
+        frame
+    }
+}
@@ -6,8 +6,11 @@
 
 //! Interface primitives
 
-use crate::fmt::format_number;
+use crate::assets::FONT_SPRITE;
+use crate::fmt::{format_number, format_time};
 use crate::graphics::{draw_rect, draw_text, DrawColors};
+use crate::input::InputFrame;
+use crate::time::FRAMES_PER_SECOND;
 
 /// Draw a message box
 pub fn draw_message_box(text: &str, x: i32, y: i32) {
@@ -28,22 +31,130 @@ pub fn draw_box(x: i32, y: i32, width: u32, height: u32) {
     draw_rect(x, y, width, height);
 }
 
+/// Size of square buttons
+pub const BUTTON_SIZE: u32 = 12;
+
+/// Horizontal position of the hint button in the HUD
+pub const HINT_BUTTON_X: i32 = 72;
+
+/// Horizontal position of the zoom out button in the HUD
+pub const ZOOM_OUT_BUTTON_X: i32 = 86;
+
+/// Horizontal position of the zoom in button in the HUD
+pub const ZOOM_IN_BUTTON_X: i32 = 100;
+
+/// Horizontal position of the undo button in the HUD, shown in practice mode
+pub const UNDO_BUTTON_X: i32 = 114;
+
+/// Vertical position of the buttons in the HUD
+pub const HUD_BUTTON_Y: i32 = 3;
+
+/// Width of the elapsed time at the left of the HUD
+const ELAPSED_TIME_WIDTH: u32 = 68;
+
+/// Draw a square button with a single character label
+///
+/// The button is highlighted when the mouse is above it.
+pub fn draw_button(text: &str, x: i32, y: i32, input: Option<InputFrame>) {
+    let highlighted = input
+        .map(|input| {
+            let (mouse_x, mouse_y) = input.coordinates();
+            is_inside(x, y, BUTTON_SIZE, BUTTON_SIZE, mouse_x, mouse_y)
+        })
+        .unwrap_or(false);
+
+    DrawColors.set(if highlighted { 0x32 } else { 0x21 });
+    draw_rect(x, y, BUTTON_SIZE, BUTTON_SIZE);
+    DrawColors.set(if highlighted { 0x01 } else { 0x03 });
+    draw_text(text, x + 2, y + 2);
+}
+
+/// Horizontal position of menu entries
+pub const ENTRY_X: i32 = 3;
+
+/// Width of menu entries
+pub const ENTRY_WIDTH: u32 = 160 - 6;
+
+/// Height of menu entries
+pub const ENTRY_HEIGHT: u32 = 14;
+
+/// Draw a menu entry spanning the whole screen
+///
+/// The entry is highlighted when selected or when the mouse is above it.
+pub fn draw_entry(text: &str, y: i32, input: Option<InputFrame>, selected: bool) {
+    let highlighted = selected
+        || input
+            .map(|input| {
+                let (mouse_x, mouse_y) = input.coordinates();
+                is_inside(ENTRY_X, y, ENTRY_WIDTH, ENTRY_HEIGHT, mouse_x, mouse_y)
+            })
+            .unwrap_or(false);
+
+    DrawColors.set(if highlighted { 0x02 } else { 0x01 });
+    draw_rect(ENTRY_X, y, ENTRY_WIDTH, ENTRY_HEIGHT);
+
+    DrawColors.set(if highlighted { 0x01 } else { 0x03 });
+    draw_text(text, ENTRY_X + 1, y + 3);
+}
+
+/// Draw the zoom buttons in the HUD
+pub fn draw_zoom_buttons(input: Option<InputFrame>) {
+    draw_button("-", ZOOM_OUT_BUTTON_X, HUD_BUTTON_Y, input);
+    draw_button("+", ZOOM_IN_BUTTON_X, HUD_BUTTON_Y, input);
+}
+
+/// Check whether a point is inside a rectangle
+pub fn is_inside(x: i32, y: i32, width: u32, height: u32, mouse_x: i16, mouse_y: i16) -> bool {
+    x <= mouse_x as i32
+        && (mouse_x as i32) < x + width as i32
+        && y <= mouse_y as i32
+        && (mouse_y as i32) < y + height as i32
+}
+
+/// Check whether a point is inside a HUD button
+pub fn is_inside_hud_button(x: i32, mouse_x: i16, mouse_y: i16) -> bool {
+    is_inside(x, HUD_BUTTON_Y, BUTTON_SIZE, BUTTON_SIZE, mouse_x, mouse_y)
+}
+
 /// Draw the remaining mines count
+///
+/// The count is drawn next to a mine icon, and it is 34 pixels wide.
 pub fn draw_remaining_mines_count(remaining_mines: usize, x: i32, y: i32) {
-    let x = x - 2;
+    let x = x + 2;
     let y = y + 2;
 
-    let s = "Mines:".to_owned() + &format_number(remaining_mines as u32, Some(2));
+    DrawColors.set(0x3340);
+    FONT_SPRITE.blit_sub(x, y, 8, 8, 8 * 11, 0);
+
     DrawColors.set(0x03);
-    draw_text(s, x, y);
+    draw_text(format_number(remaining_mines as u32, Some(3)), x + 8, y);
 }
 
 /// Draw the elapsed time
-pub fn draw_elapsed_time(elapsed_time: u32, x: i32, y: i32) {
+///
+/// The time is given in frames, and it is drawn either in whole seconds or
+/// with tenths of seconds.
+pub fn draw_elapsed_time(frames: u32, show_tenths: bool, x: i32, y: i32) {
     let x = x + 2;
     let y = y + 2;
 
-    let s = "Time:".to_owned() + &format_number(elapsed_time as u32, Some(3));
+    let s = if show_tenths {
+        format_time(frames, Some(3)) + " s"
+    } else {
+        "Time:".to_owned() + &format_number(frames / FRAMES_PER_SECOND, Some(3))
+    };
     DrawColors.set(0x03);
     draw_text(s, x, y);
 }
+
+/// Check whether a point is inside the elapsed time at the left of the HUD
+pub fn is_inside_elapsed_time(mouse_x: i16, mouse_y: i16) -> bool {
+    is_inside(
+        0,
+        HUD_BUTTON_Y,
+        ELAPSED_TIME_WIDTH,
+        BUTTON_SIZE,
+        mouse_x,
+        mouse_y,
+    )
+}
@@ -6,7 +6,7 @@
 
 //! A game engine library implementing Minesweeper based on WASM-4 engine
 
-#[cfg(feature = "buddy-alloc")]
+#[cfg(any(feature = "buddy-alloc", test))]
 mod alloc;
 
 pub mod assets;
@@ -14,6 +14,8 @@ mod debug;
 pub mod fmt;
 pub mod game;
 pub mod graphics;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod harness;
 pub mod input;
 pub mod interface;
 pub mod random;
@@ -10,36 +10,67 @@ use once_cell::unsync::Lazy;
 
 use crate::debug;
 use crate::graphics::DrawColors;
-use crate::input::Mouse;
+use crate::input::InputFrame;
 
 mod initial;
 use initial::InitialState;
 
+mod customgame;
+use customgame::CustomGameState;
+
+mod daily;
+use daily::DailyState;
+
 mod gameover;
 use gameover::GameOverState;
 
 mod gamewon;
 use gamewon::GameWonState;
 
+mod initials;
+use initials::InitialsState;
+
 mod instructions;
 use instructions::InstructionsState;
 
+mod leaderboard;
+use leaderboard::LeaderboardState;
+
 mod mainmenu;
 use mainmenu::MainMenuState;
 
+mod options;
+use options::OptionsState;
+
+mod playseed;
+use playseed::PlaySeedState;
+
 mod pause;
 use pause::PauseState;
 
 mod pregame;
 use pregame::PreGameState;
 
+mod puzzle;
+use puzzle::PuzzleState;
+
+mod puzzleselect;
+use puzzleselect::PuzzleSelectState;
+
 mod ingame;
 use ingame::InGameState;
 
+mod replay;
+use replay::ReplayState;
+
+mod statistics;
+use statistics::StatisticsState;
+
+#[cfg(test)]
+mod snapshots;
+
 /// The game state machine
-pub static mut STATE_MACHINE: Lazy<Machine> = Lazy::new(|| Machine {
-    states_stack: vec![State::Initial(InitialState::new())],
-});
+pub static mut STATE_MACHINE: Lazy<Machine> = Lazy::new(Machine::new);
 
 /// Main stackable state machine
 ///
@@ -55,16 +86,46 @@ pub struct Machine {
 }
 
 impl Machine {
+    /// Create a new state machine in the initial state
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            states_stack: vec![State::Initial(InitialState::new())],
+        }
+    }
+
+    /// Create a new state machine with a given stack of states
+    #[cfg(test)]
+    pub fn with_states(states_stack: Vec<State>) -> Self {
+        Self { states_stack }
+    }
+
+    /// Return the name of the state on top of the stack
+    pub fn state_name(&self) -> &'static str {
+        self.states_stack
+            .last()
+            .expect("Empty state machine!!!")
+            .name()
+    }
+
+    /// Return the names of all states in the stack, from bottom to top
+    pub fn state_names(&self) -> Vec<&'static str> {
+        self.states_stack.iter().map(State::name).collect()
+    }
+
     /// Draw all states in the stack
-    pub fn draw(&self) {
+    ///
+    /// Only the state on top of the stack receives the input, e.g. to
+    /// highlight entries under the mouse pointer.
+    pub fn draw(&self, input: &InputFrame) {
         for (i, state) in self.states_stack.iter().enumerate() {
-            let mouse = if i == self.states_stack.len() - 1 {
-                Some(Mouse)
+            let input = if i == self.states_stack.len() - 1 {
+                Some(*input)
             } else {
                 None
             };
             let original_colors = DrawColors.get();
-            state.draw(mouse);
+            state.draw(input);
             DrawColors.set(original_colors);
         }
     }
@@ -77,10 +138,10 @@ impl Machine {
     /// The top state is always popped from the stack.
     /// If the current state wants to remain on the stack, it must return a
     /// [`Transition::Replace`] transition containing itself.
-    pub fn update(&mut self, mouse: &Mouse) {
+    pub fn update(&mut self, input: &InputFrame) {
         let state: State = self.states_stack.pop().expect("Empty state machine!!!");
         let old_state_name = state.name();
-        let transition: Transition = state.update(mouse);
+        let transition: Transition = state.update(input);
 
         match transition {
             Transition::Replace(state) => {
@@ -115,6 +176,7 @@ impl Machine {
 }
 
 /// A state transition
+#[allow(clippy::large_enum_variant)]
 pub enum Transition {
     /// The current state is replaced with a new state
     ///
@@ -158,6 +220,36 @@ pub enum State {
 
     /// The state of main menu
     Instructions(InstructionsState),
+
+    /// The state of custom game editor
+    CustomGame(CustomGameState),
+
+    /// The state of seed code editor
+    PlaySeed(PlaySeedState),
+
+    /// The state of board of the day date editor
+    Daily(DailyState),
+
+    /// The state of a replayed game
+    Replay(ReplayState),
+
+    /// The state of initials editor after a qualifying win
+    Initials(InitialsState),
+
+    /// The state of leaderboards
+    Leaderboard(LeaderboardState),
+
+    /// The state of lifetime statistics
+    Statistics(StatisticsState),
+
+    /// The state of options editor
+    Options(OptionsState),
+
+    /// The state of puzzle selection
+    PuzzleSelect(PuzzleSelectState),
+
+    /// The state of a puzzle being played
+    Puzzle(PuzzleState),
 }
 
 impl State {
@@ -172,38 +264,68 @@ impl State {
             State::Pause(_) => "pause",
             State::MainMenu(_) => "main_menu",
             State::Instructions(_) => "instructions",
+            State::CustomGame(_) => "custom_game",
+            State::PlaySeed(_) => "play_seed",
+            State::Daily(_) => "daily",
+            State::Replay(_) => "replay",
+            State::Initials(_) => "initials",
+            State::Leaderboard(_) => "leaderboard",
+            State::Statistics(_) => "statistics",
+            State::Options(_) => "options",
+            State::PuzzleSelect(_) => "puzzle_select",
+            State::Puzzle(_) => "puzzle",
         }
     }
 
     /// Draw the current state
     ///
     /// This function delegates the drawing to the state data.
-    pub fn draw(&self, mouse: Option<Mouse>) {
+    pub fn draw(&self, input: Option<InputFrame>) {
         match self {
-            State::Initial(s) => s.draw(mouse),
-            State::PreGame(s) => s.draw(mouse),
-            State::InGame(s) => s.draw(mouse),
-            State::GameOver(s) => s.draw(mouse),
-            State::GameWon(s) => s.draw(mouse),
-            State::Pause(s) => s.draw(mouse),
-            State::MainMenu(s) => s.draw(mouse),
-            State::Instructions(s) => s.draw(mouse),
+            State::Initial(s) => s.draw(input),
+            State::PreGame(s) => s.draw(input),
+            State::InGame(s) => s.draw(input),
+            State::GameOver(s) => s.draw(input),
+            State::GameWon(s) => s.draw(input),
+            State::Pause(s) => s.draw(input),
+            State::MainMenu(s) => s.draw(input),
+            State::Instructions(s) => s.draw(input),
+            State::CustomGame(s) => s.draw(input),
+            State::PlaySeed(s) => s.draw(input),
+            State::Daily(s) => s.draw(input),
+            State::Replay(s) => s.draw(input),
+            State::Initials(s) => s.draw(input),
+            State::Leaderboard(s) => s.draw(input),
+            State::Statistics(s) => s.draw(input),
+            State::Options(s) => s.draw(input),
+            State::PuzzleSelect(s) => s.draw(input),
+            State::Puzzle(s) => s.draw(input),
         }
     }
 
     /// Update the current state
     ///
     /// This function delegates the update to the state data.
-    pub fn update(self, mouse: &Mouse) -> Transition {
+    pub fn update(self, input: &InputFrame) -> Transition {
         match self {
-            State::Initial(state) => state.update(mouse),
-            State::PreGame(state) => state.update(mouse),
-            State::InGame(state) => state.update(mouse),
-            State::GameOver(state) => state.update(mouse),
-            State::GameWon(state) => state.update(mouse),
-            State::Pause(state) => state.update(mouse),
-            State::MainMenu(state) => state.update(mouse),
-            State::Instructions(state) => state.update(mouse),
+            State::Initial(state) => state.update(input),
+            State::PreGame(state) => state.update(input),
+            State::InGame(state) => state.update(input),
+            State::GameOver(state) => state.update(input),
+            State::GameWon(state) => state.update(input),
+            State::Pause(state) => state.update(input),
+            State::MainMenu(state) => state.update(input),
+            State::Instructions(state) => state.update(input),
+            State::CustomGame(state) => state.update(input),
+            State::PlaySeed(state) => state.update(input),
+            State::Daily(state) => state.update(input),
+            State::Replay(state) => state.update(input),
+            State::Initials(state) => state.update(input),
+            State::Leaderboard(state) => state.update(input),
+            State::Statistics(state) => state.update(input),
+            State::Options(state) => state.update(input),
+            State::PuzzleSelect(state) => state.update(input),
+            State::Puzzle(state) => state.update(input),
         }
     }
 }
@@ -0,0 +1,229 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::fmt::{format_number, format_time};
+use crate::game::{Difficulty, Generator, HighScores, Settings, MAX_HEIGHT, MAX_WIDTH};
+use crate::graphics::{draw_text, DrawColors, Palette};
+use crate::input::InputFrame;
+use crate::interface::{
+    draw_button, draw_entry, is_inside, BUTTON_SIZE, ENTRY_HEIGHT, ENTRY_WIDTH, ENTRY_X,
+};
+
+use crate::wasm4::{BUTTON_1, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP};
+
+use super::{MainMenuState, PreGameState, State, Transition};
+
+/// Minimal map width and height, leaving room for a safe start
+const MIN_SIZE: usize = 4;
+
+/// Step used when changing a value with a right click
+const LARGE_STEP: isize = 10;
+
+const DECREASE_X: i32 = 96;
+const INCREASE_X: i32 = 140;
+
+const START_Y: i32 = 113;
+const BACK_Y: i32 = 130;
+
+/// Number of rows selectable with the gamepad: three values, start and back
+const ROWS_COUNT: usize = 5;
+const START_ROW: usize = 3;
+const BACK_ROW: usize = 4;
+
+#[derive(Clone)]
+pub struct CustomGameState {
+    main_menu: MainMenuState,
+    highscores: HighScores,
+    settings: Settings,
+    width: usize,
+    height: usize,
+    mines: usize,
+    selected: Option<usize>,
+}
+
+impl CustomGameState {
+    pub fn new(main_menu: MainMenuState) -> Self {
+        Self {
+            main_menu,
+            highscores: HighScores::load(),
+            settings: Settings::load(),
+            width: 8,
+            height: 8,
+            mines: 10,
+            selected: None,
+        }
+    }
+
+    pub fn draw(&self, input: Option<InputFrame>) {
+        Palette::Wheat.set();
+
+        let title = "CUSTOM GAME";
+        DrawColors.set(0x02);
+        draw_text(title, 1 + (160 - 8 * title.len() as i32) / 2, 4);
+
+        for (row, (label, value)) in [
+            ("Width", self.width),
+            ("Height", self.height),
+            ("Mines", self.mines),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let y = row_to_y(row);
+            if self.selected == Some(row) {
+                DrawColors.set(0x02);
+                draw_text(">", 0, y + 2);
+            }
+            DrawColors.set(0x03);
+            draw_text(label, 8, y + 2);
+            draw_text(format_number(*value as u32, Some(4)), 108, y + 2);
+            draw_button("<", DECREASE_X, y, input);
+            draw_button(">", INCREASE_X, y, input);
+        }
+
+        DrawColors.set(0x02);
+        draw_text("Right click: 10", 8, 81);
+
+        let difficulty = self.difficulty();
+        let no_guess = if self.settings.generator(difficulty) == Generator::NoGuess {
+            "No guessing: On"
+        } else if self.settings.is_no_guess(difficulty) {
+            "No guessing: N/A"
+        } else {
+            "No guessing: Off"
+        };
+        draw_text(no_guess, 8, 91);
+
+        let text = self
+            .highscores
+            .get(self.difficulty())
+            .map(|highscore| "Best: ".to_owned() + &format_time(highscore.time, None) + " s")
+            .unwrap_or_else(|| "Best: Unbeaten".to_owned());
+        DrawColors.set(0x03);
+        draw_text(text, 8, 101);
+
+        draw_entry("Start", START_Y, input, self.selected == Some(START_ROW));
+        draw_entry("Back", BACK_Y, input, self.selected == Some(BACK_ROW));
+    }
+
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if input.pressed(BUTTON_UP) {
+            self.selected = Some(match self.selected {
+                Some(row) if row > 0 => row - 1,
+                _ => ROWS_COUNT - 1,
+            });
+        }
+        if input.pressed(BUTTON_DOWN) {
+            self.selected = Some(match self.selected {
+                Some(row) if row + 1 < ROWS_COUNT => row + 1,
+                _ => 0,
+            });
+        }
+        if let Some(row) = self.selected {
+            if input.pressed(BUTTON_LEFT) {
+                self.change(row, -1);
+            }
+            if input.pressed(BUTTON_RIGHT) {
+                self.change(row, 1);
+            }
+            if input.pressed(BUTTON_1) {
+                match row {
+                    START_ROW => return self.start(),
+                    BACK_ROW => return Transition::Replace(State::MainMenu(self.main_menu)),
+                    _ => {}
+                }
+            }
+        }
+
+        let step = if input.left_clicked() {
+            1
+        } else if input.right_clicked() {
+            LARGE_STEP
+        } else {
+            0
+        };
+
+        if step > 0 {
+            let (mouse_x, mouse_y) = input.coordinates();
+            for row in 0..3 {
+                let y = row_to_y(row);
+                if is_inside(DECREASE_X, y, BUTTON_SIZE, BUTTON_SIZE, mouse_x, mouse_y) {
+                    self.change(row, -step);
+                }
+                if is_inside(INCREASE_X, y, BUTTON_SIZE, BUTTON_SIZE, mouse_x, mouse_y) {
+                    self.change(row, step);
+                }
+            }
+        }
+
+        if input.left_clicked() {
+            self.selected = None;
+            let (mouse_x, mouse_y) = input.coordinates();
+            if is_inside(
+                ENTRY_X,
+                START_Y,
+                ENTRY_WIDTH,
+                ENTRY_HEIGHT,
+                mouse_x,
+                mouse_y,
+            ) {
+                return self.start();
+            }
+            if is_inside(ENTRY_X, BACK_Y, ENTRY_WIDTH, ENTRY_HEIGHT, mouse_x, mouse_y) {
+                return Transition::Replace(State::MainMenu(self.main_menu));
+            }
+        }
+
+        Transition::Replace(State::CustomGame(self))
+    }
+
+    fn start(self) -> Transition {
+        let difficulty = self.difficulty();
+        let generator = self.settings.generator(difficulty);
+        Transition::Replace(State::PreGame(PreGameState::new(difficulty, generator)))
+    }
+
+    fn difficulty(&self) -> Difficulty {
+        Difficulty::Custom {
+            width: self.width,
+            height: self.height,
+            mines: self.mines,
+        }
+    }
+
+    /// Change a value, keeping all values valid
+    ///
+    /// The mines count is clamped whenever the map size changes, so that
+    /// there is always room for a safe start.
+    fn change(&mut self, row: usize, step: isize) {
+        if row > 2 {
+            return;
+        }
+        match row {
+            0 => self.width = clamp(self.width, step, MIN_SIZE, MAX_WIDTH),
+            1 => self.height = clamp(self.height, step, MIN_SIZE, MAX_HEIGHT),
+            _ => {}
+        }
+        let max_mines = Difficulty::max_mines_count(self.width, self.height);
+        let step = if row == 2 { step } else { 0 };
+        self.mines = clamp(self.mines, step, 1, max_mines);
+    }
+}
+
+fn clamp(value: usize, step: isize, min: usize, max: usize) -> usize {
+    let value = value as isize + step;
+    if value < min as isize {
+        min
+    } else if value > max as isize {
+        max
+    } else {
+        value as usize
+    }
+}
+
+fn row_to_y(row: usize) -> i32 {
+    25 + 20 * row as i32
+}
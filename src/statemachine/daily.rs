@@ -0,0 +1,183 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::fmt::{format_number, format_time};
+use crate::game::{Date, Difficulty, Generator, HighScores};
+use crate::graphics::{draw_text, DrawColors, Palette};
+use crate::input::InputFrame;
+use crate::interface::{
+    draw_button, draw_entry, is_inside, BUTTON_SIZE, ENTRY_HEIGHT, ENTRY_WIDTH, ENTRY_X,
+};
+use crate::wasm4::{BUTTON_1, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP};
+
+use super::{MainMenuState, PreGameState, State, Transition};
+
+/// Difficulty of boards of the day
+const DIFFICULTY: Difficulty = Difficulty::Medium;
+
+/// Mine layout generator of boards of the day
+const GENERATOR: Generator = Generator::NoGuess;
+
+const DECREASE_X: i32 = 96;
+const INCREASE_X: i32 = 140;
+
+const START_Y: i32 = 113;
+const BACK_Y: i32 = 130;
+
+/// Number of rows selectable with the gamepad: year, month, day, start and
+/// back
+const ROWS_COUNT: usize = 5;
+const START_ROW: usize = 3;
+const BACK_ROW: usize = 4;
+
+#[derive(Clone)]
+pub struct DailyState {
+    main_menu: MainMenuState,
+    highscores: HighScores,
+    date: Date,
+    selected: Option<usize>,
+}
+
+impl DailyState {
+    pub fn new(main_menu: MainMenuState) -> Self {
+        let highscores = HighScores::load();
+        let date = highscores
+            .daily_date()
+            .or_else(|| Date::new(2021, 1, 1))
+            .unwrap_or_else(|| Date::from_days(0));
+        Self {
+            main_menu,
+            highscores,
+            date,
+            selected: None,
+        }
+    }
+
+    pub fn draw(&self, input: Option<InputFrame>) {
+        Palette::Wheat.set();
+
+        let title = "BOARD OF THE DAY";
+        DrawColors.set(0x02);
+        draw_text(title, 1 + (160 - 8 * title.len() as i32) / 2, 4);
+
+        for (row, (label, value)) in [
+            ("Year", self.date.year() as u32),
+            ("Month", self.date.month().into()),
+            ("Day", self.date.day().into()),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let y = row_to_y(row);
+            if self.selected == Some(row) {
+                DrawColors.set(0x02);
+                draw_text(">", 0, y + 2);
+            }
+            DrawColors.set(0x03);
+            draw_text(label, 8, y + 2);
+            draw_text(format_number(*value, Some(4)), 108, y + 2);
+            draw_button("<", DECREASE_X, y, input);
+            draw_button(">", INCREASE_X, y, input);
+        }
+
+        let best = self
+            .highscores
+            .daily_best()
+            .map(|highscore| "Best: ".to_owned() + &format_time(highscore.time, None) + " s")
+            .unwrap_or_else(|| "Best: Unbeaten".to_owned());
+        let streak = "Streak: ".to_owned()
+            + &format_number(self.highscores.daily_streak(self.date).into(), None);
+        DrawColors.set(0x03);
+        draw_text(best, 8, 88);
+        draw_text(streak, 8, 98);
+
+        draw_entry("Start", START_Y, input, self.selected == Some(START_ROW));
+        draw_entry("Back", BACK_Y, input, self.selected == Some(BACK_ROW));
+    }
+
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if input.pressed(BUTTON_UP) {
+            self.selected = Some(match self.selected {
+                Some(row) if row > 0 => row - 1,
+                _ => ROWS_COUNT - 1,
+            });
+        }
+        if input.pressed(BUTTON_DOWN) {
+            self.selected = Some(match self.selected {
+                Some(row) if row + 1 < ROWS_COUNT => row + 1,
+                _ => 0,
+            });
+        }
+        if let Some(row) = self.selected {
+            if input.pressed(BUTTON_LEFT) {
+                self.change(row, -1);
+            }
+            if input.pressed(BUTTON_RIGHT) {
+                self.change(row, 1);
+            }
+            if input.pressed(BUTTON_1) {
+                match row {
+                    START_ROW => return self.start(),
+                    BACK_ROW => return Transition::Replace(State::MainMenu(self.main_menu)),
+                    _ => {}
+                }
+            }
+        }
+
+        if input.left_clicked() {
+            self.selected = None;
+            let (mouse_x, mouse_y) = input.coordinates();
+            for row in 0..3 {
+                let y = row_to_y(row);
+                if is_inside(DECREASE_X, y, BUTTON_SIZE, BUTTON_SIZE, mouse_x, mouse_y) {
+                    self.change(row, -1);
+                }
+                if is_inside(INCREASE_X, y, BUTTON_SIZE, BUTTON_SIZE, mouse_x, mouse_y) {
+                    self.change(row, 1);
+                }
+            }
+
+            if is_inside(
+                ENTRY_X,
+                START_Y,
+                ENTRY_WIDTH,
+                ENTRY_HEIGHT,
+                mouse_x,
+                mouse_y,
+            ) {
+                return self.start();
+            }
+            if is_inside(ENTRY_X, BACK_Y, ENTRY_WIDTH, ENTRY_HEIGHT, mouse_x, mouse_y) {
+                return Transition::Replace(State::MainMenu(self.main_menu));
+            }
+        }
+
+        Transition::Replace(State::Daily(self))
+    }
+
+    /// Store the date and start the board of the day
+    fn start(mut self) -> Transition {
+        self.highscores.set_daily_date(self.date);
+        self.highscores.save();
+
+        Transition::Replace(State::PreGame(
+            PreGameState::new(DIFFICULTY, GENERATOR).with_daily(self.date),
+        ))
+    }
+
+    fn change(&mut self, row: usize, step: i32) {
+        self.date = match row {
+            0 => self.date.add_years(step),
+            1 => self.date.add_months(step),
+            2 => self.date.add_days(step),
+            _ => self.date,
+        };
+    }
+}
+
+fn row_to_y(row: usize) -> i32 {
+    25 + 20 * row as i32
+}
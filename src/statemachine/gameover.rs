@@ -4,46 +4,184 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use crate::game::Map;
+use crate::fmt::{format_hundredths, format_number, pad_text};
+use crate::game::{Clicks, Map, Performance, Replay, Settings, Statistics};
 use crate::graphics::Palette;
-use crate::input::Mouse;
-use crate::interface::{draw_elapsed_time, draw_message_box, draw_remaining_mines_count};
+use crate::input::InputFrame;
+use crate::interface::{
+    draw_elapsed_time, draw_entry, draw_message_box, draw_remaining_mines_count, is_inside,
+    ENTRY_HEIGHT, ENTRY_WIDTH, ENTRY_X,
+};
 use crate::time::Timer;
+use crate::wasm4::{BUTTON_1, BUTTON_UP};
 
-use super::{MainMenuState, State, Transition};
+use super::replay::{draw_replay_entry, is_replay_requested};
+use super::{InGameState, MainMenuState, ReplayState, State, Transition};
+
+/// Vertical position of the undo entry in practice mode
+const UNDO_ENTRY_Y: i32 = 127;
+
+/// The lost game
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone)]
+enum Game {
+    /// A game that is over
+    Lost {
+        map: Map,
+        timer: Timer,
+        replay: Replay,
+    },
+
+    /// A game in practice mode, which resumes by undoing the fatal move
+    Undoable(Box<InGameState>),
+}
 
 #[derive(Clone)]
 pub struct GameOverState {
-    map: Map,
-    mines: Vec<(usize, usize)>,
-    timer: Timer,
+    game: Game,
+    three_bv: u16,
+    performance: Performance,
+    settings: Settings,
 }
 
 impl GameOverState {
-    pub fn new(map: Map, mines: Vec<(usize, usize)>, timer: Timer) -> Self {
-        Self { map, mines, timer }
+    /// Create a new state for a lost game
+    ///
+    /// Ranked games are recorded in the statistics.
+    pub fn new(map: Map, timer: Timer, clicks: Clicks, replay: Replay, ranked: bool) -> Self {
+        Self::from_game(Game::Lost { map, timer, replay }, clicks, ranked)
+    }
+
+    /// Create a new state for a lost game whose fatal move can be undone
+    ///
+    /// The game is kept as it is, including the fatal move, so that it can
+    /// be resumed without copying it.
+    pub fn with_undo(game: InGameState) -> Self {
+        let clicks = game.clicks();
+        let ranked = game.is_ranked();
+        Self::from_game(Game::Undoable(Box::new(game)), clicks, ranked)
+    }
+
+    fn from_game(game: Game, clicks: Clicks, ranked: bool) -> Self {
+        let (map, _, replay) = parts(&game);
+        let three_bv = map.three_bv() as u16;
+        let performance = Performance {
+            three_bv: map.solved_three_bv() as u16,
+            clicks: clicks.total(),
+        };
+
+        if ranked {
+            let mut statistics = Statistics::load();
+            statistics.record_loss(replay.difficulty(), map.count_uncovered_safe_tiles());
+            statistics.save();
+        }
+
+        Self {
+            game,
+            three_bv,
+            performance,
+            settings: Settings::load(),
+        }
     }
 
-    pub fn draw(&self, _mouse: Option<Mouse>) {
+    pub fn draw(&self, input: Option<InputFrame>) {
         Palette::Rustic.set();
 
-        self.map.draw(&self.mines);
+        let (map, timer, replay) = parts(&self.game);
+
+        map.draw();
 
-        let flagged_tiles = self.map.count_flagged_tiles();
-        let remaining_mines = self.mines.len() - flagged_tiles;
-        draw_remaining_mines_count(remaining_mines, 160 - 64, 2);
+        draw_remaining_mines_count(map.count_remaining_mines(), 160 - 36, 2);
 
-        let elapsed_time = self.timer.get();
-        draw_elapsed_time(elapsed_time, 2, 2);
+        draw_elapsed_time(timer.frames(), self.settings.show_tenths, 2, 2);
 
-        draw_message_box("GAME OVER!!!", 30, 30);
+        let text = "GAME OVER!!!\n\nSeed ".to_owned()
+            + &replay.seed().code()
+            + "\n\n"
+            + &performance_text(self.performance, self.three_bv, timer.frames());
+        draw_message_box(&text, 16, 30);
+
+        if let Game::Undoable(_) = self.game {
+            draw_entry("Undo move (Up)", UNDO_ENTRY_Y, input, false);
+        }
+        draw_replay_entry(input);
     }
 
-    pub fn update(self, mouse: &Mouse) -> Transition {
-        if mouse.left_clicked() {
+    pub fn update(self, input: &InputFrame) -> Transition {
+        if is_replay_requested(input) {
+            let (_, _, replay) = parts(&self.game);
+            let replay = ReplayState::new(replay.clone());
+            return Transition::Push(State::GameOver(self), State::Replay(replay));
+        }
+
+        if is_undo_requested(input) {
+            if let Game::Undoable(mut game) = self.game {
+                game.undo();
+                return Transition::Replace(State::InGame(*game));
+            }
+        }
+
+        if input.left_clicked() || input.pressed(BUTTON_1) {
             return Transition::Replace(State::MainMenu(MainMenuState::new()));
         }
 
         Transition::Replace(State::GameOver(self))
     }
 }
+
+/// Return the map, the timer and the replay of a lost game
+fn parts(game: &Game) -> (&Map, &Timer, &Replay) {
+    match game {
+        Game::Lost { map, timer, replay } => (map, timer, replay),
+        Game::Undoable(game) => (game.map(), game.timer(), game.replay()),
+    }
+}
+
+/// Check whether the player asked to undo the fatal move
+fn is_undo_requested(input: &InputFrame) -> bool {
+    let (mouse_x, mouse_y) = input.coordinates();
+    input.pressed(BUTTON_UP)
+        || input.left_clicked()
+            && is_inside(
+                ENTRY_X,
+                UNDO_ENTRY_Y,
+                ENTRY_WIDTH,
+                ENTRY_HEIGHT,
+                mouse_x,
+                mouse_y,
+            )
+}
+
+/// Return the text describing the performance of a finished game
+///
+/// The text shows the 3BV, which is prefixed by the cleared 3BV if the board
+/// was not cleared, the 3BV per second, the number of clicks and the
+/// efficiency.
+pub fn performance_text(performance: Performance, three_bv: u16, frames: u32) -> String {
+    let mut text = pad_text("3BV", 7);
+    if performance.three_bv != three_bv {
+        text += &format_number(performance.three_bv.into(), None);
+        text += "/";
+    }
+    text += &format_number(three_bv.into(), None);
+
+    text += "\n";
+    text += &pad_text("3BV/s", 7);
+    text += &performance
+        .three_bv_per_second(frames)
+        .map(format_hundredths)
+        .unwrap_or_else(|| "-".to_owned());
+
+    text += "\n";
+    text += &pad_text("Clicks", 7);
+    text += &format_number(performance.clicks.into(), None);
+
+    text += "\n";
+    text += &pad_text("Eff.", 7);
+    text += &performance
+        .efficiency()
+        .map(|efficiency| format_number(efficiency, None) + "%")
+        .unwrap_or_else(|| "-".to_owned());
+
+    text
+}
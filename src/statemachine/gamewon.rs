@@ -4,48 +4,166 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use crate::game::{Difficulty, HighScores, Map};
+use crate::fmt::format_number;
+use crate::game::{
+    Clicks, Date, Difficulty, HighScore, HighScores, LeaderboardEntry, Map, Performance, Replay,
+    Settings, Statistics,
+};
 use crate::graphics::Palette;
-use crate::input::Mouse;
+use crate::input::InputFrame;
 use crate::interface::{draw_elapsed_time, draw_message_box, draw_remaining_mines_count};
 use crate::time::Timer;
+use crate::wasm4::BUTTON_1;
 
-use super::{MainMenuState, State, Transition};
+use super::gameover::performance_text;
+use super::replay::{draw_replay_entry, is_replay_requested};
+use super::{InitialsState, MainMenuState, ReplayState, State, Transition};
 
 #[derive(Clone)]
 pub struct GameWonState {
     map: Map,
-    mines: Vec<(usize, usize)>,
     timer: Timer,
+    replay: Replay,
+    streak: Option<u16>,
+    leaderboard: Option<(usize, LeaderboardEntry)>,
+    performance: Performance,
+    settings: Settings,
+    ranked: bool,
 }
 
 impl GameWonState {
-    pub fn new(difficulty: Difficulty, map: Map, mines: Vec<(usize, usize)>, timer: Timer) -> Self {
+    /// Create a new state for a won game
+    ///
+    /// Ranked games are recorded in the high scores and in the statistics,
+    /// while unranked ones, i.e. games with undone moves, are not.
+    pub fn new(
+        map: Map,
+        timer: Timer,
+        clicks: Clicks,
+        hints: u8,
+        replay: Replay,
+        daily: Option<Date>,
+        ranked: bool,
+    ) -> Self {
+        let performance = Performance {
+            three_bv: map.three_bv() as u16,
+            clicks: clicks.total(),
+        };
+        let mut state = Self {
+            map,
+            timer,
+            replay,
+            streak: None,
+            leaderboard: None,
+            performance,
+            settings: Settings::load(),
+            ranked,
+        };
+        if ranked {
+            state.record(hints, daily);
+        }
+        state
+    }
+
+    /// Record the game in the high scores and in the statistics
+    ///
+    /// Games on the leaderboards are added once the initials are entered.
+    fn record(&mut self, hints: u8, daily: Option<Date>) {
+        let highscore = HighScore {
+            time: self.timer.frames(),
+            hints,
+            performance: Some(self.performance),
+        };
+
         let mut highscores = HighScores::load();
-        highscores.set(difficulty, timer.get() as u16);
+        let game = highscores.record_win();
+        self.streak = match daily {
+            Some(date) => {
+                highscores.set_daily(date, highscore);
+                Some(highscores.daily_streak(date))
+            }
+            None => {
+                match self.replay.difficulty() {
+                    Difficulty::Custom { .. } => {
+                        highscores.set(self.replay.difficulty(), highscore);
+                    }
+                    difficulty => {
+                        self.leaderboard =
+                            highscores.rank(difficulty, highscore.time).map(|rank| {
+                                let entry = LeaderboardEntry {
+                                    time: highscore.time,
+                                    hints,
+                                    initials: highscores.initials(),
+                                    game,
+                                    performance: highscore.performance,
+                                };
+                                (rank, entry)
+                            });
+                    }
+                }
+                None
+            }
+        };
         highscores.save();
 
-        Self { map, mines, timer }
+        let mut statistics = Statistics::load();
+        statistics.record_win(
+            self.replay.difficulty(),
+            self.timer.get() as u16,
+            self.map.count_uncovered_safe_tiles(),
+        );
+        statistics.save();
     }
 
-    pub fn draw(&self, _mouse: Option<Mouse>) {
+    pub fn draw(&self, input: Option<InputFrame>) {
         Palette::IceCream.set();
 
-        self.map.draw(&self.mines);
+        self.map.draw();
 
-        let flagged_tiles = self.map.count_flagged_tiles();
-        let remaining_mines = self.mines.len() - flagged_tiles;
-        draw_remaining_mines_count(remaining_mines, 160 - 64, 2);
+        draw_remaining_mines_count(self.map.count_remaining_mines(), 160 - 36, 2);
 
-        let elapsed_time = self.timer.get();
-        draw_elapsed_time(elapsed_time, 2, 2);
+        draw_elapsed_time(self.timer.frames(), self.settings.show_tenths, 2, 2);
 
-        draw_message_box("VICTORY!!!", 30, 30);
+        let mut text = match self.streak {
+            Some(streak) => {
+                "VICTORY!!!\n\nStreak ".to_owned() + &format_number(streak.into(), None)
+            }
+            None => "VICTORY!!!\n\nSeed ".to_owned() + &self.replay.seed().code(),
+        };
+        if !self.ranked {
+            text += "\nUnranked";
+        }
+        if let Some((rank, _)) = self.leaderboard {
+            text += "\nRank ";
+            text += &format_number(rank as u32 + 1, None);
+        }
+        text += "\n\n";
+        text += &performance_text(
+            self.performance,
+            self.performance.three_bv,
+            self.timer.frames(),
+        );
+        draw_message_box(&text, 16, 30);
+
+        draw_replay_entry(input);
     }
 
-    pub fn update(self, mouse: &Mouse) -> Transition {
-        if mouse.left_clicked() {
-            return Transition::Replace(State::MainMenu(MainMenuState::new()));
+    pub fn update(self, input: &InputFrame) -> Transition {
+        if is_replay_requested(input) {
+            let replay = ReplayState::new(self.replay.clone());
+            return Transition::Push(State::GameWon(self), State::Replay(replay));
+        }
+
+        if input.left_clicked() || input.pressed(BUTTON_1) {
+            return match self.leaderboard {
+                Some((rank, entry)) => Transition::Replace(State::Initials(InitialsState::new(
+                    self.replay.difficulty(),
+                    rank,
+                    entry,
+                    self.replay,
+                ))),
+                None => Transition::Replace(State::MainMenu(MainMenuState::new())),
+            };
         }
 
         Transition::Replace(State::GameWon(self))
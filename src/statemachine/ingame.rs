@@ -4,99 +4,198 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use crate::game::{Difficulty, Map};
-use crate::graphics::Palette;
-use crate::input::Mouse;
-use crate::interface::{draw_elapsed_time, draw_remaining_mines_count};
+use crate::game::{deduce, Action, Clicks, Cursor, Date, Map, Replay, Settings};
+use crate::graphics::{Palette, Tile};
+use crate::input::{Drag, InputFrame};
+use crate::interface::{
+    draw_button, draw_elapsed_time, draw_remaining_mines_count, draw_zoom_buttons,
+    is_inside_elapsed_time, is_inside_hud_button, HINT_BUTTON_X, HUD_BUTTON_Y, UNDO_BUTTON_X,
+    ZOOM_IN_BUTTON_X, ZOOM_OUT_BUTTON_X,
+};
 use crate::sound::play_game_over_sound;
 use crate::time::Timer;
+use crate::wasm4::{BUTTON_1, BUTTON_2};
 
 use super::{GameOverState, GameWonState, PauseState, State, Transition};
 
 const MAX_CLICK_AGE: u8 = 10;
 
+/// Seconds added to the timer for each hint
+pub const HINT_PENALTY: u32 = 10;
+
+/// Maximal number of moves that can be undone in practice mode
+///
+/// Only the tiles changed by each move are stored, but older moves are
+/// dropped anyway, since a large opening changes many tiles.
+const UNDO_DEPTH: usize = 10;
+
+/// A tile changed by a move, with its state before the move
+#[derive(Clone, Copy)]
+struct Change {
+    tile: u16,
+    previous: Tile,
+}
+
+/// A move that can be undone
+#[derive(Clone, Copy)]
+struct UndoableMove {
+    /// The index of the first tile changed by the move
+    first_change: usize,
+
+    /// The number of moves in the replay before the move
+    moves_count: usize,
+}
+
 #[derive(Clone)]
 pub struct InGameState {
-    difficulty: Difficulty,
     map: Map,
     timer: Timer,
-    mines: Vec<(usize, usize)>,
     left_click_age: u8,
     right_click_age: u8,
+    hints: u8,
+    clicks: Clicks,
+    drag: Drag,
+    cursor: Cursor,
+    replay: Replay,
+    daily: Option<Date>,
+    settings: Settings,
+    undo_moves: Vec<UndoableMove>,
+    undo_changes: Vec<Change>,
+    ranked: bool,
 }
 
 impl InGameState {
-    pub fn new(difficulty: Difficulty, map: Map, mines: Vec<(usize, usize)>) -> Self {
+    pub fn new(map: Map, cursor: Cursor, replay: Replay, daily: Option<Date>) -> Self {
         Self {
-            difficulty,
             map,
-            mines,
             timer: Timer::new(),
             left_click_age: 0,
             right_click_age: 0,
+            hints: 0,
+            // The first tile is uncovered before the game starts
+            clicks: Clicks {
+                left: 1,
+                ..Clicks::default()
+            },
+            drag: Drag::new(),
+            cursor,
+            replay,
+            daily,
+            settings: Settings::load(),
+            undo_moves: Vec::new(),
+            undo_changes: Vec::new(),
+            ranked: true,
         }
     }
 
-    pub fn draw(&self, _mouse: Option<Mouse>) {
+    pub fn draw(&self, input: Option<InputFrame>) {
         Palette::Hollow.set();
 
-        self.map.draw(&self.mines);
+        self.map.draw();
+        self.cursor.draw(&self.map);
 
-        let flagged_tiles = self.map.count_flagged_tiles();
-        let remaining_mines = self.mines.len() - flagged_tiles;
-        draw_remaining_mines_count(remaining_mines, 160 - 64, 2);
+        draw_remaining_mines_count(self.map.count_remaining_mines(), 160 - 36, 2);
 
-        let elapsed_time = self.timer.get();
-        draw_elapsed_time(elapsed_time, 2, 2);
-    }
+        draw_elapsed_time(self.timer.frames(), self.settings.show_tenths, 2, 2);
 
-    pub fn update(mut self, mouse: &Mouse) -> Transition {
-        if self.map.has_stepped_on_mine(&self.mines) {
-            for (mx, my) in &self.mines {
-                self.map.flag_tile(*mx, *my);
-            }
+        draw_button("?", HINT_BUTTON_X, HUD_BUTTON_Y, input);
+        draw_zoom_buttons(input);
+        if self.settings.practice {
+            draw_button("U", UNDO_BUTTON_X, HUD_BUTTON_Y, input);
+        }
+    }
 
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if self.map.has_stepped_on_mine() {
             play_game_over_sound();
 
+            // In practice mode the fatal move can still be undone, so the
+            // mines are flagged as part of it
+            if !self.undo_moves.is_empty() {
+                self.record_changes(Map::flag_mines);
+                return Transition::Replace(State::GameOver(GameOverState::with_undo(self)));
+            }
+
+            self.map.flag_mines();
             return Transition::Replace(State::GameOver(GameOverState::new(
-                self.map, self.mines, self.timer,
+                self.map,
+                self.timer,
+                self.clicks,
+                self.replay,
+                self.ranked,
             )));
         }
 
-        if self.has_found_all_mines() {
-            for (mx, my) in &self.mines {
-                self.map.flag_tile(*mx, *my);
-            }
+        if self.map.is_cleared() {
+            self.map.flag_mines();
             return Transition::Replace(State::GameWon(GameWonState::new(
-                self.difficulty,
                 self.map,
-                self.mines,
                 self.timer,
+                self.clicks,
+                self.hints,
+                self.replay,
+                self.daily,
+                self.ranked,
             )));
         }
 
-        if mouse.left_clicked() {
+        let (dx, dy) = self.drag.update(input);
+        self.map.pan(dx, dy);
+
+        self.cursor.update(input, &mut self.map);
+        self.handle_gamepad_buttons(input);
+
+        // Releasing the button at the end of a drag is not a click
+        let left_clicked = input.left_clicked() && !self.drag.is_dragging();
+
+        if left_clicked {
             self.left_click_age = MAX_CLICK_AGE;
         }
-        if mouse.right_clicked() {
+        if input.right_clicked() {
             self.right_click_age = MAX_CLICK_AGE;
         }
+        if left_clicked || input.right_clicked() {
+            self.cursor.hide();
+        }
 
-        if self.left_click_age > 0 && self.right_click_age > 0 {
-            let (x, y) = mouse.coordinates();
-            self.map.handle_left_and_right_click(x, y, &self.mines);
-        } else if mouse.left_clicked() {
-            let (x, y) = mouse.coordinates();
-            if self.map.mouse_to_tile(x, y).is_none() {
-                return Transition::Push(State::InGame(self), State::Pause(PauseState::new()));
+        let (mouse_x, mouse_y) = input.coordinates();
+        if input.middle_clicked()
+            || left_clicked && is_inside_hud_button(HINT_BUTTON_X, mouse_x, mouse_y)
+        {
+            self.give_hint();
+        } else if left_clicked && is_inside_hud_button(ZOOM_OUT_BUTTON_X, mouse_x, mouse_y) {
+            self.map.zoom_out();
+        } else if left_clicked && is_inside_hud_button(ZOOM_IN_BUTTON_X, mouse_x, mouse_y) {
+            self.map.zoom_in();
+        } else if left_clicked && is_inside_elapsed_time(mouse_x, mouse_y) {
+            self.toggle_tenths();
+        } else if left_clicked
+            && self.settings.practice
+            && is_inside_hud_button(UNDO_BUTTON_X, mouse_x, mouse_y)
+        {
+            self.undo();
+        } else if self.left_click_age > 0 && self.right_click_age > 0 {
+            if let Some((x, y)) = self.map.mouse_to_tile(mouse_x, mouse_y) {
+                self.chord(x, y);
+            }
+            // Chord only once per pair of clicks
+            self.left_click_age = 0;
+            self.right_click_age = 0;
+        } else if left_clicked {
+            match self.map.mouse_to_tile(mouse_x, mouse_y) {
+                Some((x, y)) => self.uncover(x, y),
+                None => {
+                    return Transition::Push(State::InGame(self), State::Pause(PauseState::new()))
+                }
+            }
+        } else if input.right_clicked() {
+            if let Some((x, y)) = self.map.mouse_to_tile(mouse_x, mouse_y) {
+                self.flip_flag(x, y);
             }
-            self.map.handle_left_click(x, y, &self.mines);
-        } else if mouse.right_clicked() {
-            let (x, y) = mouse.coordinates();
-            self.map.handle_right_click(x, y);
         }
 
         self.timer.update();
+        self.replay.tick();
 
         self.left_click_age = self.left_click_age.saturating_sub(1);
         self.right_click_age = self.right_click_age.saturating_sub(1);
@@ -104,7 +203,303 @@ impl InGameState {
         Transition::Replace(State::InGame(self))
     }
 
-    fn has_found_all_mines(&self) -> bool {
-        self.map.count_uncovered_tiles() + self.mines.len() == self.map.width() * self.map.height()
+    /// Handle gamepad buttons on the tile under the cursor
+    ///
+    /// Button 1 uncovers the tile, button 2 flags it, and pressing both
+    /// uncovers its neighbours.
+    fn handle_gamepad_buttons(&mut self, input: &InputFrame) {
+        let (x, y) = self.cursor.position();
+        let pressed_1 = input.pressed(BUTTON_1);
+        let pressed_2 = input.pressed(BUTTON_2);
+        if pressed_1 && input.held(BUTTON_2) || pressed_2 && input.held(BUTTON_1) {
+            self.chord(x, y);
+        } else if pressed_1 {
+            self.uncover(x, y);
+        } else if pressed_2 {
+            self.flip_flag(x, y);
+        }
+    }
+
+    /// Uncover a tile and record the action
+    fn uncover(&mut self, x: usize, y: usize) {
+        self.clicks.left = self.clicks.left.saturating_add(1);
+        self.apply_move(|map| map.uncover_tile(x, y));
+        self.replay.record(Action::Uncover(x, y));
+    }
+
+    /// Flag or unflag a tile and record the action
+    fn flip_flag(&mut self, x: usize, y: usize) {
+        self.clicks.right = self.clicks.right.saturating_add(1);
+        self.apply_move(|map| map.flip_flagged_tile(x, y));
+        self.replay.record(Action::Flag(x, y));
+    }
+
+    /// Uncover the neighbours of a tile and record the action
+    fn chord(&mut self, x: usize, y: usize) {
+        self.clicks.chord = self.clicks.chord.saturating_add(1);
+        self.apply_move(|map| map.chord_tile(x, y));
+        self.replay.record(Action::Chord(x, y));
+    }
+
+    /// Uncover a tile that is provably safe
+    ///
+    /// If no tile can be deduced to be safe, nothing happens and no penalty
+    /// is added.
+    /// Hints cannot be undone, and neither can the moves before them.
+    fn give_hint(&mut self) {
+        let deductions = deduce(&self.map);
+        if let Some((x, y)) = deductions.safe.first() {
+            self.undo_moves.clear();
+            self.undo_changes.clear();
+            // Flags might be wrong, so they must not drive the uncovering
+            self.map.uncover_tile_ignoring_flags(*x, *y);
+            self.replay.record(Action::Hint(*x, *y));
+            self.timer.add(HINT_PENALTY);
+            self.hints = self.hints.saturating_add(1);
+        }
+    }
+
+    /// Apply a move to the map
+    ///
+    /// In practice mode, the tiles changed by the move are stored so that
+    /// it can be undone.
+    fn apply_move(&mut self, apply: impl FnOnce(&mut Map)) {
+        if !self.settings.practice {
+            apply(&mut self.map);
+            return;
+        }
+        if self.undo_moves.len() == UNDO_DEPTH {
+            self.undo_moves.remove(0);
+            let dropped = self.undo_moves[0].first_change;
+            self.undo_changes.drain(..dropped);
+            for undoable in self.undo_moves.iter_mut() {
+                undoable.first_change -= dropped;
+            }
+        }
+        self.undo_moves.push(UndoableMove {
+            first_change: self.undo_changes.len(),
+            moves_count: self.replay.moves().len(),
+        });
+        self.record_changes(apply);
+    }
+
+    /// Apply changes to the map as part of the last move
+    ///
+    /// The previous state of each tile is stored as two bitmasks per row
+    /// while applying the changes, rather than as a copy of all tiles.
+    fn record_changes(&mut self, apply: impl FnOnce(&mut Map)) {
+        let covered = tile_rows(&self.map, Tile::Covered);
+        let flagged = tile_rows(&self.map, Tile::Flagged);
+        apply(&mut self.map);
+
+        let width = self.map.width();
+        for y in 0..self.map.height() {
+            for x in 0..width {
+                let previous = if covered[y] & 1 << x != 0 {
+                    Tile::Covered
+                } else if flagged[y] & 1 << x != 0 {
+                    Tile::Flagged
+                } else {
+                    Tile::Uncovered
+                };
+                if *self.map.tile(x, y) != previous {
+                    self.undo_changes.push(Change {
+                        tile: (x + y * width) as u16,
+                        previous,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Restore the state of the game before the last move
+    ///
+    /// The undone move is removed from the replay, and the game becomes
+    /// unranked.
+    /// Return `false` if there is no move to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.undo_moves.pop() {
+            Some(undoable) => {
+                let width = self.map.width();
+                for change in self.undo_changes.drain(undoable.first_change..) {
+                    let tile = change.tile as usize;
+                    self.map
+                        .restore_tile(tile % width, tile / width, change.previous);
+                }
+                self.replay.rewind(undoable.moves_count);
+                self.ranked = false;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Return the map
+    pub fn map(&self) -> &Map {
+        &self.map
+    }
+
+    /// Return the timer
+    pub fn timer(&self) -> &Timer {
+        &self.timer
+    }
+
+    /// Return the clicks made so far
+    pub fn clicks(&self) -> Clicks {
+        self.clicks
+    }
+
+    /// Return the replay
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Check whether the game is ranked
+    pub fn is_ranked(&self) -> bool {
+        self.ranked
+    }
+
+    /// Show or hide the tenths of seconds of the elapsed time
+    fn toggle_tenths(&mut self) {
+        self.settings.show_tenths = !self.settings.show_tenths;
+        self.settings.save();
+    }
+}
+
+/// Return one bitmask per row of the tiles in a given state
+fn tile_rows(map: &Map, tile: Tile) -> Vec<u32> {
+    (0..map.height())
+        .map(|y| {
+            (0..map.width())
+                .filter(|x| *map.tile(*x, y) == tile)
+                .fold(0, |row, x| row | 1 << x)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::{Difficulty, Generator, Seed};
+
+    #[test]
+    fn hint_ignores_wrong_flags() {
+        let _harness = crate::harness::Harness::new();
+        let map = Map::from_ascii(
+            "
+            0 0 1 . f
+            0 0 1 * .
+            0 0 1 1 1
+            ",
+            10,
+            (0, 0),
+        )
+        .unwrap();
+        let cursor = Cursor::new(&map);
+        let replay = Replay::new(
+            Difficulty::Easy,
+            Generator::Random,
+            Seed::from_code("SWEEP2").unwrap(),
+            (0, 0),
+        );
+        let mut state = InGameState::new(map, cursor, replay, None);
+
+        // Both safe tiles are next to the wrong flag
+        state.give_hint();
+        assert_eq!(state.hints, 1);
+        assert!(!state.map.has_stepped_on_mine());
+        assert_eq!(state.map.count_uncovered_tiles(), 12);
+    }
+
+    fn practice_game(map: Map) -> InGameState {
+        let cursor = Cursor::new(&map);
+        let replay = Replay::new(
+            Difficulty::Easy,
+            Generator::Random,
+            Seed::from_code("SWEEP2").unwrap(),
+            (0, 0),
+        );
+        let mut state = InGameState::new(map, cursor, replay, None);
+        state.settings.practice = true;
+        state
+    }
+
+    #[test]
+    fn undo_restores_tiles() {
+        let _harness = crate::harness::Harness::new();
+        let map = Map::from_ascii(
+            "
+            . . . . .
+            . . . . .
+            . . . * .
+            ",
+            10,
+            (0, 0),
+        )
+        .unwrap();
+        let mut state = practice_game(map);
+
+        state.uncover(0, 0);
+        let opened = state.map.to_ascii();
+        state.flip_flag(3, 2);
+        state.flip_flag(4, 2);
+        state.flip_flag(4, 2);
+        assert_eq!(state.map.count_flagged_tiles(), 1);
+
+        for _ in 0..3 {
+            assert!(state.undo());
+        }
+        assert_eq!(state.map.to_ascii(), opened);
+        assert_eq!(state.replay.moves().len(), 1);
+
+        assert!(state.undo());
+        assert_eq!(state.map.count_uncovered_tiles(), 0);
+        assert!(!state.undo());
+        assert!(!state.ranked);
+    }
+
+    #[cfg(not(feature = "buddy-alloc"))]
+    #[test]
+    fn undo_fits_console_heap_on_largest_map() {
+        use crate::game::{MAX_HEIGHT, MAX_WIDTH};
+        use crate::harness::with_console_heap;
+        use crate::wasm4::BUTTON_UP;
+
+        let _harness = crate::harness::Harness::new();
+        let (uncovered, restored) = with_console_heap(|| {
+            // The first move opens all rows but the last two
+            let mines: Vec<(usize, usize)> = (0..MAX_WIDTH)
+                .step_by(2)
+                .map(|x| (x, MAX_HEIGHT - 1))
+                .collect();
+            let map = Map::new(MAX_WIDTH, MAX_HEIGHT, 10, (0, 0)).with_mines(&mines);
+            let mut state = practice_game(map);
+
+            state.uncover(0, 0);
+            for x in 0..UNDO_DEPTH {
+                state.flip_flag(2 * x, MAX_HEIGHT - 1);
+            }
+            let uncovered = state.map.count_uncovered_tiles();
+
+            // Stepping on a mine and undoing it from the game over screen
+            state.uncover(2 * UNDO_DEPTH, MAX_HEIGHT - 1);
+            let state = match state.update(&InputFrame::default()) {
+                Transition::Replace(State::GameOver(state)) => state,
+                _ => return (uncovered, 0),
+            };
+            let input = InputFrame {
+                gamepad_buttons: BUTTON_UP,
+                ..InputFrame::default()
+            };
+            match state.update(&input) {
+                Transition::Replace(State::InGame(state)) => {
+                    (uncovered, state.map.count_uncovered_tiles())
+                }
+                _ => (uncovered, 0),
+            }
+        });
+        assert!(uncovered > MAX_WIDTH * (MAX_HEIGHT - 2));
+        assert_eq!(restored, uncovered);
     }
 }
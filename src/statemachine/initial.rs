@@ -4,7 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use crate::input::Mouse;
+use crate::input::InputFrame;
 
 use super::{MainMenuState, State, Transition};
 
@@ -17,9 +17,9 @@ impl InitialState {
         Self {}
     }
 
-    pub fn draw(&self, _mouse: Option<Mouse>) {}
+    pub fn draw(&self, _input: Option<InputFrame>) {}
 
-    pub fn update(self, _mouse: &Mouse) -> Transition {
+    pub fn update(self, _input: &InputFrame) -> Transition {
         Transition::Push(State::Initial(self), State::MainMenu(MainMenuState::new()))
     }
 }
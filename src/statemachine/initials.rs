@@ -0,0 +1,277 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::fmt::{format_number, format_time};
+use crate::game::{Difficulty, HighScores, LeaderboardEntry, Replay};
+use crate::graphics::{draw_rect, draw_text, DrawColors, Palette};
+use crate::input::InputFrame;
+use crate::interface::{
+    draw_button, draw_entry, is_inside, BUTTON_SIZE, ENTRY_HEIGHT, ENTRY_WIDTH, ENTRY_X,
+};
+use crate::wasm4::{BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP};
+
+use super::{LeaderboardState, MainMenuState, State, Transition};
+
+/// Letters available for initials
+const INITIALS_ALPHABET: &[u8; 26] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Number of letters in initials
+const INITIALS_LENGTH: usize = 3;
+
+/// Horizontal position of the first letter
+const INITIALS_X: i32 = 50;
+
+/// Horizontal distance between letters
+const INITIALS_STEP: i32 = 20;
+
+const INITIALS_UP_Y: i32 = 60;
+const INITIALS_Y: i32 = 76;
+const INITIALS_DOWN_Y: i32 = 90;
+
+const SAVE_Y: i32 = 130;
+
+/// Number of rows selectable with the gamepad: initials and save
+const ROWS_COUNT: usize = 2;
+const INITIALS_ROW: usize = 0;
+const SAVE_ROW: usize = 1;
+
+/// A state entering the initials of a player after a qualifying win
+#[derive(Clone)]
+pub struct InitialsState {
+    difficulty: Difficulty,
+    rank: usize,
+    entry: LeaderboardEntry,
+    replay: Replay,
+    initials: [usize; INITIALS_LENGTH],
+    position: usize,
+    selected: Option<usize>,
+}
+
+impl InitialsState {
+    /// Create a new state for an entry qualifying at a rank
+    ///
+    /// The initials of the entry are used as starting point.
+    pub fn new(
+        difficulty: Difficulty,
+        rank: usize,
+        entry: LeaderboardEntry,
+        replay: Replay,
+    ) -> Self {
+        let mut initials = [0; INITIALS_LENGTH];
+        for (letter, initial) in initials.iter_mut().zip(entry.initials.iter()) {
+            *letter = INITIALS_ALPHABET
+                .iter()
+                .position(|c| c == initial)
+                .unwrap_or(0);
+        }
+        Self {
+            difficulty,
+            rank,
+            entry,
+            replay,
+            initials,
+            position: 0,
+            selected: None,
+        }
+    }
+
+    pub fn draw(&self, input: Option<InputFrame>) {
+        Palette::Wheat.set();
+
+        let title = "NEW HIGH SCORE";
+        DrawColors.set(0x02);
+        draw_text(title, 1 + (160 - 8 * title.len() as i32) / 2, 4);
+
+        DrawColors.set(0x03);
+        let rank = "Rank ".to_owned()
+            + &format_number(self.rank as u32 + 1, None)
+            + " in "
+            + self.difficulty.as_ref();
+        draw_text(&rank, 1 + (160 - 8 * rank.len() as i32) / 2, 22);
+        let time = format_time(self.entry.time, None) + " s";
+        draw_text(&time, 1 + (160 - 8 * time.len() as i32) / 2, 34);
+
+        for (i, letter) in self.initials.iter().enumerate() {
+            let x = letter_to_x(i);
+            draw_button("+", x, INITIALS_UP_Y, input);
+            draw_button("-", x, INITIALS_DOWN_Y, input);
+
+            let character = [INITIALS_ALPHABET[*letter]];
+            let character = core::str::from_utf8(&character).unwrap_or("?");
+            if self.selected == Some(INITIALS_ROW) && self.position == i {
+                DrawColors.set(0x02);
+                draw_rect(x, INITIALS_Y - 2, BUTTON_SIZE, BUTTON_SIZE);
+                DrawColors.set(0x01);
+            } else {
+                DrawColors.set(0x03);
+            }
+            draw_text(character, x + 2, INITIALS_Y);
+        }
+
+        draw_entry("Save", SAVE_Y, input, self.selected == Some(SAVE_ROW));
+    }
+
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if input.pressed(BUTTON_UP) {
+            self.selected = Some(match self.selected {
+                Some(row) if row > 0 => row - 1,
+                _ => ROWS_COUNT - 1,
+            });
+        }
+        if input.pressed(BUTTON_DOWN) {
+            self.selected = Some(match self.selected {
+                Some(row) if row + 1 < ROWS_COUNT => row + 1,
+                _ => 0,
+            });
+        }
+        match self.selected {
+            Some(INITIALS_ROW) => {
+                if input.pressed(BUTTON_LEFT) {
+                    self.position = (self.position + INITIALS_LENGTH - 1) % INITIALS_LENGTH;
+                }
+                if input.pressed(BUTTON_RIGHT) {
+                    self.position = (self.position + 1) % INITIALS_LENGTH;
+                }
+                if input.pressed(BUTTON_1) {
+                    self.change_letter(self.position, 1);
+                }
+                if input.pressed(BUTTON_2) {
+                    self.change_letter(self.position, -1);
+                }
+            }
+            Some(SAVE_ROW) if input.pressed(BUTTON_1) => return self.save(),
+            _ => {}
+        }
+
+        if input.left_clicked() {
+            self.selected = None;
+            let (mouse_x, mouse_y) = input.coordinates();
+
+            let is_inside_button =
+                |x, y| is_inside(x, y, BUTTON_SIZE, BUTTON_SIZE, mouse_x, mouse_y);
+            for i in 0..INITIALS_LENGTH {
+                if is_inside_button(letter_to_x(i), INITIALS_UP_Y) {
+                    self.change_letter(i, 1);
+                }
+                if is_inside_button(letter_to_x(i), INITIALS_DOWN_Y) {
+                    self.change_letter(i, -1);
+                }
+            }
+
+            if is_inside(ENTRY_X, SAVE_Y, ENTRY_WIDTH, ENTRY_HEIGHT, mouse_x, mouse_y) {
+                return self.save();
+            }
+        }
+
+        Transition::Replace(State::Initials(self))
+    }
+
+    /// Add the entry to the leaderboard and show it
+    ///
+    /// The replay is stored if the entry is the best one.
+    fn save(self) -> Transition {
+        let entry = LeaderboardEntry {
+            initials: self.initials(),
+            ..self.entry
+        };
+
+        let mut highscores = HighScores::load();
+        let rank = highscores.insert(self.difficulty, entry);
+        if rank == Some(0) {
+            highscores.set_replay(self.replay);
+        }
+        highscores.save();
+
+        let mut leaderboard = LeaderboardState::new(MainMenuState::new(), self.difficulty);
+        if let Some(rank) = rank {
+            leaderboard = leaderboard.with_highlight(rank);
+        }
+        Transition::Replace(State::Leaderboard(leaderboard))
+    }
+
+    /// Return the entered initials
+    fn initials(&self) -> [u8; INITIALS_LENGTH] {
+        let mut initials = [0; INITIALS_LENGTH];
+        for (initial, letter) in initials.iter_mut().zip(self.initials.iter()) {
+            *initial = INITIALS_ALPHABET[*letter];
+        }
+        initials
+    }
+
+    fn change_letter(&mut self, position: usize, step: isize) {
+        let count = INITIALS_ALPHABET.len() as isize;
+        self.initials[position] =
+            (self.initials[position] as isize + step).rem_euclid(count) as usize;
+    }
+}
+
+fn letter_to_x(position: usize) -> i32 {
+    INITIALS_X + INITIALS_STEP * position as i32 + (INITIALS_STEP - BUTTON_SIZE as i32) / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::{Generator, Seed};
+
+    fn press(button: u8) -> InputFrame {
+        InputFrame {
+            gamepad_buttons: button,
+            ..InputFrame::default()
+        }
+    }
+
+    fn update(state: InitialsState, input: &InputFrame) -> InitialsState {
+        match state.update(input) {
+            Transition::Replace(State::Initials(state)) => state,
+            _ => panic!("Unexpected transition"),
+        }
+    }
+
+    #[test]
+    fn gamepad_enters_initials() {
+        let _harness = crate::harness::Harness::new();
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        let replay = Replay::new(Difficulty::Easy, Generator::Random, seed, (0, 0));
+        let entry = LeaderboardEntry {
+            time: 42,
+            hints: 0,
+            initials: *b"AZ-",
+            game: 3,
+            performance: None,
+        };
+        let mut state = InitialsState::new(Difficulty::Easy, 0, entry, replay.clone());
+        assert_eq!(state.initials(), *b"AZA");
+
+        state = update(state, &press(BUTTON_DOWN));
+        state = update(state, &press(BUTTON_2));
+        state = update(state, &press(BUTTON_RIGHT));
+        state = update(state, &press(BUTTON_1));
+        state = update(state, &press(BUTTON_LEFT));
+        state = update(state, &press(BUTTON_LEFT));
+        state = update(state, &press(BUTTON_1));
+        state = update(state, &press(BUTTON_1));
+        assert_eq!(state.initials(), *b"ZAC");
+
+        state = update(state, &press(BUTTON_DOWN));
+        match state.update(&press(BUTTON_1)) {
+            Transition::Replace(State::Leaderboard(_)) => {}
+            _ => panic!("Unexpected transition"),
+        }
+
+        let highscores = HighScores::load();
+        assert_eq!(
+            highscores.leaderboard(Difficulty::Easy),
+            &[LeaderboardEntry {
+                initials: *b"ZAC",
+                ..entry
+            }]
+        );
+        assert_eq!(highscores.initials(), *b"ZAC");
+        assert_eq!(highscores.replay(Difficulty::Easy), Some(&replay));
+    }
+}
@@ -5,8 +5,9 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use crate::graphics::{draw_text, DrawColors, Tile};
-use crate::input::Mouse;
+use crate::input::InputFrame;
 use crate::interface::draw_box;
+use crate::wasm4::{BUTTON_1, BUTTON_LEFT, BUTTON_RIGHT};
 
 use super::{State, Transition};
 
@@ -23,7 +24,7 @@ impl InstructionsState {
         Self { index: 0 }
     }
 
-    pub fn draw(&self, _mouse: Option<Mouse>) {
+    pub fn draw(&self, _input: Option<InputFrame>) {
         let x = 10;
         let y = 10;
 
@@ -32,8 +33,11 @@ impl InstructionsState {
         PAGES[self.index](x, y);
     }
 
-    pub fn update(mut self, mouse: &Mouse) -> Transition {
-        if mouse.left_clicked() {
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if input.pressed(BUTTON_LEFT) {
+            self.index = self.index.saturating_sub(1);
+            Transition::Replace(State::Instructions(self))
+        } else if input.left_clicked() || input.pressed(BUTTON_1) || input.pressed(BUTTON_RIGHT) {
             if self.index >= PAGES.len() - 1 {
                 Transition::Pop
             } else {
@@ -46,7 +50,7 @@ impl InstructionsState {
     }
 }
 
-const PAGES: [fn(i32, i32) -> (); 8] = [
+const PAGES: [fn(i32, i32) -> (); 9] = [
     |x, y| {
         DrawColors.set(3);
         draw_text(
@@ -252,6 +256,25 @@ more flags.",
         Tile::Uncovered.draw(x + 66 + 20, y + 105, TILE_SIZE, false, 3);
         Tile::Flagged.draw(x + 66 + 30, y + 105, TILE_SIZE, true, 0);
     },
+    |x, y| {
+        DrawColors.set(3);
+        draw_text(
+            "Stuck? Click on
+the ? button, or
+middle click, to
+uncover a safe
+tile.",
+            x + 3,
+            y + 3,
+        );
+        draw_text(
+            "Each hint adds
+10 seconds to
+the timer.",
+            x + 3,
+            y + 55,
+        );
+    },
     |x, y| {
         DrawColors.set(3);
         draw_text(
@@ -265,3 +288,51 @@ tiles.",
         draw_text("Except mines ;)", x + 3, y + 40);
     },
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::input::{InputSource, ScriptedInput};
+
+    /// Run a script on the instructions, returning the page index or `None`
+    /// if the instructions were closed
+    fn run(input: &mut ScriptedInput) -> Option<usize> {
+        let mut state = InstructionsState::new();
+        while !input.is_finished() {
+            match state.update(&input.next_frame()) {
+                Transition::Replace(State::Instructions(s)) => state = s,
+                Transition::Pop => return None,
+                _ => panic!("Unexpected transition"),
+            }
+        }
+        Some(state.index)
+    }
+
+    #[test]
+    fn clicks_turn_pages() {
+        let mut input = ScriptedInput::new();
+        input.left_click(80, 80).left_click(80, 80);
+        assert_eq!(run(&mut input), Some(2));
+    }
+
+    #[test]
+    fn gamepad_turns_pages_both_ways() {
+        let mut input = ScriptedInput::new();
+        input
+            .press(BUTTON_RIGHT)
+            .press(BUTTON_1)
+            .press(BUTTON_1)
+            .press(BUTTON_LEFT);
+        assert_eq!(run(&mut input), Some(2));
+    }
+
+    #[test]
+    fn last_page_closes_instructions() {
+        let mut input = ScriptedInput::new();
+        for _ in 0..PAGES.len() {
+            input.left_click(80, 80);
+        }
+        assert_eq!(run(&mut input), None);
+    }
+}
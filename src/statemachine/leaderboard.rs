@@ -0,0 +1,150 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::fmt::{format_number, format_time};
+use crate::game::{Difficulty, HighScores, LEADERBOARD_SIZE};
+use crate::graphics::{draw_rect, draw_text, DrawColors, Palette};
+use crate::input::InputFrame;
+use crate::interface::{
+    draw_button, draw_entry, is_inside, BUTTON_SIZE, ENTRY_HEIGHT, ENTRY_WIDTH, ENTRY_X,
+};
+use crate::wasm4::{BUTTON_1, BUTTON_2, BUTTON_LEFT, BUTTON_RIGHT};
+
+use super::{MainMenuState, State, Transition};
+
+/// Difficulties with a leaderboard
+const DIFFICULTIES: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+const DIFFICULTY_Y: i32 = 25;
+const DECREASE_X: i32 = 8;
+const INCREASE_X: i32 = 140;
+
+/// Vertical position of the first entry
+const ENTRIES_Y: i32 = 48;
+
+/// Vertical distance between entries
+const ENTRIES_STEP: i32 = 12;
+
+const BACK_Y: i32 = 130;
+
+/// A state showing the leaderboards of easy, medium and hard games
+#[derive(Clone)]
+pub struct LeaderboardState {
+    main_menu: MainMenuState,
+    highscores: HighScores,
+    difficulty: usize,
+    highlighted: Option<usize>,
+}
+
+impl LeaderboardState {
+    /// Create a new state showing the leaderboard of a difficulty
+    ///
+    /// Custom games have no leaderboard, so the easy one is shown instead.
+    pub fn new(main_menu: MainMenuState, difficulty: Difficulty) -> Self {
+        Self {
+            main_menu,
+            highscores: HighScores::load(),
+            difficulty: DIFFICULTIES
+                .iter()
+                .position(|other| *other == difficulty)
+                .unwrap_or(0),
+            highlighted: None,
+        }
+    }
+
+    /// Highlight the entry at a rank
+    pub fn with_highlight(self, rank: usize) -> Self {
+        Self {
+            highlighted: Some(rank),
+            ..self
+        }
+    }
+
+    pub fn draw(&self, input: Option<InputFrame>) {
+        Palette::Wheat.set();
+
+        let title = "LEADERBOARDS";
+        DrawColors.set(0x02);
+        draw_text(title, 1 + (160 - 8 * title.len() as i32) / 2, 4);
+
+        let difficulty = DIFFICULTIES[self.difficulty];
+        DrawColors.set(0x03);
+        draw_text(
+            difficulty.as_ref(),
+            1 + (160 - 8 * difficulty.as_ref().len() as i32) / 2,
+            DIFFICULTY_Y + 2,
+        );
+        draw_button("<", DECREASE_X, DIFFICULTY_Y, input);
+        draw_button(">", INCREASE_X, DIFFICULTY_Y, input);
+
+        let leaderboard = self.highscores.leaderboard(difficulty);
+        for rank in 0..LEADERBOARD_SIZE {
+            let y = ENTRIES_Y + ENTRIES_STEP * rank as i32;
+            let is_highlighted = self.highlighted == Some(rank);
+            if is_highlighted {
+                DrawColors.set(0x02);
+                draw_rect(3, y - 2, 154, ENTRIES_STEP as u32);
+                DrawColors.set(0x01);
+            } else {
+                DrawColors.set(0x03);
+            }
+            draw_text(format_number(rank as u32 + 1, None), 8, y);
+
+            match leaderboard.get(rank) {
+                Some(entry) => {
+                    let initials = core::str::from_utf8(&entry.initials).unwrap_or("???");
+                    draw_text(initials, 24, y);
+                    draw_text(format_time(entry.time, Some(4)) + " s", 56, y);
+                    if entry.game > 0 {
+                        let game = "#".to_owned() + &format_number(entry.game.into(), None);
+                        draw_text(&game, 156 - 8 * game.len() as i32, y);
+                    }
+                }
+                None => draw_text("---", 24, y),
+            }
+        }
+
+        draw_entry("Back", BACK_Y, input, false);
+    }
+
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if input.pressed(BUTTON_LEFT) {
+            self.change_difficulty(-1);
+        }
+        if input.pressed(BUTTON_RIGHT) {
+            self.change_difficulty(1);
+        }
+        if input.pressed(BUTTON_1) || input.pressed(BUTTON_2) {
+            return Transition::Replace(State::MainMenu(self.main_menu));
+        }
+
+        if input.left_clicked() {
+            let (mouse_x, mouse_y) = input.coordinates();
+            let is_inside_button =
+                |x, y| is_inside(x, y, BUTTON_SIZE, BUTTON_SIZE, mouse_x, mouse_y);
+            if is_inside_button(DECREASE_X, DIFFICULTY_Y) {
+                self.change_difficulty(-1);
+            }
+            if is_inside_button(INCREASE_X, DIFFICULTY_Y) {
+                self.change_difficulty(1);
+            }
+            if is_inside(ENTRY_X, BACK_Y, ENTRY_WIDTH, ENTRY_HEIGHT, mouse_x, mouse_y) {
+                return Transition::Replace(State::MainMenu(self.main_menu));
+            }
+        }
+
+        Transition::Replace(State::Leaderboard(self))
+    }
+
+    /// Show the leaderboard of another difficulty
+    ///
+    /// The highlight only applies to the leaderboard it was set for.
+    fn change_difficulty(&mut self, step: isize) {
+        let count = DIFFICULTIES.len() as isize;
+        self.difficulty = (self.difficulty as isize + step).rem_euclid(count) as usize;
+        self.highlighted = None;
+    }
+}
@@ -5,108 +5,241 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use crate::fmt::{format_number, pad_text};
-use crate::game::{Difficulty, HighScores};
+use crate::game::{Difficulty, HighScores, Settings};
 use crate::graphics::{draw_rect, draw_text, DrawColors, Palette};
-use crate::input::Mouse;
+use crate::input::InputFrame;
+use crate::time::FRAMES_PER_SECOND;
+use crate::wasm4::{BUTTON_1, BUTTON_DOWN, BUTTON_UP};
 
-use super::{InstructionsState, PreGameState, State, Transition};
+use super::{
+    CustomGameState, DailyState, InstructionsState, LeaderboardState, OptionsState, PlaySeedState,
+    PreGameState, PuzzleSelectState, ReplayState, State, StatisticsState, Transition,
+};
 
 #[derive(Clone)]
 pub struct MainMenuState {
     highscores: HighScores,
+    selected: Option<usize>,
 }
 
 const WIDTH: u32 = 160 - 6;
-const HEIGHT: u32 = 14;
+const HEIGHT: u32 = 10;
+
+/// Number of menu entries
+const ENTRIES_COUNT: usize = 11;
+
+/// Difficulties listed in the high scores table
+const HIGHSCORE_DIFFICULTIES: [Difficulty; 3] =
+    [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+/// Height of a row in the high scores table
+const HIGHSCORE_HEIGHT: u32 = 9;
+
+/// Number of digits of the high score times
+const TIME_LENGTH: usize = 4;
+
+/// Longest high score time shown, in seconds
+const MAX_SECONDS: u32 = 9999;
+
+/// Horizontal position of the hints count of high scores, one character
+/// after the time
+const HINTS_X: i32 = 4 + 8 * (7 + TIME_LENGTH as i32 + 3);
+
+/// Number of selectable entries, i.e. menu entries and high scores
+const SELECTABLE_COUNT: usize = ENTRIES_COUNT + HIGHSCORE_DIFFICULTIES.len();
 
 impl MainMenuState {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             highscores: HighScores::load(),
+            selected: None,
         }
     }
 
-    pub fn draw(&self, mouse: Option<Mouse>) {
+    pub fn draw(&self, input: Option<InputFrame>) {
         Palette::Wheat.set();
 
         let title = "MINESWEEPER";
         DrawColors.set(0x02);
         draw_text(title, 1 + (160 - 8 * title.len() as i32) / 2, 4);
-        self.draw_menu_entry(0, "Start an easy game", mouse);
-        self.draw_menu_entry(1, "Start a medium game", mouse);
-        self.draw_menu_entry(2, "Start a hard game", mouse);
+        self.draw_menu_entry(0, "Start an easy game", input);
+        self.draw_menu_entry(1, "Start a medium game", input);
+        self.draw_menu_entry(2, "Start a hard game", input);
+        self.draw_menu_entry(3, "Start a custom game", input);
+        self.draw_menu_entry(4, "Play a seed", input);
+        self.draw_menu_entry(5, "Board of the day", input);
+        self.draw_menu_entry(6, "Puzzles", input);
+        self.draw_menu_entry(7, "Options", input);
 
-        self.draw_menu_entry(3, "Instructions", mouse);
+        self.draw_menu_entry(8, "Leaderboards", input);
+        self.draw_menu_entry(9, "Statistics", input);
+        self.draw_menu_entry(10, "Instructions", input);
 
         DrawColors.set(0x2);
-        draw_text("HIGH SCORES", 4, 90);
-        for (i, difficulty) in ([Difficulty::Easy, Difficulty::Medium, Difficulty::Hard])
-            .iter()
-            .enumerate()
-        {
-            let text = self
-                .highscores
-                .get(*difficulty)
-                .map(|time| {
-                    pad_text(difficulty.as_ref(), 7) + &format_number(time.into(), None) + " s"
+        draw_text("HIGH SCORES", 4, 123);
+        for (i, difficulty) in HIGHSCORE_DIFFICULTIES.iter().enumerate() {
+            let index = ENTRIES_COUNT + i;
+            let (x, y) = self.entry_to_coordinates(index);
+            let is_highlighted = self.is_highlighted(index, input);
+            if is_highlighted {
+                DrawColors.set(0x02);
+                draw_rect(x, y, WIDTH, HIGHSCORE_HEIGHT);
+            }
+
+            let highscore = self.highscores.get(*difficulty);
+            let text = highscore
+                .map(|highscore| {
+                    let seconds = (highscore.time / FRAMES_PER_SECOND).min(MAX_SECONDS);
+                    pad_text(difficulty.as_ref(), 7)
+                        + &format_number(seconds, Some(TIME_LENGTH))
+                        + " s"
                 })
                 .unwrap_or_else(|| pad_text(difficulty.as_ref(), 7) + "Unbeaten");
-            DrawColors.set(0x3);
-            draw_text(text, 4, 102 + 10 * i as i32);
+            DrawColors.set(if is_highlighted { 0x1 } else { 0x3 });
+            draw_text(text, 4, y + 1);
+
+            if let Some(hints) = highscore
+                .map(|highscore| highscore.hints)
+                .filter(|h| *h > 0)
+            {
+                let text = String::from("+") + &format_number(hints.into(), None) + "H";
+                DrawColors.set(if is_highlighted { 0x1 } else { 0x2 });
+                draw_text(&text, HINTS_X, y + 1);
+            }
         }
 
         #[cfg(feature = "debug")]
         {
-            let text = concat!("Version ", env!("CARGO_PKG_VERSION"));
+            let text = concat!("v", env!("CARGO_PKG_VERSION"));
             DrawColors.set(0x2);
-            draw_text(&text, 160 - 2 - 8 * text.len() as i32, 150);
+            draw_text(text, 160 - 4 - 8 * text.len() as i32, 123);
         }
     }
 
-    pub fn update(self, mouse: &Mouse) -> Transition {
-        if mouse.left_clicked() {
-            let (mouse_x, mouse_y) = Mouse.coordinates();
-            for (index, difficulty) in
-                (0..=2).zip([Difficulty::Easy, Difficulty::Medium, Difficulty::Hard])
-            {
-                if self.is_mouse_inside_entry(index, mouse_x, mouse_y) {
-                    return Transition::Replace(State::PreGame(PreGameState::new(difficulty)));
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if input.pressed(BUTTON_UP) {
+            let mut index = self.selected.unwrap_or(0);
+            loop {
+                index = (index + SELECTABLE_COUNT - 1) % SELECTABLE_COUNT;
+                if self.is_selectable(index) {
+                    break;
                 }
             }
+            self.selected = Some(index);
+        }
+        if input.pressed(BUTTON_DOWN) {
+            let mut index = self.selected.unwrap_or(SELECTABLE_COUNT - 1);
+            loop {
+                index = (index + 1) % SELECTABLE_COUNT;
+                if self.is_selectable(index) {
+                    break;
+                }
+            }
+            self.selected = Some(index);
+        }
+
+        if input.pressed(BUTTON_1) {
+            if let Some(index) = self.selected {
+                return self.activate_entry(index);
+            }
+        }
 
-            if self.is_mouse_inside_entry(3, mouse_x, mouse_y) {
-                return Transition::Push(
-                    State::MainMenu(self),
-                    State::Instructions(InstructionsState::new()),
-                );
+        if input.left_clicked() {
+            self.selected = None;
+            let (mouse_x, mouse_y) = input.coordinates();
+            if let Some(index) = (0..SELECTABLE_COUNT).find(|index| {
+                self.is_selectable(*index) && self.is_mouse_inside_entry(*index, mouse_x, mouse_y)
+            }) {
+                return self.activate_entry(index);
             }
         }
 
         Transition::Replace(State::MainMenu(self))
     }
 
+    fn activate_entry(self, index: usize) -> Transition {
+        match index {
+            0..=2 => {
+                let difficulty = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard][index];
+                let generator = Settings::load().generator(difficulty);
+                Transition::Replace(State::PreGame(PreGameState::new(difficulty, generator)))
+            }
+            3 => Transition::Replace(State::CustomGame(CustomGameState::new(self))),
+            4 => Transition::Replace(State::PlaySeed(PlaySeedState::new(self))),
+            5 => Transition::Replace(State::Daily(DailyState::new(self))),
+            6 => Transition::Replace(State::PuzzleSelect(PuzzleSelectState::new(self))),
+            7 => Transition::Replace(State::Options(OptionsState::new(self))),
+            8 => Transition::Replace(State::Leaderboard(LeaderboardState::new(
+                self,
+                Difficulty::Easy,
+            ))),
+            9 => Transition::Replace(State::Statistics(StatisticsState::new(self))),
+            10 => Transition::Push(
+                State::MainMenu(self),
+                State::Instructions(InstructionsState::new()),
+            ),
+            _ => {
+                let difficulty = HIGHSCORE_DIFFICULTIES[index - ENTRIES_COUNT];
+                match self.highscores.replay(difficulty).cloned() {
+                    Some(replay) => Transition::Push(
+                        State::MainMenu(self),
+                        State::Replay(ReplayState::new(replay)),
+                    ),
+                    None => Transition::Replace(State::MainMenu(self)),
+                }
+            }
+        }
+    }
+
+    /// Check whether an entry can be selected
+    ///
+    /// High scores can be selected only when the replay of the best game is
+    /// stored.
+    fn is_selectable(&self, index: usize) -> bool {
+        match index.checked_sub(ENTRIES_COUNT) {
+            None => true,
+            Some(i) => HIGHSCORE_DIFFICULTIES
+                .get(i)
+                .map(|difficulty| self.highscores.replay(*difficulty).is_some())
+                .unwrap_or(false),
+        }
+    }
+
     fn entry_to_coordinates(&self, index: usize) -> (i32, i32) {
-        (3, 15 + index as i32 * 15)
+        match index.checked_sub(ENTRIES_COUNT) {
+            None => (3, 12 + index as i32 * HEIGHT as i32),
+            Some(i) => (3, 132 + i as i32 * HIGHSCORE_HEIGHT as i32),
+        }
     }
 
     fn is_mouse_inside_entry(&self, index: usize, mouse_x: i16, mouse_y: i16) -> bool {
         let (x, y) = self.entry_to_coordinates(index);
+        let height = if index < ENTRIES_COUNT {
+            HEIGHT
+        } else {
+            HIGHSCORE_HEIGHT
+        };
         x <= mouse_x as i32
             && mouse_x as i32 <= x + WIDTH as i32
             && y <= mouse_y as i32
-            && mouse_y as i32 <= y + HEIGHT as i32
+            && mouse_y as i32 <= y + height as i32
     }
 
-    fn draw_menu_entry(&self, index: usize, text: &str, mouse: Option<Mouse>) {
-        let (x, y) = self.entry_to_coordinates(index);
-
-        let is_highlighted = mouse
-            .map(|mouse| {
-                let (mouse_x, mouse_y) = mouse.coordinates();
+    fn is_highlighted(&self, index: usize, input: Option<InputFrame>) -> bool {
+        let is_hovered = input
+            .map(|input| {
+                let (mouse_x, mouse_y) = input.coordinates();
                 self.is_mouse_inside_entry(index, mouse_x, mouse_y)
             })
             .unwrap_or(false);
+        self.is_selectable(index) && is_hovered || self.selected == Some(index)
+    }
+
+    fn draw_menu_entry(&self, index: usize, text: &str, input: Option<InputFrame>) {
+        let (x, y) = self.entry_to_coordinates(index);
+
+        let is_highlighted = self.is_highlighted(index, input);
 
         if is_highlighted {
             DrawColors.set(0x02);
@@ -120,6 +253,6 @@ impl MainMenuState {
         } else {
             DrawColors.set(0x03);
         }
-        draw_text(text, x + 1, y + 3);
+        draw_text(text, x + 1, y + 1);
     }
 }
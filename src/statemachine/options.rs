@@ -0,0 +1,266 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::game::{Difficulty, FirstClick, Settings};
+use crate::graphics::{draw_text, DrawColors, Palette};
+use crate::input::InputFrame;
+use crate::interface::{draw_entry, is_inside, ENTRY_HEIGHT, ENTRY_WIDTH, ENTRY_X};
+use crate::wasm4::{BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_UP};
+
+use super::{MainMenuState, State, Transition};
+
+/// Difficulties with their own no-guess option
+///
+/// Only the kind of difficulty matters, so the custom one has no size.
+const DIFFICULTIES: [Difficulty; 4] = [
+    Difficulty::Easy,
+    Difficulty::Medium,
+    Difficulty::Hard,
+    Difficulty::Custom {
+        width: 0,
+        height: 0,
+        mines: 0,
+    },
+];
+
+/// Available first click policies
+const FIRST_CLICKS: [FirstClick; 3] = [FirstClick::Safe, FirstClick::Opening, FirstClick::Classic];
+
+const NO_GUESS_TITLE_Y: i32 = 18;
+
+/// Vertical position of the no-guess entry of the first difficulty
+const NO_GUESS_Y: i32 = 28;
+
+/// Vertical distance between no-guess entries
+const NO_GUESS_STEP: i32 = 14;
+
+const PRACTICE_Y: i32 = 88;
+const FIRST_CLICK_Y: i32 = 102;
+const DESCRIPTION_Y: i32 = 122;
+const BACK_Y: i32 = 142;
+
+/// Number of rows selectable with the gamepad: no guessing for each
+/// difficulty, practice, first click policy and back
+const ROWS_COUNT: usize = 7;
+const NO_GUESS_ROW: usize = 0;
+const PRACTICE_ROW: usize = 4;
+const FIRST_CLICK_ROW: usize = 5;
+const BACK_ROW: usize = 6;
+
+/// A state choosing the mine layout generator of each difficulty, the
+/// practice mode and the first click policy
+///
+/// All options are stored in the settings.
+#[derive(Clone)]
+pub struct OptionsState {
+    main_menu: MainMenuState,
+    settings: Settings,
+    selected: Option<usize>,
+}
+
+impl OptionsState {
+    pub fn new(main_menu: MainMenuState) -> Self {
+        Self {
+            main_menu,
+            settings: Settings::load(),
+            selected: None,
+        }
+    }
+
+    pub fn draw(&self, input: Option<InputFrame>) {
+        Palette::Wheat.set();
+
+        let title = "OPTIONS";
+        DrawColors.set(0x02);
+        draw_text(title, 1 + (160 - 8 * title.len() as i32) / 2, 4);
+
+        DrawColors.set(0x02);
+        draw_text("No guessing", 4, NO_GUESS_TITLE_Y);
+        for (i, difficulty) in DIFFICULTIES.iter().enumerate() {
+            let state = if self.settings.is_no_guess(*difficulty) {
+                ": On"
+            } else {
+                ": Off"
+            };
+            draw_entry(
+                &(difficulty.as_ref().to_owned() + state),
+                no_guess_to_y(i),
+                input,
+                self.selected == Some(NO_GUESS_ROW + i),
+            );
+        }
+
+        let practice = if self.settings.practice {
+            "Practice: On"
+        } else {
+            "Practice: Off"
+        };
+        draw_entry(
+            practice,
+            PRACTICE_Y,
+            input,
+            self.selected == Some(PRACTICE_ROW),
+        );
+
+        draw_entry(
+            &("First tile: ".to_owned() + self.settings.first_click.as_ref()),
+            FIRST_CLICK_Y,
+            input,
+            self.selected == Some(FIRST_CLICK_ROW),
+        );
+
+        let description = match self.selected {
+            Some(row) if row < PRACTICE_ROW => "No guessing always\nstarts in a region",
+            _ => match self.settings.first_click {
+                FirstClick::Safe => "The first tile is\nnever a mine",
+                FirstClick::Opening => "The first tile has\nno adjacent mines",
+                FirstClick::Classic => "The first tile can\nbe a mine",
+            },
+        };
+        DrawColors.set(0x03);
+        draw_text(description, 4, DESCRIPTION_Y);
+
+        draw_entry("Back", BACK_Y, input, self.selected == Some(BACK_ROW));
+    }
+
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if input.pressed(BUTTON_UP) {
+            self.selected = Some(match self.selected {
+                Some(row) if row > 0 => row - 1,
+                _ => ROWS_COUNT - 1,
+            });
+        }
+        if input.pressed(BUTTON_DOWN) {
+            self.selected = Some(match self.selected {
+                Some(row) if row + 1 < ROWS_COUNT => row + 1,
+                _ => 0,
+            });
+        }
+        if input.pressed(BUTTON_2) {
+            return self.back();
+        }
+        if input.pressed(BUTTON_1) {
+            if let Some(row) = self.selected {
+                return self.activate_row(row);
+            }
+        }
+
+        if input.left_clicked() {
+            self.selected = None;
+            let (mouse_x, mouse_y) = input.coordinates();
+            if let Some(row) = (0..ROWS_COUNT).find(|row| {
+                is_inside(
+                    ENTRY_X,
+                    row_to_y(*row),
+                    ENTRY_WIDTH,
+                    ENTRY_HEIGHT,
+                    mouse_x,
+                    mouse_y,
+                )
+            }) {
+                return self.activate_row(row);
+            }
+        }
+
+        Transition::Replace(State::Options(self))
+    }
+
+    fn activate_row(mut self, row: usize) -> Transition {
+        match row {
+            PRACTICE_ROW => self.settings.practice = !self.settings.practice,
+            FIRST_CLICK_ROW => {
+                let index = FIRST_CLICKS
+                    .iter()
+                    .position(|first_click| *first_click == self.settings.first_click)
+                    .unwrap_or(0);
+                self.settings.first_click = FIRST_CLICKS[(index + 1) % FIRST_CLICKS.len()];
+            }
+            BACK_ROW => return self.back(),
+            _ => {
+                let difficulty = DIFFICULTIES[row - NO_GUESS_ROW];
+                let no_guess = self.settings.is_no_guess(difficulty);
+                self.settings.set_no_guess(difficulty, !no_guess);
+            }
+        }
+        self.settings.save();
+        Transition::Replace(State::Options(self))
+    }
+
+    fn back(self) -> Transition {
+        Transition::Replace(State::MainMenu(self.main_menu))
+    }
+}
+
+fn no_guess_to_y(index: usize) -> i32 {
+    NO_GUESS_Y + NO_GUESS_STEP * index as i32
+}
+
+fn row_to_y(row: usize) -> i32 {
+    match row {
+        PRACTICE_ROW => PRACTICE_Y,
+        FIRST_CLICK_ROW => FIRST_CLICK_Y,
+        BACK_ROW => BACK_Y,
+        _ => no_guess_to_y(row - NO_GUESS_ROW),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::Generator;
+
+    fn press(button: u8) -> InputFrame {
+        InputFrame {
+            gamepad_buttons: button,
+            ..InputFrame::default()
+        }
+    }
+
+    fn update(state: OptionsState, input: &InputFrame) -> OptionsState {
+        match state.update(input) {
+            Transition::Replace(State::Options(state)) => state,
+            _ => panic!("Unexpected transition"),
+        }
+    }
+
+    #[test]
+    fn gamepad_changes_options() {
+        let _harness = crate::harness::Harness::new();
+        let mut state = OptionsState::new(MainMenuState::new());
+        assert_eq!(state.settings.first_click, FirstClick::Safe);
+
+        state = update(state, &press(BUTTON_DOWN));
+        state = update(state, &press(BUTTON_DOWN));
+        state = update(state, &press(BUTTON_1));
+        assert!(state.settings.is_no_guess(Difficulty::Medium));
+        assert!(!state.settings.is_no_guess(Difficulty::Hard));
+
+        for _ in 0..3 {
+            state = update(state, &press(BUTTON_DOWN));
+        }
+        state = update(state, &press(BUTTON_1));
+        assert!(state.settings.practice);
+
+        state = update(state, &press(BUTTON_DOWN));
+        state = update(state, &press(BUTTON_1));
+        assert_eq!(state.settings.first_click, FirstClick::Opening);
+        let settings = Settings::load();
+        assert_eq!(settings.first_click, FirstClick::Opening);
+        assert!(settings.practice);
+        assert_eq!(settings.generator(Difficulty::Easy), Generator::Random);
+        assert_eq!(settings.generator(Difficulty::Medium), Generator::NoGuess);
+
+        state = update(state, &press(BUTTON_1));
+        state = update(state, &press(BUTTON_1));
+        assert_eq!(state.settings.first_click, FirstClick::Safe);
+
+        match state.update(&press(BUTTON_2)) {
+            Transition::Replace(State::MainMenu(_)) => {}
+            _ => panic!("Unexpected transition"),
+        }
+    }
+}
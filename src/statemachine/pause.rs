@@ -4,8 +4,9 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use crate::input::Mouse;
+use crate::input::InputFrame;
 use crate::interface::draw_message_box;
+use crate::wasm4::BUTTON_1;
 
 use super::{State, Transition};
 
@@ -18,15 +19,15 @@ impl PauseState {
         Self {}
     }
 
-    pub fn draw(&self, _mouse: Option<Mouse>) {
+    pub fn draw(&self, _input: Option<InputFrame>) {
         let x = 16;
         let y = 30;
 
-        draw_message_box("Game paused\n\nClick to resume", x, y);
+        draw_message_box("Game paused\n\nClick or press\nX to resume", x, y);
     }
 
-    pub fn update(self, mouse: &Mouse) -> Transition {
-        if mouse.left_clicked() {
+    pub fn update(self, input: &InputFrame) -> Transition {
+        if input.left_clicked() || input.pressed(BUTTON_1) {
             Transition::Pop
         } else {
             Transition::Replace(State::Pause(self))
@@ -0,0 +1,305 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::game::{Difficulty, Generator, Seed, CODE_ALPHABET, CODE_LENGTH};
+use crate::graphics::{draw_rect, draw_text, DrawColors, Palette};
+use crate::input::InputFrame;
+use crate::interface::{
+    draw_button, draw_entry, is_inside, BUTTON_SIZE, ENTRY_HEIGHT, ENTRY_WIDTH, ENTRY_X,
+};
+use crate::wasm4::{BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP};
+
+use super::{MainMenuState, PreGameState, State, Transition};
+
+/// Difficulties available for seeded games
+const DIFFICULTIES: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+/// Mine layout generator of seeded games
+///
+/// The generator does not depend on the settings, so that a code always
+/// gives the same board.
+const GENERATOR: Generator = Generator::Random;
+
+const DIFFICULTY_Y: i32 = 25;
+const DECREASE_X: i32 = 8;
+const INCREASE_X: i32 = 140;
+
+/// Horizontal position of the first character of the code
+const CODE_X: i32 = 20;
+
+/// Horizontal distance between characters of the code
+const CODE_STEP: i32 = 20;
+
+const CODE_UP_Y: i32 = 50;
+const CODE_Y: i32 = 66;
+const CODE_DOWN_Y: i32 = 80;
+
+const START_Y: i32 = 113;
+const BACK_Y: i32 = 130;
+
+/// Number of rows selectable with the gamepad: difficulty, code, start and
+/// back
+const ROWS_COUNT: usize = 4;
+const DIFFICULTY_ROW: usize = 0;
+const CODE_ROW: usize = 1;
+const START_ROW: usize = 2;
+const BACK_ROW: usize = 3;
+
+#[derive(Clone)]
+pub struct PlaySeedState {
+    main_menu: MainMenuState,
+    difficulty: usize,
+    code: [usize; CODE_LENGTH],
+    position: usize,
+    selected: Option<usize>,
+}
+
+impl PlaySeedState {
+    pub fn new(main_menu: MainMenuState) -> Self {
+        Self {
+            main_menu,
+            difficulty: 0,
+            code: [0; CODE_LENGTH],
+            position: 0,
+            selected: None,
+        }
+    }
+
+    pub fn draw(&self, input: Option<InputFrame>) {
+        Palette::Wheat.set();
+
+        let title = "PLAY SEED";
+        DrawColors.set(0x02);
+        draw_text(title, 1 + (160 - 8 * title.len() as i32) / 2, 4);
+
+        let difficulty = DIFFICULTIES[self.difficulty].as_ref();
+        if self.selected == Some(DIFFICULTY_ROW) {
+            DrawColors.set(0x02);
+            draw_text(">", 0, DIFFICULTY_Y + 2);
+        }
+        DrawColors.set(0x03);
+        draw_text(
+            difficulty,
+            1 + (160 - 8 * difficulty.len() as i32) / 2,
+            DIFFICULTY_Y + 2,
+        );
+        draw_button("<", DECREASE_X, DIFFICULTY_Y, input);
+        draw_button(">", INCREASE_X, DIFFICULTY_Y, input);
+
+        for (i, digit) in self.code.iter().enumerate() {
+            let x = code_to_x(i);
+            draw_button("+", x, CODE_UP_Y, input);
+            draw_button("-", x, CODE_DOWN_Y, input);
+
+            let character = [CODE_ALPHABET[*digit]];
+            let character = core::str::from_utf8(&character).unwrap_or("?");
+            if self.selected == Some(CODE_ROW) && self.position == i {
+                DrawColors.set(0x02);
+                draw_rect(x, CODE_Y - 2, BUTTON_SIZE, BUTTON_SIZE);
+                DrawColors.set(0x01);
+            } else {
+                DrawColors.set(0x03);
+            }
+            draw_text(character, x + 2, CODE_Y);
+        }
+
+        let text = "Starts at centre";
+        DrawColors.set(0x02);
+        draw_text(text, 1 + (160 - 8 * text.len() as i32) / 2, 98);
+
+        draw_entry("Start", START_Y, input, self.selected == Some(START_ROW));
+        draw_entry("Back", BACK_Y, input, self.selected == Some(BACK_ROW));
+    }
+
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if input.pressed(BUTTON_UP) {
+            self.selected = Some(match self.selected {
+                Some(row) if row > 0 => row - 1,
+                _ => ROWS_COUNT - 1,
+            });
+        }
+        if input.pressed(BUTTON_DOWN) {
+            self.selected = Some(match self.selected {
+                Some(row) if row + 1 < ROWS_COUNT => row + 1,
+                _ => 0,
+            });
+        }
+        match self.selected {
+            Some(DIFFICULTY_ROW) => {
+                if input.pressed(BUTTON_LEFT) {
+                    self.change_difficulty(-1);
+                }
+                if input.pressed(BUTTON_RIGHT) {
+                    self.change_difficulty(1);
+                }
+            }
+            Some(CODE_ROW) => {
+                if input.pressed(BUTTON_LEFT) {
+                    self.position = (self.position + CODE_LENGTH - 1) % CODE_LENGTH;
+                }
+                if input.pressed(BUTTON_RIGHT) {
+                    self.position = (self.position + 1) % CODE_LENGTH;
+                }
+                if input.pressed(BUTTON_1) {
+                    self.change_character(self.position, 1);
+                }
+                if input.pressed(BUTTON_2) {
+                    self.change_character(self.position, -1);
+                }
+            }
+            Some(START_ROW) if input.pressed(BUTTON_1) => return self.start(),
+            Some(BACK_ROW) if input.pressed(BUTTON_1) => {
+                return Transition::Replace(State::MainMenu(self.main_menu));
+            }
+            _ => {}
+        }
+
+        if input.left_clicked() {
+            self.selected = None;
+            let (mouse_x, mouse_y) = input.coordinates();
+
+            let is_inside_button =
+                |x, y| is_inside(x, y, BUTTON_SIZE, BUTTON_SIZE, mouse_x, mouse_y);
+            if is_inside_button(DECREASE_X, DIFFICULTY_Y) {
+                self.change_difficulty(-1);
+            }
+            if is_inside_button(INCREASE_X, DIFFICULTY_Y) {
+                self.change_difficulty(1);
+            }
+            for i in 0..CODE_LENGTH {
+                if is_inside_button(code_to_x(i), CODE_UP_Y) {
+                    self.change_character(i, 1);
+                }
+                if is_inside_button(code_to_x(i), CODE_DOWN_Y) {
+                    self.change_character(i, -1);
+                }
+            }
+
+            if is_inside(
+                ENTRY_X,
+                START_Y,
+                ENTRY_WIDTH,
+                ENTRY_HEIGHT,
+                mouse_x,
+                mouse_y,
+            ) {
+                return self.start();
+            }
+            if is_inside(ENTRY_X, BACK_Y, ENTRY_WIDTH, ENTRY_HEIGHT, mouse_x, mouse_y) {
+                return Transition::Replace(State::MainMenu(self.main_menu));
+            }
+        }
+
+        Transition::Replace(State::PlaySeed(self))
+    }
+
+    fn start(self) -> Transition {
+        Transition::Replace(State::PreGame(
+            PreGameState::new(DIFFICULTIES[self.difficulty], GENERATOR).with_seed(self.seed()),
+        ))
+    }
+
+    /// Return the seed corresponding to the current code
+    fn seed(&self) -> Seed {
+        let code: String = self
+            .code
+            .iter()
+            .map(|digit| CODE_ALPHABET[*digit] as char)
+            .collect();
+        Seed::from_code(&code).expect("Code must be valid")
+    }
+
+    fn change_difficulty(&mut self, step: isize) {
+        self.difficulty = wrap(self.difficulty, step, DIFFICULTIES.len());
+    }
+
+    fn change_character(&mut self, position: usize, step: isize) {
+        self.code[position] = wrap(self.code[position], step, CODE_ALPHABET.len());
+    }
+}
+
+/// Change a value by a step, wrapping around at the bounds
+fn wrap(value: usize, step: isize, count: usize) -> usize {
+    (value as isize + step).rem_euclid(count as isize) as usize
+}
+
+fn code_to_x(position: usize) -> i32 {
+    CODE_X + CODE_STEP * position as i32 + (CODE_STEP - BUTTON_SIZE as i32) / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{FirstClick, Settings};
+    use crate::wasm4::MOUSE_LEFT;
+
+    fn press(button: u8) -> InputFrame {
+        InputFrame {
+            gamepad_buttons: button,
+            ..InputFrame::default()
+        }
+    }
+
+    fn update(state: PlaySeedState, input: &InputFrame) -> PlaySeedState {
+        match state.update(input) {
+            Transition::Replace(State::PlaySeed(state)) => state,
+            _ => panic!("Unexpected transition"),
+        }
+    }
+
+    #[test]
+    fn gamepad_enters_code() {
+        // Lock the console, since the main menu loads the high scores
+        let _harness = crate::harness::Harness::new();
+        let mut state = PlaySeedState::new(MainMenuState::new());
+
+        state = update(state, &press(BUTTON_DOWN));
+        state = update(state, &press(BUTTON_DOWN));
+        state = update(state, &press(BUTTON_1));
+        state = update(state, &press(BUTTON_RIGHT));
+        state = update(state, &press(BUTTON_2));
+        state = update(state, &press(BUTTON_2));
+        state = update(state, &press(BUTTON_LEFT));
+        state = update(state, &press(BUTTON_LEFT));
+        state = update(state, &press(BUTTON_1));
+        assert_eq!(state.seed(), Seed::from_code("B8AAAB").unwrap());
+    }
+
+    /// Start the game of a code and return its mines
+    fn play(settings: Settings, click: (i16, i16)) -> Vec<(usize, usize)> {
+        settings.save();
+        let mut state = PlaySeedState::new(MainMenuState::new());
+        state.change_character(0, 1);
+        let state = match state.start() {
+            Transition::Replace(State::PreGame(state)) => state,
+            _ => panic!("Unexpected transition"),
+        };
+        let input = InputFrame {
+            pointer: click,
+            previous_mouse_buttons: MOUSE_LEFT,
+            ..InputFrame::default()
+        };
+        match state.update(&input) {
+            Transition::Replace(State::InGame(state)) => state.map().mines(),
+            _ => panic!("Unexpected transition"),
+        }
+    }
+
+    #[test]
+    fn code_gives_same_mines() {
+        let _harness = crate::harness::Harness::new();
+        let mines = play(Settings::default(), (20, 40));
+        let other_mines = play(
+            Settings {
+                first_click: FirstClick::Classic,
+                no_guess: [true; 4],
+                ..Settings::default()
+            },
+            (140, 130),
+        );
+        assert_eq!(mines, other_mines);
+    }
+}
@@ -4,84 +4,297 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use rand_core::RngCore;
+use rand_xorshift::XorShiftRng;
 
-use crate::game::{Difficulty, Map};
+use crate::debug;
+use crate::game::{
+    Cursor, Date, Difficulty, FirstClick, Generator, LayoutSearch, Map, Replay, Seed, Settings,
+};
 use crate::graphics::Palette;
-use crate::input::Mouse;
-use crate::interface::{draw_elapsed_time, draw_remaining_mines_count};
-use crate::random::RNG;
+use crate::input::{Drag, InputFrame};
+use crate::interface::{
+    draw_elapsed_time, draw_message_box, draw_remaining_mines_count, draw_zoom_buttons,
+    is_inside_elapsed_time, is_inside_hud_button, ZOOM_IN_BUTTON_X, ZOOM_OUT_BUTTON_X,
+};
+use crate::wasm4::BUTTON_1;
 
-use super::{InGameState, State, Transition};
+use super::{InGameState, MainMenuState, State, Transition};
+
+/// Initial size of tiles in pixels
+pub const TILE_SIZE: u32 = 10;
+
+/// Screen area below the HUD where the map is drawn
+pub const MAP_AREA: (i32, i32, u32, u32) = (0, 20, 160, 140);
+
+/// Number of steps of the search of the mine layout after which the player
+/// is told that the mines are being placed
+const SEARCH_MESSAGE_STEPS: usize = 15;
 
 #[derive(Clone)]
 pub struct PreGameState {
     map: Map,
     difficulty: Difficulty,
+    generator: Generator,
+    first_click: FirstClick,
+    seed: Option<Seed>,
+    first_tile: Option<(usize, usize)>,
+    daily: Option<Date>,
+    search: Option<Box<(Replay, LayoutSearch<XorShiftRng>)>>,
+    drag: Drag,
+    cursor: Cursor,
+    settings: Settings,
+    failed: bool,
 }
 
 impl PreGameState {
-    pub fn new(difficulty: Difficulty) -> Self {
-        let width = 16;
-        let height = 14;
-        let offset = (0, 20);
-        let tile_size = 10;
+    pub fn new(difficulty: Difficulty, generator: Generator) -> Self {
+        let width = difficulty.width();
+        let height = difficulty.height();
+        let map = Map::new(width, height, TILE_SIZE, (0, 0)).with_area(MAP_AREA);
+        let settings = Settings::load();
         Self {
-            map: Map::new(width, height, tile_size, offset),
+            cursor: Cursor::new(&map),
+            map,
             difficulty,
+            generator,
+            first_click: settings.first_click,
+            seed: None,
+            first_tile: None,
+            daily: None,
+            search: None,
+            drag: Drag::new(),
+            settings,
+            failed: false,
         }
     }
 
-    pub fn draw(&self, _mouse: Option<Mouse>) {
+    /// Use a given seed instead of a random one
+    ///
+    /// The game starts right away by uncovering the tile at the centre of
+    /// the map, and the default first click policy is used regardless of the
+    /// settings, so that all players get the same board.
+    pub fn with_seed(mut self, seed: Seed) -> Self {
+        self.seed = Some(seed);
+        self.first_tile = Some((self.map.width() / 2, self.map.height() / 2));
+        self.first_click = FirstClick::default();
+        self
+    }
+
+    /// Play the board of a day
+    ///
+    /// The board is generated from the date seed, as for any seeded game.
+    pub fn with_daily(self, date: Date) -> Self {
+        let mut state = self.with_seed(date.seed());
+        state.daily = Some(date);
+        state
+    }
+
+    pub fn draw(&self, input: Option<InputFrame>) {
         Palette::Hollow.set();
 
-        self.map.draw(&[]);
+        self.map.draw();
+        self.cursor.draw(&self.map);
 
         let remaining_mines = self.difficulty.mines_count();
-        draw_remaining_mines_count(remaining_mines, 160 - 64, 2);
+        draw_remaining_mines_count(remaining_mines, 160 - 36, 2);
 
-        let elapsed_time = 0;
-        draw_elapsed_time(elapsed_time, 2, 2);
+        draw_elapsed_time(0, self.settings.show_tenths, 2, 2);
+
+        draw_zoom_buttons(input);
+
+        let is_searching = self.search.as_ref().map_or(false, |search| {
+            let (_, search) = &**search;
+            search.steps() >= SEARCH_MESSAGE_STEPS
+        });
+        if is_searching {
+            draw_message_box("PLACING MINES...", 12, 60);
+        }
+
+        if self.failed {
+            let text = if self.seed.is_some() {
+                "NO LAYOUT FOUND\n\nClick to go back"
+            } else {
+                "NO LAYOUT FOUND\n\nClick to retry"
+            };
+            draw_message_box(text, 12, 60);
+        }
     }
 
-    pub fn update(mut self, mouse: &Mouse) -> Transition {
-        if mouse.left_clicked() {
-            let (mouse_x, mouse_y) = mouse.coordinates();
-            if let Some((tx, ty)) = self.map.mouse_to_tile(mouse_x, mouse_y) {
-                let mines = self.place_mines_randomly(self.map.width(), self.map.height(), tx, ty);
-
-                self.map.uncover_tile(tx, ty, &mines);
-                return Transition::Replace(State::InGame(InGameState::new(
-                    self.difficulty,
-                    self.map,
-                    mines,
-                )));
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if self.failed {
+            if input.left_clicked() || input.pressed(BUTTON_1) {
+                if self.seed.is_some() {
+                    // The same seed would fail again
+                    return Transition::Replace(State::MainMenu(MainMenuState::new()));
+                }
+                self.failed = false;
+            }
+            return Transition::Replace(State::PreGame(self));
+        }
+
+        if self.search.is_some() {
+            return self.continue_search();
+        }
+
+        if let Some(position) = self.first_tile {
+            return self.start(position);
+        }
+
+        let (dx, dy) = self.drag.update(input);
+        self.map.pan(dx, dy);
+
+        self.cursor.update(input, &mut self.map);
+        if input.pressed(BUTTON_1) {
+            let position = self.cursor.position();
+            return self.start(position);
+        }
+
+        if input.left_clicked() && !self.drag.is_dragging() {
+            self.cursor.hide();
+            let (mouse_x, mouse_y) = input.coordinates();
+            if is_inside_hud_button(ZOOM_OUT_BUTTON_X, mouse_x, mouse_y) {
+                self.map.zoom_out();
+            } else if is_inside_hud_button(ZOOM_IN_BUTTON_X, mouse_x, mouse_y) {
+                self.map.zoom_in();
+            } else if is_inside_elapsed_time(mouse_x, mouse_y) {
+                self.settings.show_tenths = !self.settings.show_tenths;
+                self.settings.save();
+            } else if let Some(position) = self.map.mouse_to_tile(mouse_x, mouse_y) {
+                return self.start(position);
             }
         }
 
         Transition::Replace(State::PreGame(self))
     }
 
-    fn place_mines_randomly(
-        &self,
-        width: usize,
-        height: usize,
-        forbidden_x: usize,
-        forbidden_y: usize,
-    ) -> Vec<(usize, usize)> {
-        let mut mines = Vec::new();
-        let generator = unsafe { &mut RNG };
-        for i in 0..self.difficulty.mines_count() {
-            let mut x = generator.next_u32() as usize % width;
-            let mut y = generator.next_u32() as usize % height;
-            while mines[0..i].iter().any(|pos| *pos == (x, y))
-                || x == forbidden_x && y == forbidden_y
-            {
-                x = generator.next_u32() as usize % width;
-                y = generator.next_u32() as usize % height;
+    /// Start placing the mines for a game starting from a tile
+    fn start(mut self, position: (usize, usize)) -> Transition {
+        let seed = self.seed.unwrap_or_else(Seed::random);
+        debug!("Starting game with seed {}", seed.code());
+
+        let replay = Replay::new(self.difficulty, self.generator, seed, position)
+            .with_first_click(self.first_click);
+        let search = replay.search();
+        self.search = Some(Box::new((replay, search)));
+        self.continue_search()
+    }
+
+    /// Search the mine layout for a step, and start the game when it is
+    /// found
+    ///
+    /// No-guess layouts can take long to find, so a single step is made at
+    /// each frame.
+    /// The player is told when the mines cannot be placed.
+    fn continue_search(mut self) -> Transition {
+        let mut searching = match self.search.take() {
+            Some(searching) => searching,
+            None => return Transition::Replace(State::PreGame(self)),
+        };
+        if !searching.1.step() {
+            self.search = Some(searching);
+            return Transition::Replace(State::PreGame(self));
+        }
+        let (replay, search) = *searching;
+
+        let mines = match search.into_mines() {
+            Some(mines) => mines,
+            None => {
+                debug!(
+                    "Could not place the mines with seed {}",
+                    replay.seed().code()
+                );
+                self.failed = true;
+                self.first_tile = None;
+                return Transition::Replace(State::PreGame(self));
+            }
+        };
+        let (tx, ty) = replay.start();
+        let mut map = self.map.with_mines(&mines);
+        map.uncover_tile(tx, ty);
+        Transition::Replace(State::InGame(InGameState::new(
+            map,
+            self.cursor,
+            replay,
+            self.daily,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(button: u8) -> InputFrame {
+        InputFrame {
+            gamepad_buttons: button,
+            ..InputFrame::default()
+        }
+    }
+
+    fn update(state: PreGameState, input: &InputFrame) -> PreGameState {
+        match state.update(input) {
+            Transition::Replace(State::PreGame(state)) => state,
+            _ => panic!("Unexpected transition"),
+        }
+    }
+
+    /// A difficulty too dense for no-guess layouts
+    const DENSE: Difficulty = Difficulty::Custom {
+        width: 16,
+        height: 14,
+        mines: 90,
+    };
+
+    #[test]
+    fn failure_is_reported() {
+        let _harness = crate::harness::Harness::new();
+        let mut state = PreGameState::new(DENSE, Generator::NoGuess);
+
+        state = update(state, &press(BUTTON_1));
+        assert!(state.failed);
+
+        // Another seed is tried at the next click
+        state = update(state, &press(BUTTON_1));
+        assert!(!state.failed);
+    }
+
+    #[test]
+    fn no_guess_layout_is_searched_over_frames() {
+        let _harness = crate::harness::Harness::new();
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        let replay = Replay::new(Difficulty::Hard, Generator::NoGuess, seed, (8, 7));
+        let mut state = PreGameState::new(Difficulty::Hard, Generator::NoGuess).with_seed(seed);
+
+        let mut frames = 0;
+        let game = loop {
+            frames += 1;
+            match state.update(&InputFrame::default()) {
+                Transition::Replace(State::PreGame(next)) => state = next,
+                Transition::Replace(State::InGame(game)) => break game,
+                _ => panic!("Unexpected transition"),
             }
-            mines.push((x, y));
+        };
+        assert!(frames > 1);
+
+        let mut mines = game.map().mines();
+        let mut expected = replay.mines().unwrap();
+        mines.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(mines, expected);
+    }
+
+    #[test]
+    fn failure_with_seed_goes_back() {
+        let _harness = crate::harness::Harness::new();
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        let mut state = PreGameState::new(DENSE, Generator::NoGuess).with_seed(seed);
+
+        state = update(state, &press(BUTTON_1));
+        assert!(state.failed);
+
+        match state.update(&press(BUTTON_1)) {
+            Transition::Replace(State::MainMenu(_)) => {}
+            _ => panic!("Unexpected transition"),
         }
-        mines
     }
 }
@@ -0,0 +1,295 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::game::{deduce, Cursor, Map, PuzzleProgress, PUZZLES};
+use crate::graphics::{draw_text, DrawColors, Palette, Tile};
+use crate::input::InputFrame;
+use crate::interface::{draw_message_box, draw_remaining_mines_count};
+use crate::sound::play_game_over_sound;
+use crate::wasm4::{BUTTON_1, BUTTON_2};
+
+use super::pregame::{MAP_AREA, TILE_SIZE};
+use super::{MainMenuState, PuzzleSelectState, State, Transition};
+
+const MAX_CLICK_AGE: u8 = 10;
+
+/// The outcome of a finished puzzle
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Outcome {
+    /// All safe tiles were uncovered without guessing
+    Solved,
+
+    /// A mine was uncovered
+    Exploded,
+
+    /// A tile was uncovered although it could not be deduced to be safe
+    Guessed,
+}
+
+/// A state playing a puzzle
+///
+/// Puzzles must be solved without guessing: uncovering a tile that cannot be
+/// deduced to be safe fails the puzzle, even if the tile has no mine.
+#[derive(Clone)]
+pub struct PuzzleState {
+    index: usize,
+    map: Map,
+    cursor: Cursor,
+    left_click_age: u8,
+    right_click_age: u8,
+    outcome: Option<Outcome>,
+}
+
+impl PuzzleState {
+    /// Create a new state playing the puzzle at an index
+    ///
+    /// Return `None` if there is no such puzzle.
+    pub fn new(index: usize) -> Option<Self> {
+        let map = PUZZLES
+            .get(index)?
+            .map(TILE_SIZE, (0, 0))?
+            .with_area(MAP_AREA);
+        Some(Self {
+            index,
+            cursor: Cursor::new(&map),
+            map,
+            left_click_age: 0,
+            right_click_age: 0,
+            outcome: None,
+        })
+    }
+
+    pub fn draw(&self, _input: Option<InputFrame>) {
+        match self.outcome {
+            None | Some(Outcome::Solved) => Palette::Hollow.set(),
+            Some(_) => Palette::Rustic.set(),
+        }
+
+        self.map.draw();
+        self.cursor.draw(&self.map);
+
+        DrawColors.set(0x03);
+        draw_text(PUZZLES[self.index].name, 4, 4);
+        draw_remaining_mines_count(self.map.count_remaining_mines(), 160 - 36, 2);
+
+        let text = match self.outcome {
+            None => return,
+            Some(Outcome::Solved) => "PUZZLE SOLVED!!!",
+            Some(Outcome::Exploded) => "BOOM!!!",
+            Some(Outcome::Guessed) => "NO GUESSING!!!\n\nThat tile could\nnot be deduced",
+        };
+        draw_message_box(text, 16, 30);
+    }
+
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if self.outcome.is_some() {
+            if input.left_clicked() || input.pressed(BUTTON_1) {
+                return Transition::Replace(State::PuzzleSelect(PuzzleSelectState::new(
+                    MainMenuState::new(),
+                )));
+            }
+            return Transition::Replace(State::Puzzle(self));
+        }
+
+        self.cursor.update(input, &mut self.map);
+        self.handle_gamepad_buttons(input);
+
+        let left_clicked = input.left_clicked();
+        if left_clicked {
+            self.left_click_age = MAX_CLICK_AGE;
+        }
+        if input.right_clicked() {
+            self.right_click_age = MAX_CLICK_AGE;
+        }
+        if left_clicked || input.right_clicked() {
+            self.cursor.hide();
+        }
+
+        let (mouse_x, mouse_y) = input.coordinates();
+        if self.left_click_age > 0 && self.right_click_age > 0 {
+            if let Some((x, y)) = self.map.mouse_to_tile(mouse_x, mouse_y) {
+                self.chord(x, y);
+            }
+            // Chord only once per pair of clicks
+            self.left_click_age = 0;
+            self.right_click_age = 0;
+        } else if left_clicked {
+            if let Some((x, y)) = self.map.mouse_to_tile(mouse_x, mouse_y) {
+                self.uncover(x, y);
+            }
+        } else if input.right_clicked() {
+            if let Some((x, y)) = self.map.mouse_to_tile(mouse_x, mouse_y) {
+                self.map.flip_flagged_tile(x, y);
+            }
+        }
+
+        self.left_click_age = self.left_click_age.saturating_sub(1);
+        self.right_click_age = self.right_click_age.saturating_sub(1);
+
+        Transition::Replace(State::Puzzle(self))
+    }
+
+    /// Handle gamepad buttons on the tile under the cursor
+    ///
+    /// Button 1 uncovers the tile, button 2 flags it, and pressing both
+    /// uncovers its neighbours.
+    fn handle_gamepad_buttons(&mut self, input: &InputFrame) {
+        let (x, y) = self.cursor.position();
+        let pressed_1 = input.pressed(BUTTON_1);
+        let pressed_2 = input.pressed(BUTTON_2);
+        if pressed_1 && input.held(BUTTON_2) || pressed_2 && input.held(BUTTON_1) {
+            self.chord(x, y);
+        } else if pressed_1 {
+            self.uncover(x, y);
+        } else if pressed_2 {
+            self.map.flip_flagged_tile(x, y);
+        }
+    }
+
+    /// Uncover a tile, which must be provably safe
+    fn uncover(&mut self, x: usize, y: usize) {
+        self.play(vec![(x, y)]);
+    }
+
+    /// Uncover the neighbours of a tile, which must be provably safe
+    ///
+    /// As in games, the neighbours are only uncovered if as many of them are
+    /// flagged as there are adjacent mines.
+    fn chord(&mut self, x: usize, y: usize) {
+        let is_uncovered = matches!(self.map.tile(x, y), Tile::Uncovered);
+        if is_uncovered
+            && self.map.count_neighbour_flags(x, y) == self.map.count_neighbour_mines(x, y)
+        {
+            let neighbours = self.map.neighbours(x, y);
+            self.play(neighbours);
+        }
+    }
+
+    /// Uncover tiles and check whether the move finished the puzzle
+    ///
+    /// The move fails the puzzle if it uncovers any of the given tiles
+    /// without them being deduced to be safe beforehand.
+    /// Tiles are uncovered regardless of flags, so the only other tiles
+    /// uncovered by the move are in openings around tiles without adjacent
+    /// mines, which are safe.
+    fn play(&mut self, tiles: Vec<(usize, usize)>) {
+        let safe = deduce(&self.map).safe;
+        let mut guessed = false;
+        for (x, y) in tiles {
+            if matches!(self.map.tile(x, y), Tile::Covered) {
+                guessed |= !safe.contains(&(x, y));
+                self.map.uncover_tile_ignoring_flags(x, y);
+            }
+        }
+
+        self.outcome = if self.map.has_stepped_on_mine() {
+            Some(Outcome::Exploded)
+        } else if guessed {
+            Some(Outcome::Guessed)
+        } else if self.map.is_cleared() {
+            Some(Outcome::Solved)
+        } else {
+            None
+        };
+
+        match self.outcome {
+            None => {}
+            Some(Outcome::Solved) => {
+                self.map.flag_mines();
+                let mut progress = PuzzleProgress::load();
+                progress.mark_solved(self.index);
+                progress.save();
+            }
+            Some(_) => {
+                self.map.flag_mines();
+                play_game_over_sound();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(button: u8) -> InputFrame {
+        InputFrame {
+            gamepad_buttons: button,
+            ..InputFrame::default()
+        }
+    }
+
+    fn update(state: PuzzleState, input: &InputFrame) -> PuzzleState {
+        match state.update(input) {
+            Transition::Replace(State::Puzzle(state)) => state,
+            _ => panic!("Unexpected transition"),
+        }
+    }
+
+    #[test]
+    fn deduced_tiles_solve_the_puzzle() {
+        let _harness = crate::harness::Harness::new();
+        let mut state = PuzzleState::new(0).unwrap();
+        while state.outcome.is_none() {
+            let safe = deduce(&state.map).safe;
+            let (x, y) = safe[0];
+            state.uncover(x, y);
+        }
+        assert_eq!(state.outcome, Some(Outcome::Solved));
+        assert!(PuzzleProgress::load().is_solved(0));
+        assert!(!PuzzleProgress::load().is_solved(1));
+
+        match state.update(&press(BUTTON_1)) {
+            Transition::Replace(State::PuzzleSelect(_)) => {}
+            _ => panic!("Unexpected transition"),
+        }
+    }
+
+    #[test]
+    fn wrong_flags_do_not_uncover_mines() {
+        let _harness = crate::harness::Harness::new();
+        let mut state = PuzzleState::new(0).unwrap();
+        state.map = Map::from_ascii(
+            "
+            0 0 1 . f
+            0 0 1 * .
+            0 0 1 1 1
+            ",
+            TILE_SIZE,
+            (0, 0),
+        )
+        .unwrap();
+
+        state.uncover(3, 0);
+        assert_eq!(state.outcome, None);
+        assert!(!state.map.has_stepped_on_mine());
+    }
+
+    #[test]
+    fn guessing_fails_the_puzzle() {
+        let _harness = crate::harness::Harness::new();
+        let mut state = PuzzleState::new(0).unwrap();
+        let map = &state.map;
+        let deductions = deduce(map);
+        let (x, y) = (0..map.height())
+            .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+            .find(|(x, y)| {
+                !map.is_mine(*x, *y)
+                    && matches!(map.tile(*x, *y), Tile::Covered)
+                    && !deductions.safe.contains(&(*x, *y))
+            })
+            .unwrap();
+
+        // The tile is safe, but it could not be deduced
+        state.uncover(x, y);
+        assert_eq!(state.outcome, Some(Outcome::Guessed));
+        assert!(!state.map.has_stepped_on_mine());
+
+        state = update(state, &InputFrame::default());
+        assert_eq!(state.outcome, Some(Outcome::Guessed));
+        assert!(!PuzzleProgress::load().is_solved(0));
+    }
+}
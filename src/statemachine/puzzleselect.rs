@@ -0,0 +1,140 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::fmt::format_number;
+use crate::game::{PuzzleProgress, PUZZLES};
+use crate::graphics::{draw_text, DrawColors, Palette};
+use crate::input::InputFrame;
+use crate::interface::{draw_entry, is_inside, ENTRY_HEIGHT, ENTRY_WIDTH, ENTRY_X};
+use crate::wasm4::{BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_UP};
+
+use super::{MainMenuState, PuzzleState, State, Transition};
+
+/// Vertical position of the first puzzle entry
+const PUZZLES_Y: i32 = 14;
+
+/// Vertical distance between puzzle entries
+const PUZZLES_STEP: i32 = 14;
+
+const BACK_Y: i32 = 130;
+
+/// Number of rows selectable with the gamepad: puzzles and back
+const ROWS_COUNT: usize = PUZZLES.len() + 1;
+const BACK_ROW: usize = PUZZLES.len();
+
+/// A state choosing a puzzle
+///
+/// Solved puzzles are marked.
+#[derive(Clone)]
+pub struct PuzzleSelectState {
+    main_menu: MainMenuState,
+    progress: PuzzleProgress,
+    selected: Option<usize>,
+}
+
+impl PuzzleSelectState {
+    pub fn new(main_menu: MainMenuState) -> Self {
+        Self {
+            main_menu,
+            progress: PuzzleProgress::load(),
+            selected: None,
+        }
+    }
+
+    pub fn draw(&self, input: Option<InputFrame>) {
+        Palette::Wheat.set();
+
+        let title = "PUZZLES";
+        DrawColors.set(0x02);
+        draw_text(title, 1 + (160 - 8 * title.len() as i32) / 2, 4);
+
+        for (i, puzzle) in PUZZLES.iter().enumerate() {
+            let marker = if self.progress.is_solved(i) {
+                "* "
+            } else {
+                "  "
+            };
+            draw_entry(
+                &(marker.to_owned() + puzzle.name),
+                row_to_y(i),
+                input,
+                self.selected == Some(i),
+            );
+        }
+
+        let solved = format_number(self.progress.count_solved() as u32, None)
+            + "/"
+            + &format_number(PUZZLES.len() as u32, None);
+        DrawColors.set(0x03);
+        draw_text(&solved, 156 - 8 * solved.len() as i32, 4);
+
+        draw_entry("Back", BACK_Y, input, self.selected == Some(BACK_ROW));
+    }
+
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if input.pressed(BUTTON_UP) {
+            self.selected = Some(match self.selected {
+                Some(row) if row > 0 => row - 1,
+                _ => ROWS_COUNT - 1,
+            });
+        }
+        if input.pressed(BUTTON_DOWN) {
+            self.selected = Some(match self.selected {
+                Some(row) if row + 1 < ROWS_COUNT => row + 1,
+                _ => 0,
+            });
+        }
+        if input.pressed(BUTTON_2) {
+            return self.back();
+        }
+        if input.pressed(BUTTON_1) {
+            if let Some(row) = self.selected {
+                return self.activate_row(row);
+            }
+        }
+
+        if input.left_clicked() {
+            self.selected = None;
+            let (mouse_x, mouse_y) = input.coordinates();
+            if let Some(row) = (0..ROWS_COUNT).find(|row| {
+                is_inside(
+                    ENTRY_X,
+                    row_to_y(*row),
+                    ENTRY_WIDTH,
+                    ENTRY_HEIGHT,
+                    mouse_x,
+                    mouse_y,
+                )
+            }) {
+                return self.activate_row(row);
+            }
+        }
+
+        Transition::Replace(State::PuzzleSelect(self))
+    }
+
+    fn activate_row(self, row: usize) -> Transition {
+        if row == BACK_ROW {
+            return self.back();
+        }
+        match PuzzleState::new(row) {
+            Some(puzzle) => Transition::Replace(State::Puzzle(puzzle)),
+            None => Transition::Replace(State::PuzzleSelect(self)),
+        }
+    }
+
+    fn back(self) -> Transition {
+        Transition::Replace(State::MainMenu(self.main_menu))
+    }
+}
+
+fn row_to_y(row: usize) -> i32 {
+    if row == BACK_ROW {
+        BACK_Y
+    } else {
+        PUZZLES_Y + PUZZLES_STEP * row as i32
+    }
+}
@@ -0,0 +1,314 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::game::{Action, Map, Replay, Settings};
+use crate::graphics::Palette;
+use crate::input::{Drag, InputFrame};
+use crate::interface::{
+    draw_button, draw_elapsed_time, draw_entry, draw_remaining_mines_count, is_inside,
+    is_inside_hud_button, ENTRY_HEIGHT, ENTRY_WIDTH, ENTRY_X, HUD_BUTTON_Y,
+};
+use crate::time::FRAMES_PER_SECOND;
+use crate::wasm4::{BUTTON_1, BUTTON_2, BUTTON_RIGHT, BUTTON_UP};
+
+use super::ingame::HINT_PENALTY;
+use super::pregame::{MAP_AREA, TILE_SIZE};
+use super::{State, Transition};
+
+/// Number of frames simulated at each update when fast-forwarding
+const FAST_FORWARD_SPEED: u32 = 4;
+
+const PLAY_BUTTON_X: i32 = 70;
+const STEP_BUTTON_X: i32 = 84;
+const FAST_FORWARD_BUTTON_X: i32 = 98;
+const EXIT_BUTTON_X: i32 = 112;
+
+/// Vertical position of the replay entry on end-of-game screens
+const REPLAY_ENTRY_Y: i32 = 143;
+
+/// Draw the entry opening the replay on end-of-game screens
+pub fn draw_replay_entry(input: Option<InputFrame>) {
+    draw_entry("Watch replay (Z)", REPLAY_ENTRY_Y, input, false);
+}
+
+/// Check whether the player asked to watch the replay
+pub fn is_replay_requested(input: &InputFrame) -> bool {
+    let (mouse_x, mouse_y) = input.coordinates();
+    input.pressed(BUTTON_2)
+        || input.left_clicked()
+            && is_inside(
+                ENTRY_X,
+                REPLAY_ENTRY_Y,
+                ENTRY_WIDTH,
+                ENTRY_HEIGHT,
+                mouse_x,
+                mouse_y,
+            )
+}
+
+/// A state re-simulating a recorded game
+///
+/// The game is re-simulated from the beginning, applying each recorded move
+/// at its frame.
+/// The replay can be paused, advanced one move at a time and fast-forwarded.
+#[derive(Clone)]
+pub struct ReplayState {
+    replay: Replay,
+    map: Map,
+    frame: u32,
+    next_move: usize,
+    penalty: u32,
+    playing: bool,
+    fast_forward: bool,
+    drag: Drag,
+    settings: Settings,
+}
+
+impl ReplayState {
+    pub fn new(replay: Replay) -> Self {
+        // Recorded games were played, so their mines could always be placed
+        let mines = replay.mines().unwrap_or_default();
+        let map = new_map(&replay, &mines);
+        Self {
+            replay,
+            map,
+            frame: 0,
+            next_move: 0,
+            penalty: 0,
+            playing: true,
+            fast_forward: false,
+            drag: Drag::new(),
+            settings: Settings::load(),
+        }
+    }
+
+    pub fn draw(&self, input: Option<InputFrame>) {
+        Palette::Hollow.set();
+
+        self.map.draw();
+
+        draw_remaining_mines_count(self.map.count_remaining_mines(), 160 - 36, 2);
+
+        let elapsed_time = self.frame + self.penalty * FRAMES_PER_SECOND;
+        draw_elapsed_time(elapsed_time, self.settings.show_tenths, 2, 2);
+
+        let play = if self.playing { "|" } else { ">" };
+        draw_button(play, PLAY_BUTTON_X, HUD_BUTTON_Y, input);
+        draw_button("S", STEP_BUTTON_X, HUD_BUTTON_Y, input);
+        draw_button("F", FAST_FORWARD_BUTTON_X, HUD_BUTTON_Y, input);
+        draw_button("X", EXIT_BUTTON_X, HUD_BUTTON_Y, input);
+    }
+
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        let (dx, dy) = self.drag.update(input);
+        self.map.pan(dx, dy);
+
+        // Releasing the button at the end of a drag is not a click
+        let left_clicked = input.left_clicked() && !self.drag.is_dragging();
+        let (mouse_x, mouse_y) = input.coordinates();
+        let is_clicked = |x| left_clicked && is_inside_hud_button(x, mouse_x, mouse_y);
+
+        if input.pressed(BUTTON_2) || is_clicked(EXIT_BUTTON_X) {
+            return Transition::Pop;
+        }
+
+        if input.pressed(BUTTON_1) || is_clicked(PLAY_BUTTON_X) {
+            if self.is_finished() {
+                self.restart();
+                self.playing = true;
+            } else {
+                self.playing = !self.playing;
+            }
+        }
+        if input.pressed(BUTTON_RIGHT) || is_clicked(STEP_BUTTON_X) {
+            self.playing = false;
+            self.step();
+        }
+        if input.pressed(BUTTON_UP) || is_clicked(FAST_FORWARD_BUTTON_X) {
+            self.fast_forward = !self.fast_forward;
+        }
+
+        if self.playing {
+            let speed = if self.fast_forward {
+                FAST_FORWARD_SPEED
+            } else {
+                1
+            };
+            for _ in 0..speed {
+                self.advance();
+            }
+            if self.is_finished() {
+                self.playing = false;
+            }
+        }
+
+        Transition::Replace(State::Replay(self))
+    }
+
+    /// Check whether all recorded frames were simulated
+    fn is_finished(&self) -> bool {
+        self.next_move >= self.replay.moves().len() && self.frame >= self.replay.frames()
+    }
+
+    /// Restart the replay from the beginning
+    fn restart(&mut self) {
+        self.map = new_map(&self.replay, &self.map.mines());
+        self.frame = 0;
+        self.next_move = 0;
+        self.penalty = 0;
+    }
+
+    /// Simulate the current frame and move to the next one
+    fn advance(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+        self.apply_moves_until(self.frame);
+        self.frame += 1;
+    }
+
+    /// Simulate all frames until the next move is applied
+    fn step(&mut self) {
+        if let Some(next) = self.replay.moves().get(self.next_move) {
+            let frame = next.frame;
+            self.apply_moves_until(frame);
+            self.frame = frame + 1;
+        }
+    }
+
+    /// Apply all moves not yet applied up to a frame
+    fn apply_moves_until(&mut self, frame: u32) {
+        while let Some(next) = self.replay.moves().get(self.next_move).copied() {
+            if next.frame > frame {
+                break;
+            }
+            self.apply(next.action);
+            self.next_move += 1;
+        }
+    }
+
+    fn apply(&mut self, action: Action) {
+        match action {
+            Action::Uncover(x, y) => self.map.uncover_tile(x, y),
+            Action::Flag(x, y) => self.map.flip_flagged_tile(x, y),
+            Action::Chord(x, y) => self.map.chord_tile(x, y),
+            Action::Hint(x, y) => {
+                self.map.uncover_tile_ignoring_flags(x, y);
+                self.penalty += HINT_PENALTY;
+            }
+        }
+    }
+}
+
+/// Create the map at the beginning of a game
+fn new_map(replay: &Replay, mines: &[(usize, usize)]) -> Map {
+    let difficulty = replay.difficulty();
+    let mut map = Map::new(difficulty.width(), difficulty.height(), TILE_SIZE, (0, 0))
+        .with_area(MAP_AREA)
+        .with_mines(mines);
+    let (x, y) = replay.start();
+    map.uncover_tile(x, y);
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::{Difficulty, Generator, Seed};
+
+    fn update(state: ReplayState, input: &InputFrame) -> ReplayState {
+        match state.update(input) {
+            Transition::Replace(State::Replay(state)) => state,
+            _ => panic!("Unexpected transition"),
+        }
+    }
+
+    /// Record a game and return it together with the final map
+    fn record_game() -> (Replay, Map) {
+        let seed = Seed::from_code("SWEEP2").unwrap();
+        let mut replay = Replay::new(Difficulty::Medium, Generator::Random, seed, (0, 0));
+        let mines = replay.mines().unwrap();
+        let mut map = new_map(&replay, &mines);
+
+        let safe_tiles = (0..14)
+            .flat_map(|y| (0..16).map(move |x| (x, y)))
+            .filter(|tile| !mines.contains(tile))
+            .step_by(7)
+            .take(5);
+        for (x, y) in safe_tiles {
+            for _ in 0..30 {
+                replay.tick();
+            }
+            replay.record(Action::Uncover(x, y));
+            map.uncover_tile(x, y);
+        }
+
+        let (x, y) = mines[0];
+        replay.record(Action::Flag(x, y));
+        map.flip_flagged_tile(x, y);
+        replay.tick();
+
+        (replay, map)
+    }
+
+    fn assert_same_tiles(left: &Map, right: &Map) {
+        for x in 0..left.width() {
+            for y in 0..left.height() {
+                assert_eq!(left.tile(x, y), right.tile(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn replay_reproduces_game() {
+        let (replay, map) = record_game();
+        let frames = replay.frames();
+
+        let mut state = ReplayState::new(replay);
+        for _ in 0..frames {
+            state = update(state, &InputFrame::default());
+        }
+
+        assert!(state.is_finished());
+        assert!(!state.playing);
+        assert_same_tiles(&state.map, &map);
+    }
+
+    #[test]
+    fn fast_forward_is_faster() {
+        let (replay, map) = record_game();
+        let frames = replay.frames();
+
+        let press_up = InputFrame {
+            gamepad_buttons: BUTTON_UP,
+            ..InputFrame::default()
+        };
+        let mut state = update(ReplayState::new(replay), &press_up);
+        for _ in 0..(frames / FAST_FORWARD_SPEED) {
+            state = update(state, &InputFrame::default());
+        }
+
+        assert!(state.is_finished());
+        assert_same_tiles(&state.map, &map);
+    }
+
+    #[test]
+    fn step_applies_one_move() {
+        let (replay, _) = record_game();
+        let first = replay.moves()[0];
+
+        let press_right = InputFrame {
+            gamepad_buttons: BUTTON_RIGHT,
+            ..InputFrame::default()
+        };
+        let state = update(ReplayState::new(replay), &press_right);
+
+        assert!(!state.playing);
+        assert_eq!(state.next_move, 1);
+        assert_eq!(state.frame, first.frame + 1);
+    }
+}
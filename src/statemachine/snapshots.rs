@@ -0,0 +1,392 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Golden-image snapshot tests of all states
+
+use crate::game::{
+    Clicks, Cursor, Date, Difficulty, FirstClick, Generator, HighScore, HighScores,
+    LeaderboardEntry, Map, Performance, PuzzleProgress, Replay, Seed, Settings, Statistics,
+};
+use crate::harness::Harness;
+use crate::input::InputFrame;
+use crate::time::Timer;
+use crate::wasm4::{BUTTON_1, BUTTON_DOWN, BUTTON_UP};
+
+use super::{
+    CustomGameState, DailyState, GameOverState, GameWonState, InGameState, InitialState,
+    InitialsState, InstructionsState, LeaderboardState, Machine, MainMenuState, OptionsState,
+    PauseState, PlaySeedState, PreGameState, PuzzleSelectState, PuzzleState, ReplayState, State,
+    StatisticsState, Transition,
+};
+
+const MINES: [(usize, usize); 10] = [
+    (3, 1),
+    (7, 2),
+    (12, 3),
+    (1, 5),
+    (9, 6),
+    (14, 8),
+    (4, 9),
+    (11, 11),
+    (6, 12),
+    (15, 13),
+];
+
+fn replay() -> Replay {
+    let seed = Seed::from_code("SWEEP2").unwrap();
+    Replay::new(Difficulty::Easy, Generator::Random, seed, (0, 0))
+}
+
+fn score(time: u32, hints: u8) -> HighScore {
+    HighScore {
+        time,
+        hints,
+        performance: None,
+    }
+}
+
+fn timer(seconds: u32) -> Timer {
+    let mut timer = Timer::new();
+    timer.add(seconds);
+    timer
+}
+
+fn clicks() -> Clicks {
+    Clicks {
+        left: 24,
+        right: 6,
+        chord: 3,
+    }
+}
+
+fn new_map() -> Map {
+    Map::new(16, 14, 10, (0, 0))
+        .with_area((0, 20, 160, 140))
+        .with_mines(&MINES)
+}
+
+/// Return a map after a few moves, with a flagged mine
+fn played_map() -> Map {
+    let mut map = new_map();
+    map.uncover_tile(0, 0);
+    map.uncover_tile(15, 0);
+    map.flag_tile(7, 2);
+    map
+}
+
+fn in_game_state(map: Map) -> State {
+    let cursor = Cursor::new(&map);
+    State::InGame(InGameState::new(map, cursor, replay(), None))
+}
+
+/// Draw a stack of states and compare the result with a snapshot
+fn assert_states_snapshot<F>(name: &str, states: F)
+where
+    F: FnOnce() -> Vec<State>,
+{
+    let mut harness = Harness::new();
+    harness.set_machine(Machine::with_states(states()));
+    harness.step();
+    harness.assert_snapshot(name);
+}
+
+#[test]
+fn initial() {
+    assert_states_snapshot("initial", || vec![State::Initial(InitialState::new())]);
+}
+
+#[test]
+fn main_menu() {
+    assert_states_snapshot("main_menu", || vec![State::MainMenu(MainMenuState::new())]);
+}
+
+#[test]
+fn main_menu_with_high_scores() {
+    assert_states_snapshot("main_menu_with_high_scores", || {
+        let mut highscores = HighScores::load();
+        highscores.set(Difficulty::Easy, score(42 * 60 + 17, 0));
+        highscores.set(Difficulty::Hard, score(321 * 60 + 45, 2));
+        highscores.save();
+        vec![State::MainMenu(MainMenuState::new())]
+    });
+}
+
+#[test]
+fn main_menu_with_replay_selected() {
+    assert_states_snapshot("main_menu_with_replay_selected", || {
+        let mut highscores = HighScores::load();
+        highscores.set(Difficulty::Easy, score(42 * 60 + 17, 1));
+        highscores.set_replay(replay());
+        highscores.save();
+
+        // Selecting upwards wraps around to the last high score with a replay
+        let press_up = InputFrame {
+            gamepad_buttons: BUTTON_UP,
+            ..InputFrame::default()
+        };
+        match MainMenuState::new().update(&press_up) {
+            Transition::Replace(state) => vec![state],
+            _ => panic!("Unexpected transition"),
+        }
+    });
+}
+
+#[test]
+fn custom_game() {
+    assert_states_snapshot("custom_game", || {
+        vec![State::CustomGame(
+            CustomGameState::new(MainMenuState::new()),
+        )]
+    });
+}
+
+#[test]
+fn play_seed() {
+    assert_states_snapshot("play_seed", || {
+        vec![State::PlaySeed(PlaySeedState::new(MainMenuState::new()))]
+    });
+}
+
+#[test]
+fn daily() {
+    assert_states_snapshot("daily", || {
+        let date = Date::new(2021, 10, 18).unwrap();
+        let mut highscores = HighScores::load();
+        highscores.set_daily_date(date);
+        highscores.set_daily(date.add_days(-1), score(95 * 60, 0));
+        highscores.set_daily(date, score(87 * 60 + 36, 1));
+        highscores.save();
+        vec![State::Daily(DailyState::new(MainMenuState::new()))]
+    });
+}
+
+#[test]
+fn pre_game() {
+    assert_states_snapshot("pre_game", || {
+        vec![State::PreGame(PreGameState::new(
+            Difficulty::Easy,
+            Generator::Random,
+        ))]
+    });
+}
+
+#[test]
+fn in_game() {
+    assert_states_snapshot("in_game", || vec![in_game_state(played_map())]);
+}
+
+#[test]
+fn in_game_zoomed_in() {
+    assert_states_snapshot("in_game_zoomed_in", || {
+        let mut map = played_map();
+        map.zoom_in();
+        vec![in_game_state(map)]
+    });
+}
+
+#[test]
+fn in_game_zoomed_out() {
+    assert_states_snapshot("in_game_zoomed_out", || {
+        let mut map = played_map();
+        map.zoom_out();
+        map.zoom_out();
+        vec![in_game_state(map)]
+    });
+}
+
+#[test]
+fn pause() {
+    assert_states_snapshot("pause", || {
+        vec![
+            State::Initial(InitialState::new()),
+            in_game_state(played_map()),
+            State::Pause(PauseState::new()),
+        ]
+    });
+}
+
+#[test]
+fn game_over() {
+    assert_states_snapshot("game_over", || {
+        let mut map = played_map();
+        map.uncover_tile(3, 1);
+        map.flag_mines();
+        vec![State::GameOver(GameOverState::new(
+            map,
+            timer(25),
+            clicks(),
+            replay(),
+            true,
+        ))]
+    });
+}
+
+#[test]
+fn game_won() {
+    assert_states_snapshot("game_won", || {
+        let mut map = new_map();
+        for x in 0..16 {
+            for y in 0..14 {
+                if map.is_mine(x, y) {
+                    map.flag_tile(x, y);
+                } else {
+                    map.uncover_tile(x, y);
+                }
+            }
+        }
+        vec![State::GameWon(GameWonState::new(
+            map,
+            timer(40),
+            clicks(),
+            1,
+            replay(),
+            None,
+            true,
+        ))]
+    });
+}
+
+#[test]
+fn initials() {
+    assert_states_snapshot("initials", || {
+        let entry = LeaderboardEntry {
+            time: 42 * 60 + 25,
+            hints: 0,
+            initials: *b"ABC",
+            game: 7,
+            performance: Some(Performance {
+                three_bv: 31,
+                clicks: 36,
+            }),
+        };
+        vec![State::Initials(InitialsState::new(
+            Difficulty::Easy,
+            1,
+            entry,
+            replay(),
+        ))]
+    });
+}
+
+#[test]
+fn leaderboard() {
+    assert_states_snapshot("leaderboard", || {
+        let mut highscores = HighScores::load();
+        for (time, initials) in [(2545, b"ABC"), (3431, b"XYZ"), (7808, b"QRS")].iter() {
+            let game = highscores.record_win();
+            highscores.insert(
+                Difficulty::Easy,
+                LeaderboardEntry {
+                    time: *time,
+                    hints: 0,
+                    initials: **initials,
+                    game,
+                    performance: None,
+                },
+            );
+        }
+        highscores.save();
+        vec![State::Leaderboard(
+            LeaderboardState::new(MainMenuState::new(), Difficulty::Easy).with_highlight(1),
+        )]
+    });
+}
+
+fn statistics_state() -> StatisticsState {
+    let mut statistics = Statistics::load();
+    statistics.record_win(Difficulty::Easy, 42, 71);
+    statistics.record_win(Difficulty::Easy, 57, 71);
+    statistics.record_loss(Difficulty::Easy, 23);
+    statistics.save();
+    StatisticsState::new(MainMenuState::new())
+}
+
+#[test]
+fn statistics() {
+    assert_states_snapshot("statistics", || vec![State::Statistics(statistics_state())]);
+}
+
+#[test]
+fn statistics_reset() {
+    assert_states_snapshot("statistics_reset", || {
+        let press_down = InputFrame {
+            gamepad_buttons: BUTTON_DOWN,
+            ..InputFrame::default()
+        };
+        let press_x = InputFrame {
+            gamepad_buttons: BUTTON_1,
+            ..InputFrame::default()
+        };
+        let mut state = State::Statistics(statistics_state());
+        for input in [press_down, press_x].iter() {
+            state = match state.update(input) {
+                Transition::Replace(state) => state,
+                _ => panic!("Unexpected transition"),
+            };
+        }
+        vec![state]
+    });
+}
+
+#[test]
+fn options() {
+    assert_states_snapshot("options", || {
+        let mut settings = Settings::load();
+        settings.first_click = FirstClick::Opening;
+        settings.save();
+        vec![State::Options(OptionsState::new(MainMenuState::new()))]
+    });
+}
+
+#[test]
+fn puzzle_select() {
+    assert_states_snapshot("puzzle_select", || {
+        let mut progress = PuzzleProgress::load();
+        progress.mark_solved(0);
+        progress.mark_solved(2);
+        progress.save();
+        vec![State::PuzzleSelect(PuzzleSelectState::new(
+            MainMenuState::new(),
+        ))]
+    });
+}
+
+#[test]
+fn puzzle() {
+    assert_states_snapshot("puzzle", || {
+        vec![State::Puzzle(PuzzleState::new(1).unwrap())]
+    });
+}
+
+#[test]
+fn replay_viewer() {
+    assert_states_snapshot("replay", || {
+        vec![
+            State::Initial(InitialState::new()),
+            State::Replay(ReplayState::new(replay())),
+        ]
+    });
+}
+
+#[test]
+fn instructions() {
+    let mut harness = Harness::new();
+    harness.set_machine(Machine::with_states(vec![
+        State::Initial(InitialState::new()),
+        State::MainMenu(MainMenuState::new()),
+        State::Instructions(InstructionsState::new()),
+    ]));
+    harness.step();
+
+    let mut page = 1;
+    while harness.state_name() == "instructions" {
+        harness.assert_snapshot(&format!("instructions_{}", page));
+        harness.input().left_click(80, 80);
+        harness.run_script();
+        page += 1;
+    }
+    assert_eq!(page, 10);
+}
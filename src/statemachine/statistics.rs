@@ -0,0 +1,263 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::fmt::format_number;
+use crate::game::{Difficulty, Statistics};
+use crate::graphics::{draw_text, DrawColors, Palette};
+use crate::input::InputFrame;
+use crate::interface::{
+    draw_button, draw_entry, draw_message_box, is_inside, BUTTON_SIZE, ENTRY_HEIGHT, ENTRY_WIDTH,
+    ENTRY_X,
+};
+use crate::wasm4::{BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP};
+
+use super::{MainMenuState, State, Transition};
+
+/// Difficulties with separate statistics
+///
+/// All custom games share the same statistics.
+const DIFFICULTIES: [Difficulty; 4] = [
+    Difficulty::Easy,
+    Difficulty::Medium,
+    Difficulty::Hard,
+    Difficulty::Custom {
+        width: 0,
+        height: 0,
+        mines: 0,
+    },
+];
+
+const DIFFICULTY_Y: i32 = 20;
+const DECREASE_X: i32 = 8;
+const INCREASE_X: i32 = 140;
+
+/// Vertical position of the first line
+const LINES_Y: i32 = 40;
+
+/// Vertical distance between lines
+const LINES_STEP: i32 = 9;
+
+const RESET_Y: i32 = 113;
+const BACK_Y: i32 = 130;
+
+/// Number of rows selectable with the gamepad: reset and back
+const ROWS_COUNT: usize = 2;
+const RESET_ROW: usize = 0;
+const BACK_ROW: usize = 1;
+
+/// A state showing the lifetime statistics
+///
+/// Resetting the statistics requires a confirmation.
+#[derive(Clone)]
+pub struct StatisticsState {
+    main_menu: MainMenuState,
+    statistics: Statistics,
+    difficulty: usize,
+    selected: Option<usize>,
+    confirming: bool,
+}
+
+impl StatisticsState {
+    pub fn new(main_menu: MainMenuState) -> Self {
+        Self {
+            main_menu,
+            statistics: Statistics::load(),
+            difficulty: 0,
+            selected: None,
+            confirming: false,
+        }
+    }
+
+    pub fn draw(&self, input: Option<InputFrame>) {
+        Palette::Wheat.set();
+
+        let title = "STATISTICS";
+        DrawColors.set(0x02);
+        draw_text(title, 1 + (160 - 8 * title.len() as i32) / 2, 4);
+
+        let difficulty = DIFFICULTIES[self.difficulty].as_ref();
+        DrawColors.set(0x03);
+        draw_text(
+            difficulty,
+            1 + (160 - 8 * difficulty.len() as i32) / 2,
+            DIFFICULTY_Y + 2,
+        );
+        draw_button("<", DECREASE_X, DIFFICULTY_Y, input);
+        draw_button(">", INCREASE_X, DIFFICULTY_Y, input);
+
+        let statistics = self.statistics.get(DIFFICULTIES[self.difficulty]);
+        let or_dash = |value: Option<u32>, unit: &str| {
+            value
+                .map(|value| format_number(value, None) + unit)
+                .unwrap_or_else(|| "-".to_owned())
+        };
+        let lines = [
+            ("Played", format_number(statistics.played(), None)),
+            ("Won", format_number(statistics.won.into(), None)),
+            ("Lost", format_number(statistics.lost.into(), None)),
+            ("Win rate", or_dash(statistics.win_rate(), "%")),
+            ("Streak", format_number(statistics.streak.into(), None)),
+            (
+                "Best streak",
+                format_number(statistics.longest_streak.into(), None),
+            ),
+            ("Avg. time", or_dash(statistics.average_time(), " s")),
+            ("Tiles", format_number(statistics.tiles, None)),
+        ];
+        for (i, (label, value)) in lines.iter().enumerate() {
+            let y = LINES_Y + LINES_STEP * i as i32;
+            DrawColors.set(0x03);
+            draw_text(label, 8, y);
+            DrawColors.set(0x02);
+            draw_text(value, 152 - 8 * value.len() as i32, y);
+        }
+
+        if self.confirming {
+            draw_message_box("Reset all\nstatistics?", 30, 50);
+            draw_entry("Confirm reset", RESET_Y, input, self.selected == Some(RESET_ROW));
+            draw_entry("Cancel", BACK_Y, input, self.selected == Some(BACK_ROW));
+        } else {
+            draw_entry(
+                "Reset statistics",
+                RESET_Y,
+                input,
+                self.selected == Some(RESET_ROW),
+            );
+            draw_entry("Back", BACK_Y, input, self.selected == Some(BACK_ROW));
+        }
+    }
+
+    pub fn update(mut self, input: &InputFrame) -> Transition {
+        if input.pressed(BUTTON_UP) {
+            self.selected = Some(match self.selected {
+                Some(row) if row > 0 => row - 1,
+                _ => ROWS_COUNT - 1,
+            });
+        }
+        if input.pressed(BUTTON_DOWN) {
+            self.selected = Some(match self.selected {
+                Some(row) if row + 1 < ROWS_COUNT => row + 1,
+                _ => 0,
+            });
+        }
+        if !self.confirming {
+            if input.pressed(BUTTON_LEFT) {
+                self.change_difficulty(-1);
+            }
+            if input.pressed(BUTTON_RIGHT) {
+                self.change_difficulty(1);
+            }
+        }
+        if input.pressed(BUTTON_2) {
+            return self.back();
+        }
+        if input.pressed(BUTTON_1) {
+            match self.selected {
+                Some(RESET_ROW) => return self.reset(),
+                Some(BACK_ROW) => return self.back(),
+                _ => {}
+            }
+        }
+
+        if input.left_clicked() {
+            self.selected = None;
+            let (mouse_x, mouse_y) = input.coordinates();
+            if !self.confirming {
+                let is_inside_button =
+                    |x, y| is_inside(x, y, BUTTON_SIZE, BUTTON_SIZE, mouse_x, mouse_y);
+                if is_inside_button(DECREASE_X, DIFFICULTY_Y) {
+                    self.change_difficulty(-1);
+                }
+                if is_inside_button(INCREASE_X, DIFFICULTY_Y) {
+                    self.change_difficulty(1);
+                }
+            }
+            if is_inside(
+                ENTRY_X,
+                RESET_Y,
+                ENTRY_WIDTH,
+                ENTRY_HEIGHT,
+                mouse_x,
+                mouse_y,
+            ) {
+                return self.reset();
+            }
+            if is_inside(ENTRY_X, BACK_Y, ENTRY_WIDTH, ENTRY_HEIGHT, mouse_x, mouse_y) {
+                return self.back();
+            }
+        }
+
+        Transition::Replace(State::Statistics(self))
+    }
+
+    /// Ask for confirmation, or reset the statistics if already confirmed
+    fn reset(mut self) -> Transition {
+        if self.confirming {
+            self.statistics.reset();
+            self.statistics.save();
+        }
+        self.confirming = !self.confirming;
+        Transition::Replace(State::Statistics(self))
+    }
+
+    /// Cancel the confirmation, or return to the main menu
+    fn back(mut self) -> Transition {
+        if self.confirming {
+            self.confirming = false;
+            Transition::Replace(State::Statistics(self))
+        } else {
+            Transition::Replace(State::MainMenu(self.main_menu))
+        }
+    }
+
+    fn change_difficulty(&mut self, step: isize) {
+        let count = DIFFICULTIES.len() as isize;
+        self.difficulty = (self.difficulty as isize + step).rem_euclid(count) as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(button: u8) -> InputFrame {
+        InputFrame {
+            gamepad_buttons: button,
+            ..InputFrame::default()
+        }
+    }
+
+    fn update(state: StatisticsState, input: &InputFrame) -> StatisticsState {
+        match state.update(input) {
+            Transition::Replace(State::Statistics(state)) => state,
+            _ => panic!("Unexpected transition"),
+        }
+    }
+
+    #[test]
+    fn reset_requires_confirmation() {
+        let _harness = crate::harness::Harness::new();
+        let mut statistics = Statistics::load();
+        statistics.record_win(Difficulty::Hard, 100, 200);
+        statistics.save();
+
+        let mut state = StatisticsState::new(MainMenuState::new());
+        state = update(state, &press(BUTTON_DOWN));
+        state = update(state, &press(BUTTON_1));
+        assert!(state.confirming);
+
+        // Cancelling keeps the statistics
+        state = update(state, &press(BUTTON_2));
+        assert!(!state.confirming);
+        assert_eq!(Statistics::load().get(Difficulty::Hard).won, 1);
+
+        state = update(state, &press(BUTTON_1));
+        state = update(state, &press(BUTTON_1));
+        assert!(!state.confirming);
+        assert_eq!(Statistics::load(), Statistics::default());
+        assert_eq!(state.statistics, Statistics::default());
+    }
+}
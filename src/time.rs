@@ -6,6 +6,11 @@
 
 //! Time primitives and subsystems
 
+/// Number of frames in a second
+///
+/// WASM-4 engine runs at 60 frames per second.
+pub const FRAMES_PER_SECOND: u32 = 60;
+
 mod ticker;
 pub use ticker::BigTicker;
 pub use ticker::Ticker;
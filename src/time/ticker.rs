@@ -6,6 +6,8 @@
 
 //! Engine ticker
 
+use super::FRAMES_PER_SECOND;
+
 /// A ticker to keep track of the current frame
 ///
 /// WASM-4 engine runs at 60 frames per second.
@@ -21,7 +23,7 @@ impl Ticker {
     pub fn update(&mut self) {
         unsafe {
             TICKER_COUNTER += 1;
-            TICKER_COUNTER %= 60;
+            TICKER_COUNTER %= FRAMES_PER_SECOND as u8;
         }
     }
 
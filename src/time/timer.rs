@@ -6,9 +6,11 @@
 
 //! Timer data types and function
 
-use super::Ticker;
+use super::FRAMES_PER_SECOND;
 
 /// A timer to keep track of elapsed time
+///
+/// Time is kept with frame accuracy.
 #[derive(Clone)]
 pub struct Timer(u32);
 
@@ -22,13 +24,21 @@ impl Timer {
     ///
     /// This function must be called at each frame.
     pub fn update(&mut self) {
-        if Ticker.get() == 0 {
-            self.0 += 1;
-        }
+        self.0 = self.0.saturating_add(1);
+    }
+
+    /// Add a penalty to the timer
+    pub fn add(&mut self, seconds: u32) {
+        self.0 = self.0.saturating_add(seconds * FRAMES_PER_SECOND);
     }
 
-    /// Return the time since the time started in seconds
+    /// Return the time since the time started in whole seconds
     pub fn get(&self) -> u32 {
+        self.0 / FRAMES_PER_SECOND
+    }
+
+    /// Return the time since the time started in frames
+    pub fn frames(&self) -> u32 {
         self.0
     }
 }
@@ -38,3 +48,22 @@ impl Default for Timer {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_frames() {
+        let mut timer = Timer::new();
+        for _ in 0..150 {
+            timer.update();
+        }
+        assert_eq!(timer.frames(), 150);
+        assert_eq!(timer.get(), 2);
+
+        timer.add(10);
+        assert_eq!(timer.frames(), 750);
+        assert_eq!(timer.get(), 12);
+    }
+}
@@ -5,6 +5,12 @@
 
 #![allow(unused)]
 
+// The native backend emulates the console on the host, e.g. for tests.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native;
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::{blit, blit_sub, diskr, diskw, hline, line, oval, rect, text, tone, trace, vline};
+
 // ┌───────────────────────────────────────────────────────────────────────────┐
 // │                                                                           │
 // │ Platform Constants                                                        │
@@ -19,17 +25,50 @@ pub const SCREEN_SIZE: u32 = 160;
 // │                                                                           │
 // └───────────────────────────────────────────────────────────────────────────┘
 
+#[cfg(target_arch = "wasm32")]
 pub static mut PALETTE: *mut [u32; 4] = 0x04 as *mut [u32; 4];
+#[cfg(target_arch = "wasm32")]
 pub const DRAW_COLORS: *mut u16 = 0x14 as *mut u16;
+#[cfg(target_arch = "wasm32")]
 pub const GAMEPAD1: *const u8 = 0x16 as *const u8;
+#[cfg(target_arch = "wasm32")]
 pub const GAMEPAD2: *const u8 = 0x17 as *const u8;
+#[cfg(target_arch = "wasm32")]
 pub const GAMEPAD3: *const u8 = 0x18 as *const u8;
+#[cfg(target_arch = "wasm32")]
 pub const GAMEPAD4: *const u8 = 0x19 as *const u8;
+#[cfg(target_arch = "wasm32")]
 pub const MOUSE_X: *const i16 = 0x1a as *const i16;
+#[cfg(target_arch = "wasm32")]
 pub const MOUSE_Y: *const i16 = 0x1c as *const i16;
+#[cfg(target_arch = "wasm32")]
 pub const MOUSE_BUTTONS: *const u8 = 0x1e as *const u8;
+#[cfg(target_arch = "wasm32")]
 pub static mut FRAMEBUFFER: *mut [u8; 6400] = 0xa0 as *mut [u8; 6400];
 
+// Pointers to the emulated memory cannot be constants, since constants cannot
+// refer to statics.
+#[cfg(not(target_arch = "wasm32"))]
+pub static mut PALETTE: *mut [u32; 4] = native::MEMORY.palette.get();
+#[cfg(not(target_arch = "wasm32"))]
+pub static mut DRAW_COLORS: *mut u16 = native::MEMORY.draw_colors.get();
+#[cfg(not(target_arch = "wasm32"))]
+pub static mut GAMEPAD1: *const u8 = native::MEMORY.gamepads[0].get();
+#[cfg(not(target_arch = "wasm32"))]
+pub static mut GAMEPAD2: *const u8 = native::MEMORY.gamepads[1].get();
+#[cfg(not(target_arch = "wasm32"))]
+pub static mut GAMEPAD3: *const u8 = native::MEMORY.gamepads[2].get();
+#[cfg(not(target_arch = "wasm32"))]
+pub static mut GAMEPAD4: *const u8 = native::MEMORY.gamepads[3].get();
+#[cfg(not(target_arch = "wasm32"))]
+pub static mut MOUSE_X: *const i16 = native::MEMORY.mouse_x.get();
+#[cfg(not(target_arch = "wasm32"))]
+pub static mut MOUSE_Y: *const i16 = native::MEMORY.mouse_y.get();
+#[cfg(not(target_arch = "wasm32"))]
+pub static mut MOUSE_BUTTONS: *const u8 = native::MEMORY.mouse_buttons.get();
+#[cfg(not(target_arch = "wasm32"))]
+pub static mut FRAMEBUFFER: *mut [u8; 6400] = native::MEMORY.framebuffer.get();
+
 pub const BUTTON_1: u8 = 1;
 pub const BUTTON_2: u8 = 2;
 pub const BUTTON_LEFT: u8 = 16;
@@ -37,6 +76,10 @@ pub const BUTTON_RIGHT: u8 = 32;
 pub const BUTTON_UP: u8 = 64;
 pub const BUTTON_DOWN: u8 = 128;
 
+pub const MOUSE_LEFT: u8 = 1;
+pub const MOUSE_RIGHT: u8 = 2;
+pub const MOUSE_MIDDLE: u8 = 4;
+
 // ┌───────────────────────────────────────────────────────────────────────────┐
 // │                                                                           │
 // │ Drawing Functions                                                         │
@@ -44,9 +87,11 @@ pub const BUTTON_DOWN: u8 = 128;
 // └───────────────────────────────────────────────────────────────────────────┘
 
 /// Copy pixels to the framebuffer.
+#[cfg(target_arch = "wasm32")]
 pub fn blit(sprite: &[u8], x: i32, y: i32, width: u32, height: u32, flags: u32) {
     unsafe { extern_blit(sprite.as_ptr(), x, y, width, height, flags) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "blit"]
     fn extern_blit(sprite: *const u8, x: i32, y: i32, width: u32, height: u32, flags: u32);
@@ -54,6 +99,7 @@ extern "C" {
 
 /// Copy a subregion within a larger sprite atlas to the framebuffer.
 #[allow(clippy::too_many_arguments)]
+#[cfg(target_arch = "wasm32")]
 pub fn blit_sub(
     sprite: &[u8],
     x: i32,
@@ -79,6 +125,7 @@ pub fn blit_sub(
         )
     }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "blitSub"]
     fn extern_blit_sub(
@@ -101,61 +148,73 @@ pub const BLIT_FLIP_Y: u32 = 4;
 pub const BLIT_ROTATE: u32 = 8;
 
 /// Draw a line between two points.
+#[cfg(target_arch = "wasm32")]
 pub fn line(x1: i32, y1: i32, x2: i32, y2: i32) {
     unsafe { extern_line(x1, y1, x2, y2) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "line"]
     fn extern_line(x1: i32, y1: i32, x2: i32, y2: i32);
 }
 
 /// Draw an oval (or circle).
+#[cfg(target_arch = "wasm32")]
 pub fn oval(x: i32, y: i32, width: u32, height: u32) {
     unsafe { extern_oval(x, y, width, height) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "oval"]
     fn extern_oval(x: i32, y: i32, width: u32, height: u32);
 }
 
 /// Draw a rectangle.
+#[cfg(target_arch = "wasm32")]
 pub fn rect(x: i32, y: i32, width: u32, height: u32) {
     unsafe { extern_rect(x, y, width, height) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "rect"]
     fn extern_rect(x: i32, y: i32, width: u32, height: u32);
 }
 
 /// Draw text using the built-in system font.
+#[cfg(target_arch = "wasm32")]
 pub fn text<T: AsRef<str>>(text: T, x: i32, y: i32) {
     let text_ref = text.as_ref();
     unsafe { extern_text(text_ref.as_ptr(), text_ref.len(), x, y) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "textUtf8"]
     fn extern_text(text: *const u8, length: usize, x: i32, y: i32);
 }
 
 /// Draw a vertical line
+#[cfg(target_arch = "wasm32")]
 pub fn vline(x: i32, y: i32, len: u32) {
     unsafe {
         extern_vline(x, y, len);
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "vline"]
     fn extern_vline(x: i32, y: i32, len: u32);
 }
 
 /// Draw a horizontal line
+#[cfg(target_arch = "wasm32")]
 pub fn hline(x: i32, y: i32, len: u32) {
     unsafe {
         extern_hline(x, y, len);
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "hline"]
     fn extern_hline(x: i32, y: i32, len: u32);
@@ -168,9 +227,11 @@ extern "C" {
 // └───────────────────────────────────────────────────────────────────────────┘
 
 /// Play a sound tone.
+#[cfg(target_arch = "wasm32")]
 pub fn tone(frequency: u32, duration: u32, volume: u32, flags: u32) {
     unsafe { extern_tone(frequency, duration, volume, flags) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "tone"]
     fn extern_tone(frequency: u32, duration: u32, volume: u32, flags: u32);
@@ -191,6 +252,7 @@ pub const TONE_MODE4: u32 = 12;
 // │                                                                           │
 // └───────────────────────────────────────────────────────────────────────────┘
 
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     /// Read up to `size` bytes from persistent storage into the pointer `dest`.
     pub fn diskr(dest: *mut u8, size: u32) -> u32;
@@ -206,10 +268,12 @@ extern "C" {
 // └───────────────────────────────────────────────────────────────────────────┘
 
 /// Print a message to the debug console.
+#[cfg(target_arch = "wasm32")]
 pub fn trace<T: AsRef<str>>(text: T) {
     let text_ref = text.as_ref();
     unsafe { extern_trace(text_ref.as_ptr(), text_ref.len()) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "traceUtf8"]
     fn extern_trace(trace: *const u8, length: usize);
@@ -0,0 +1,558 @@
+// Copyright Claudio Mattera 2021.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Native backend of WASM-4 runtime
+//!
+//! This backend emulates the console on the host: the memory, including the
+//! framebuffer and the input registers, is a static variable, drawing
+//! functions are implemented in software, and the persistent storage is kept
+//! in memory.
+//!
+//! Text is drawn with the console font, so snapshots show readable text.
+//!
+//! All state is global, as on the console, so tests using this backend must
+//! hold the lock returned by [`lock`].
+
+use std::cell::UnsafeCell;
+use std::sync::{Mutex, MutexGuard};
+
+use once_cell::sync::Lazy;
+
+use super::{BLIT_1BPP, BLIT_2BPP, BLIT_FLIP_X, BLIT_FLIP_Y, SCREEN_SIZE};
+
+/// First character of the console font
+const FIRST_CHARACTER: u8 = b' ';
+
+/// Number of characters of the console font, up to `~`
+const CHARACTERS_COUNT: u8 = 95;
+
+/// Console font, as 8x8 1BPP sprites
+///
+/// Glyphs are stored inverted, so the text uses the first draw color and
+/// the background uses the second one.
+#[rustfmt::skip]
+const FONT: [u8; 8 * CHARACTERS_COUNT as usize] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // space
+    0xc7, 0xc7, 0xc7, 0xcf, 0xcf, 0xff, 0xcf, 0xff, // !
+    0x93, 0x93, 0x93, 0xff, 0xff, 0xff, 0xff, 0xff, // "
+    0x93, 0x01, 0x93, 0x93, 0x93, 0x01, 0x93, 0xff, // #
+    0xef, 0x83, 0x2f, 0x83, 0xe9, 0x03, 0xef, 0xff, // $
+    0x9d, 0x5b, 0x37, 0xef, 0xd9, 0xb5, 0x73, 0xff, // %
+    0x8f, 0x27, 0x27, 0x8f, 0x25, 0x33, 0x81, 0xff, // &
+    0xcf, 0xcf, 0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, // '
+    0xf3, 0xe7, 0xcf, 0xcf, 0xcf, 0xe7, 0xf3, 0xff, // (
+    0x9f, 0xcf, 0xe7, 0xe7, 0xe7, 0xcf, 0x9f, 0xff, // )
+    0xff, 0x93, 0xc7, 0x01, 0xc7, 0x93, 0xff, 0xff, // *
+    0xff, 0xe7, 0xe7, 0x81, 0xe7, 0xe7, 0xff, 0xff, // +
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xcf, 0xcf, 0x9f, // ,
+    0xff, 0xff, 0xff, 0x81, 0xff, 0xff, 0xff, 0xff, // -
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xcf, 0xcf, 0xff, // .
+    0xfd, 0xfb, 0xf7, 0xef, 0xdf, 0xbf, 0x7f, 0xff, // /
+    0xc7, 0xb3, 0x39, 0x39, 0x39, 0x9b, 0xc7, 0xff, // 0
+    0xe7, 0xc7, 0xe7, 0xe7, 0xe7, 0xe7, 0x81, 0xff, // 1
+    0x83, 0x39, 0xf1, 0xc3, 0x87, 0x1f, 0x01, 0xff, // 2
+    0x81, 0xf3, 0xe7, 0xc3, 0xf9, 0x39, 0x83, 0xff, // 3
+    0xe3, 0xc3, 0x93, 0x33, 0x01, 0xf3, 0xf3, 0xff, // 4
+    0x03, 0x3f, 0x03, 0xf9, 0xf9, 0x39, 0x83, 0xff, // 5
+    0xc3, 0x9f, 0x3f, 0x03, 0x39, 0x39, 0x83, 0xff, // 6
+    0x01, 0x39, 0xf3, 0xe7, 0xcf, 0xcf, 0xcf, 0xff, // 7
+    0x87, 0x3b, 0x1b, 0x87, 0x61, 0x79, 0x83, 0xff, // 8
+    0x83, 0x39, 0x39, 0x81, 0xf9, 0xf3, 0x87, 0xff, // 9
+    0xff, 0xcf, 0xcf, 0xff, 0xcf, 0xcf, 0xff, 0xff, // :
+    0xff, 0xcf, 0xcf, 0xff, 0xcf, 0xcf, 0x9f, 0xff, // ;
+    0xf3, 0xe7, 0xcf, 0x9f, 0xcf, 0xe7, 0xf3, 0xff, // <
+    0xff, 0xff, 0x01, 0xff, 0x01, 0xff, 0xff, 0xff, // =
+    0x9f, 0xcf, 0xe7, 0xf3, 0xe7, 0xcf, 0x9f, 0xff, // >
+    0x83, 0x01, 0x39, 0xf3, 0xc7, 0xff, 0xc7, 0xff, // ?
+    0x83, 0x7d, 0x45, 0x55, 0x41, 0x7f, 0x83, 0xff, // @
+    0xc7, 0x93, 0x39, 0x39, 0x01, 0x39, 0x39, 0xff, // A
+    0x03, 0x39, 0x39, 0x03, 0x39, 0x39, 0x03, 0xff, // B
+    0xc3, 0x99, 0x3f, 0x3f, 0x3f, 0x99, 0xc3, 0xff, // C
+    0x07, 0x33, 0x39, 0x39, 0x39, 0x33, 0x07, 0xff, // D
+    0x01, 0x3f, 0x3f, 0x03, 0x3f, 0x3f, 0x01, 0xff, // E
+    0x01, 0x3f, 0x3f, 0x03, 0x3f, 0x3f, 0x3f, 0xff, // F
+    0xc1, 0x9f, 0x3f, 0x31, 0x39, 0x99, 0xc1, 0xff, // G
+    0x39, 0x39, 0x39, 0x01, 0x39, 0x39, 0x39, 0xff, // H
+    0x81, 0xe7, 0xe7, 0xe7, 0xe7, 0xe7, 0x81, 0xff, // I
+    0xf9, 0xf9, 0xf9, 0xf9, 0xf9, 0x39, 0x83, 0xff, // J
+    0x39, 0x33, 0x27, 0x0f, 0x07, 0x23, 0x31, 0xff, // K
+    0x9f, 0x9f, 0x9f, 0x9f, 0x9f, 0x9f, 0x81, 0xff, // L
+    0x39, 0x11, 0x01, 0x01, 0x29, 0x39, 0x39, 0xff, // M
+    0x39, 0x19, 0x09, 0x01, 0x21, 0x31, 0x39, 0xff, // N
+    0x83, 0x39, 0x39, 0x39, 0x39, 0x39, 0x83, 0xff, // O
+    0x03, 0x39, 0x39, 0x39, 0x03, 0x3f, 0x3f, 0xff, // P
+    0x83, 0x39, 0x39, 0x39, 0x21, 0x33, 0x85, 0xff, // Q
+    0x03, 0x39, 0x39, 0x31, 0x07, 0x23, 0x31, 0xff, // R
+    0x87, 0x33, 0x3f, 0x83, 0xf9, 0x39, 0x83, 0xff, // S
+    0x81, 0xe7, 0xe7, 0xe7, 0xe7, 0xe7, 0xe7, 0xff, // T
+    0x39, 0x39, 0x39, 0x39, 0x39, 0x39, 0x83, 0xff, // U
+    0x39, 0x39, 0x39, 0x11, 0x83, 0xc7, 0xef, 0xff, // V
+    0x39, 0x39, 0x29, 0x01, 0x01, 0x11, 0x39, 0xff, // W
+    0x39, 0x11, 0x83, 0xc7, 0x83, 0x11, 0x39, 0xff, // X
+    0x99, 0x99, 0x99, 0xc3, 0xe7, 0xe7, 0xe7, 0xff, // Y
+    0x01, 0xf1, 0xe3, 0xc7, 0x8f, 0x1f, 0x01, 0xff, // Z
+    0xc3, 0xcf, 0xcf, 0xcf, 0xcf, 0xcf, 0xc3, 0xff, // [
+    0x7f, 0xbf, 0xdf, 0xef, 0xf7, 0xfb, 0xfd, 0xff, // \
+    0x87, 0xe7, 0xe7, 0xe7, 0xe7, 0xe7, 0x87, 0xff, // ]
+    0xc7, 0x93, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // ^
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0xff, // _
+    0xef, 0xf7, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // `
+    0xff, 0xff, 0x83, 0xf9, 0x81, 0x39, 0x81, 0xff, // a
+    0x3f, 0x3f, 0x03, 0x39, 0x39, 0x39, 0x83, 0xff, // b
+    0xff, 0xff, 0x81, 0x3f, 0x3f, 0x3f, 0x81, 0xff, // c
+    0xf9, 0xf9, 0x81, 0x39, 0x39, 0x39, 0x81, 0xff, // d
+    0xff, 0xff, 0x83, 0x39, 0x01, 0x3f, 0x83, 0xff, // e
+    0xf1, 0xe7, 0x81, 0xe7, 0xe7, 0xe7, 0xe7, 0xff, // f
+    0xff, 0xff, 0x81, 0x39, 0x39, 0x81, 0xf9, 0x83, // g
+    0x3f, 0x3f, 0x03, 0x39, 0x39, 0x39, 0x39, 0xff, // h
+    0xe7, 0xff, 0xc7, 0xe7, 0xe7, 0xe7, 0x81, 0xff, // i
+    0xf3, 0xff, 0xe3, 0xf3, 0xf3, 0xf3, 0xf3, 0x87, // j
+    0x3f, 0x3f, 0x31, 0x03, 0x07, 0x23, 0x31, 0xff, // k
+    0xc7, 0xe7, 0xe7, 0xe7, 0xe7, 0xe7, 0x81, 0xff, // l
+    0xff, 0xff, 0x03, 0x49, 0x49, 0x49, 0x49, 0xff, // m
+    0xff, 0xff, 0x03, 0x39, 0x39, 0x39, 0x39, 0xff, // n
+    0xff, 0xff, 0x83, 0x39, 0x39, 0x39, 0x83, 0xff, // o
+    0xff, 0xff, 0x03, 0x39, 0x39, 0x03, 0x3f, 0x3f, // p
+    0xff, 0xff, 0x81, 0x39, 0x39, 0x81, 0xf9, 0xf9, // q
+    0xff, 0xff, 0x91, 0x8f, 0x9f, 0x9f, 0x9f, 0xff, // r
+    0xff, 0xff, 0x83, 0x3f, 0x83, 0xf9, 0x03, 0xff, // s
+    0xe7, 0xe7, 0x81, 0xe7, 0xe7, 0xe7, 0xe7, 0xff, // t
+    0xff, 0xff, 0x39, 0x39, 0x39, 0x39, 0x81, 0xff, // u
+    0xff, 0xff, 0x99, 0x99, 0x99, 0xc3, 0xe7, 0xff, // v
+    0xff, 0xff, 0x49, 0x49, 0x49, 0x49, 0x81, 0xff, // w
+    0xff, 0xff, 0x39, 0x01, 0xc7, 0x01, 0x39, 0xff, // x
+    0xff, 0xff, 0x39, 0x39, 0x39, 0x81, 0xf9, 0x83, // y
+    0xff, 0xff, 0x01, 0xf3, 0xc7, 0x9f, 0x01, 0xff, // z
+    0xf3, 0xe7, 0xe7, 0xcf, 0xe7, 0xe7, 0xf3, 0xff, // {
+    0xe7, 0xe7, 0xe7, 0xe7, 0xe7, 0xe7, 0xe7, 0xff, // |
+    0x9f, 0xcf, 0xcf, 0xe7, 0xcf, 0xcf, 0x9f, 0xff, // }
+    0xff, 0xff, 0x8f, 0x45, 0xe3, 0xff, 0xff, 0xff, // ~
+];
+
+/// Maximal size of the persistent storage
+const DISK_SIZE: usize = 1024;
+
+/// Default palette of the console
+const DEFAULT_PALETTE: [u32; 4] = [0xe0f8cf, 0x86c06c, 0x306850, 0x071821];
+
+/// Default draw colors of the console
+const DEFAULT_DRAW_COLORS: u16 = 0x1203;
+
+/// Emulated memory of the console
+///
+/// Each register is a cell, so that the pointers to registers in the parent
+/// module can be initialized at compile time.
+pub struct Memory {
+    pub palette: UnsafeCell<[u32; 4]>,
+    pub draw_colors: UnsafeCell<u16>,
+    pub gamepads: [UnsafeCell<u8>; 4],
+    pub mouse_x: UnsafeCell<i16>,
+    pub mouse_y: UnsafeCell<i16>,
+    pub mouse_buttons: UnsafeCell<u8>,
+    pub framebuffer: UnsafeCell<[u8; 6400]>,
+}
+
+// Registers are only accessed through raw pointers, as on the console, and
+// tests serialize their access with the lock
+unsafe impl Sync for Memory {}
+
+pub static MEMORY: Memory = Memory {
+    palette: UnsafeCell::new(DEFAULT_PALETTE),
+    draw_colors: UnsafeCell::new(DEFAULT_DRAW_COLORS),
+    gamepads: [
+        UnsafeCell::new(0),
+        UnsafeCell::new(0),
+        UnsafeCell::new(0),
+        UnsafeCell::new(0),
+    ],
+    mouse_x: UnsafeCell::new(0),
+    mouse_y: UnsafeCell::new(0),
+    mouse_buttons: UnsafeCell::new(0),
+    framebuffer: UnsafeCell::new([0; 6400]),
+};
+
+static mut DISK: Vec<u8> = Vec::new();
+
+static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Acquire exclusive access to the console
+///
+/// The console is reset to its initial state, with an empty persistent
+/// storage.
+pub fn lock() -> MutexGuard<'static, ()> {
+    let guard = LOCK.lock().unwrap_or_else(|error| error.into_inner());
+    reset();
+    guard
+}
+
+/// Reset the console to its initial state
+pub fn reset() {
+    unsafe {
+        *MEMORY.palette.get() = DEFAULT_PALETTE;
+        *MEMORY.draw_colors.get() = DEFAULT_DRAW_COLORS;
+        for gamepad in MEMORY.gamepads.iter() {
+            *gamepad.get() = 0;
+        }
+        *MEMORY.mouse_x.get() = 0;
+        *MEMORY.mouse_y.get() = 0;
+        *MEMORY.mouse_buttons.get() = 0;
+        *MEMORY.framebuffer.get() = [0; 6400];
+        (*core::ptr::addr_of_mut!(DISK)).clear();
+    }
+}
+
+/// Clear the framebuffer, as the console does before each frame
+pub fn clear_framebuffer() {
+    unsafe { *super::FRAMEBUFFER = [0; 6400] }
+}
+
+/// Return the palette index of a pixel in the framebuffer
+pub fn pixel(x: u32, y: u32) -> u8 {
+    let index = (y * SCREEN_SIZE + x) as usize;
+    let byte = unsafe { (*super::FRAMEBUFFER)[index / 4] };
+    (byte >> ((index % 4) * 2)) & 0b11
+}
+
+/// Return the current palette
+pub fn palette() -> [u32; 4] {
+    unsafe { *super::PALETTE }
+}
+
+/// Set the mouse registers
+pub fn set_mouse(x: i16, y: i16, buttons: u8) {
+    unsafe {
+        *(super::MOUSE_X as *mut i16) = x;
+        *(super::MOUSE_Y as *mut i16) = y;
+        *(super::MOUSE_BUTTONS as *mut u8) = buttons;
+    }
+}
+
+/// Set the first gamepad register
+pub fn set_gamepad(buttons: u8) {
+    unsafe { *(super::GAMEPAD1 as *mut u8) = buttons }
+}
+
+/// Return the content of the persistent storage
+pub fn disk() -> Vec<u8> {
+    unsafe { (*core::ptr::addr_of!(DISK)).clone() }
+}
+
+/// Replace the content of the persistent storage
+pub fn set_disk(content: &[u8]) {
+    let length = core::cmp::min(content.len(), DISK_SIZE);
+    unsafe { *core::ptr::addr_of_mut!(DISK) = content[..length].to_vec() }
+}
+
+/// Copy pixels to the framebuffer.
+pub fn blit(sprite: &[u8], x: i32, y: i32, width: u32, height: u32, flags: u32) {
+    blit_sub(sprite, x, y, width, height, 0, 0, width, flags);
+}
+
+/// Copy a subregion within a larger sprite atlas to the framebuffer.
+///
+/// Rotation is not supported.
+#[allow(clippy::too_many_arguments)]
+pub fn blit_sub(
+    sprite: &[u8],
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    src_x: u32,
+    src_y: u32,
+    stride: u32,
+    flags: u32,
+) {
+    for row in 0..height {
+        for column in 0..width {
+            let sx = if flags & BLIT_FLIP_X != 0 {
+                src_x + width - 1 - column
+            } else {
+                src_x + column
+            };
+            let sy = if flags & BLIT_FLIP_Y != 0 {
+                src_y + height - 1 - row
+            } else {
+                src_y + row
+            };
+            let index = (sy * stride + sx) as usize;
+            let value = if flags & BLIT_2BPP != 0 {
+                (sprite[index / 4] >> (6 - 2 * (index % 4))) & 0b11
+            } else {
+                (sprite[index / 8] >> (7 - index % 8)) & 0b1
+            };
+            if let Some(color) = draw_color(value) {
+                set_pixel(x + column as i32, y + row as i32, color);
+            }
+        }
+    }
+}
+
+/// Draw a line between two points.
+pub fn line(x1: i32, y1: i32, x2: i32, y2: i32) {
+    let color = match draw_color(0) {
+        Some(color) => color,
+        None => return,
+    };
+
+    let (dx, dy) = ((x2 - x1).abs(), -(y2 - y1).abs());
+    let (step_x, step_y) = ((x2 - x1).signum(), (y2 - y1).signum());
+    let (mut x, mut y, mut error) = (x1, y1, dx + dy);
+    loop {
+        set_pixel(x, y, color);
+        if x == x2 && y == y2 {
+            break;
+        }
+        let doubled_error = 2 * error;
+        if doubled_error >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled_error <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+/// Draw an oval (or circle).
+pub fn oval(x: i32, y: i32, width: u32, height: u32) {
+    let (a, b) = (width as f32 / 2.0, height as f32 / 2.0);
+    let inside = |px: f32, py: f32| {
+        let (nx, ny) = ((px - a) / a, (py - b) / b);
+        nx * nx + ny * ny <= 1.0
+    };
+    for row in 0..height as i32 {
+        for column in 0..width as i32 {
+            let (px, py) = (column as f32 + 0.5, row as f32 + 0.5);
+            if !inside(px, py) {
+                continue;
+            }
+            let is_border = !inside(px - 1.0, py)
+                || !inside(px + 1.0, py)
+                || !inside(px, py - 1.0)
+                || !inside(px, py + 1.0);
+            let color = if is_border {
+                draw_color(1).or_else(|| draw_color(0))
+            } else {
+                draw_color(0)
+            };
+            if let Some(color) = color {
+                set_pixel(x + column, y + row, color);
+            }
+        }
+    }
+}
+
+/// Draw a rectangle.
+pub fn rect(x: i32, y: i32, width: u32, height: u32) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    if let Some(color) = draw_color(0) {
+        for row in 0..height as i32 {
+            for column in 0..width as i32 {
+                set_pixel(x + column, y + row, color);
+            }
+        }
+    }
+    if let Some(color) = draw_color(1) {
+        let (right, bottom) = (x + width as i32 - 1, y + height as i32 - 1);
+        for column in x..=right {
+            set_pixel(column, y, color);
+            set_pixel(column, bottom, color);
+        }
+        for row in y..=bottom {
+            set_pixel(x, row, color);
+            set_pixel(right, row, color);
+        }
+    }
+}
+
+/// Draw text using the built-in system font.
+///
+/// As on the console, each byte is a character, and line feeds start a new
+/// line.
+/// Only printable ASCII characters are available, others are left blank.
+pub fn text<T: AsRef<str>>(text: T, x: i32, y: i32) {
+    let (mut cx, mut cy) = (x, y);
+    for c in text.as_ref().bytes() {
+        if c == b'\n' {
+            cx = x;
+            cy += 8;
+            continue;
+        }
+        if (FIRST_CHARACTER..FIRST_CHARACTER + CHARACTERS_COUNT).contains(&c) {
+            let offset = (c - FIRST_CHARACTER) as usize * 8;
+            blit(&FONT[offset..offset + 8], cx, cy, 8, 8, BLIT_1BPP);
+        }
+        cx += 8;
+    }
+}
+
+/// Draw a vertical line
+pub fn vline(x: i32, y: i32, len: u32) {
+    if let Some(color) = draw_color(0) {
+        for row in 0..len as i32 {
+            set_pixel(x, y + row, color);
+        }
+    }
+}
+
+/// Draw a horizontal line
+pub fn hline(x: i32, y: i32, len: u32) {
+    if let Some(color) = draw_color(0) {
+        for column in 0..len as i32 {
+            set_pixel(x + column, y, color);
+        }
+    }
+}
+
+/// Play a sound tone.
+///
+/// Sound is not emulated.
+pub fn tone(_frequency: u32, _duration: u32, _volume: u32, _flags: u32) {}
+
+/// Read up to `size` bytes from persistent storage into the pointer `dest`.
+///
+/// # Safety
+///
+/// `dest` must be valid for writing `size` bytes.
+pub unsafe fn diskr(dest: *mut u8, size: u32) -> u32 {
+    let disk = &*core::ptr::addr_of!(DISK);
+    let length = core::cmp::min(size as usize, disk.len());
+    core::ptr::copy_nonoverlapping(disk.as_ptr(), dest, length);
+    length as u32
+}
+
+/// Write up to `size` bytes from the pointer `src` into persistent storage.
+///
+/// # Safety
+///
+/// `src` must be valid for reading `size` bytes.
+pub unsafe fn diskw(src: *const u8, size: u32) -> u32 {
+    let length = core::cmp::min(size as usize, DISK_SIZE);
+    set_disk(core::slice::from_raw_parts(src, length));
+    length as u32
+}
+
+/// Print a message to the standard output.
+pub fn trace<T: AsRef<str>>(text: T) {
+    println!("{}", text.as_ref());
+}
+
+/// Return the palette index for a sprite value, if it is not transparent
+fn draw_color(value: u8) -> Option<u8> {
+    let draw_colors = unsafe { *super::DRAW_COLORS };
+    let color = (draw_colors >> (4 * value as u16)) & 0xf;
+    if color == 0 {
+        None
+    } else {
+        Some(((color - 1) & 0b11) as u8)
+    }
+}
+
+fn set_pixel(x: i32, y: i32, color: u8) {
+    if x < 0 || y < 0 || x >= SCREEN_SIZE as i32 || y >= SCREEN_SIZE as i32 {
+        return;
+    }
+    let index = (y as u32 * SCREEN_SIZE + x as u32) as usize;
+    let shift = (index % 4) * 2;
+    unsafe {
+        let byte = &mut (*super::FRAMEBUFFER)[index / 4];
+        *byte = (*byte & !(0b11 << shift)) | (color << shift);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::wasm4::DRAW_COLORS;
+
+    fn set_draw_colors(draw_colors: u16) {
+        unsafe { *DRAW_COLORS = draw_colors }
+    }
+
+    #[test]
+    fn rect_has_fill_and_outline() {
+        let _lock = lock();
+        set_draw_colors(0x42);
+        rect(10, 10, 4, 3);
+        assert_eq!(pixel(10, 10), 3);
+        assert_eq!(pixel(13, 12), 3);
+        assert_eq!(pixel(11, 11), 1);
+        assert_eq!(pixel(14, 11), 0);
+    }
+
+    #[test]
+    fn transparent_color_is_not_drawn() {
+        let _lock = lock();
+        set_draw_colors(0x4);
+        rect(0, 0, 3, 3);
+        set_draw_colors(0x20);
+        rect(0, 0, 3, 3);
+        assert_eq!(pixel(0, 0), 1);
+        assert_eq!(pixel(1, 1), 3);
+    }
+
+    #[test]
+    fn blit_maps_values_to_draw_colors() {
+        let _lock = lock();
+        // Values 0, 1, 2, 3 in the first row, 3, 2, 1, 0 in the second row
+        let sprite = [0b0001_1011, 0b1110_0100];
+        set_draw_colors(0x4320);
+        blit(&sprite, 0, 0, 4, 2, BLIT_2BPP);
+        assert_eq!(pixel(0, 0), 0);
+        assert_eq!(pixel(1, 0), 1);
+        assert_eq!(pixel(2, 0), 2);
+        assert_eq!(pixel(3, 0), 3);
+        assert_eq!(pixel(0, 1), 3);
+
+        clear_framebuffer();
+        set_draw_colors(0x40);
+        blit(&[0b1000_0000], 0, 0, 8, 1, BLIT_1BPP | BLIT_FLIP_X);
+        assert_eq!(pixel(7, 0), 3);
+        assert_eq!(pixel(0, 0), 0);
+    }
+
+    #[test]
+    fn text_uses_console_font() {
+        let _lock = lock();
+        set_draw_colors(0x4);
+        text("!\n!", 0, 0);
+        assert_eq!(pixel(0, 0), 0);
+        assert_eq!(pixel(2, 0), 3);
+        assert_eq!(pixel(2, 5), 0);
+        assert_eq!(pixel(2, 6), 3);
+        assert_eq!(pixel(2, 8), 3);
+        assert_eq!(pixel(10, 0), 0);
+    }
+
+    #[test]
+    fn drawing_outside_screen_is_clipped() {
+        let _lock = lock();
+        set_draw_colors(0x2);
+        rect(-5, 158, 10, 10);
+        assert_eq!(pixel(0, 159), 1);
+        assert_eq!(pixel(4, 159), 1);
+        assert_eq!(pixel(5, 159), 0);
+    }
+
+    #[test]
+    fn disk_round_trip() {
+        let _lock = lock();
+        let data = [1, 2, 3];
+        let mut buffer = [0; 8];
+        unsafe {
+            assert_eq!(diskr(buffer.as_mut_ptr(), 8), 0);
+            assert_eq!(diskw(data.as_ptr(), 3), 3);
+            assert_eq!(diskr(buffer.as_mut_ptr(), 8), 3);
+        }
+        assert_eq!(&buffer[..3], &data);
+        assert_eq!(disk(), data.to_vec());
+    }
+}